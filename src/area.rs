@@ -0,0 +1,93 @@
+//! Estimates the area of the set inside a region by sampling points and counting those that
+//! don't escape. The samples follow the (2, 3) Halton sequence rather than being random, so they
+//! cover the region evenly and the estimate can keep being refined where the last batch stopped.
+//!
+//! Points that merely haven't escaped yet count as inside, so the estimate is too large at low
+//! iteration limits. The whole set has an area of about 1.5066.
+
+/// A running count of the samples of a region that lie inside the set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AreaEstimate {
+    pub samples: u64,
+    pub inside: u64,
+}
+
+impl AreaEstimate {
+    /// Takes the next `count` samples of the unit square, which `is_inside` maps into the region
+    /// and classifies.
+    pub fn sample(&mut self, count: u64, mut is_inside: impl FnMut([f64; 2]) -> bool) {
+        for i in self.samples..self.samples + count {
+            // Index 0 is the corner of the square in every base, skip it.
+            if is_inside([halton(i + 1, 2), halton(i + 1, 3)]) {
+                self.inside += 1;
+            }
+        }
+        self.samples += count;
+    }
+
+    /// The fraction of samples inside the set.
+    pub fn fraction(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.inside as f64 / self.samples as f64
+    }
+
+    /// The estimated area of the set within a region of `region_area`.
+    pub fn area(&self, region_area: f64) -> f64 {
+        self.fraction() * region_area
+    }
+
+    /// The standard error of [`Self::area`] if the samples were independent. Halton samples
+    /// usually do better than that, so it's a conservative bound.
+    pub fn standard_error(&self, region_area: f64) -> f64 {
+        if self.samples == 0 {
+            return region_area;
+        }
+        let p = self.fraction();
+        (p * (1.0 - p) / self.samples as f64).sqrt() * region_area
+    }
+}
+
+/// The `i`th element of the van der Corput sequence in `base`, the radical inverse of `i`.
+pub fn halton(mut i: u64, base: u64) -> f64 {
+    let mut result = 0.0;
+    let mut scale = 1.0;
+    while i > 0 {
+        scale /= base as f64;
+        result += scale * (i % base) as f64;
+        i /= base;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_the_area_of_the_whole_set() {
+        // Contains the whole set.
+        let (min, size) = ([-2.0, -1.25], [2.5, 2.5]);
+        let mut estimate = AreaEstimate::default();
+        for _ in 0..10 {
+            estimate.sample(10_000, |[u, v]| {
+                let c = [min[0] + u * size[0], min[1] + v * size[1]];
+                crate::core::escape(c, 2000).is_none()
+            });
+        }
+        assert_eq!(estimate.samples, 100_000);
+        let area = estimate.area(size[0] * size[1]);
+        assert!((area - 1.5066).abs() < 0.02, "{area}");
+        assert!(estimate.standard_error(size[0] * size[1]) < 0.02);
+    }
+
+    #[test]
+    fn halton_fills_the_unit_interval() {
+        assert_eq!(
+            (1..8).map(|i| halton(i, 2)).collect::<Vec<_>>(),
+            [0.5, 0.25, 0.75, 0.125, 0.625, 0.375, 0.875]
+        );
+        assert!((halton(5, 3) - 7.0 / 9.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,77 @@
+//! Rendering many images in one go from a JSON job file, see `--batch`.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    headless::{Headless, ImageFormat},
+    manifest::Manifest,
+    MandelbrotUniform,
+};
+
+/// One image to render. A job file holds a JSON array of these.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BatchJob {
+    /// Where to save the image, in the format its extension names.
+    pub out: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// The view and coloring to render, in the same form as screenshot manifests.
+    pub view: Manifest,
+}
+
+impl BatchJob {
+    /// The uniform rendering this job, centered on the view's center at the job's aspect ratio.
+    pub fn uniform(&self) -> MandelbrotUniform {
+        let mut uniform = self.view.to_uniform();
+        let center = uniform.center();
+        uniform.aspect_ratio = self.width as f64 / self.height as f64;
+        uniform.set_center(center);
+        uniform
+    }
+
+    async fn render(
+        &self,
+        headless: &Headless,
+        write_manifest: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.width == 0 || self.height == 0 {
+            return Err("the image size has to be nonzero".into());
+        }
+        let extension = self.out.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let format = ImageFormat::from_extension(extension)
+            .ok_or_else(|| format!("unknown image format {extension:?}"))?;
+        let uniform = self.uniform();
+        headless
+            .save_image(&uniform, self.width, self.height, format, &self.out)
+            .await?;
+        if write_manifest {
+            Manifest::new(&uniform).write(self.out.with_extension("json"))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<BatchJob>, Box<dyn Error>> {
+    Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+}
+
+/// Renders the jobs one after another, reporting progress on stdout. A failed job is reported
+/// on stderr and doesn't stop the others. Returns the number of jobs that failed.
+pub async fn run(headless: &Headless, jobs: &[BatchJob], write_manifest: bool) -> usize {
+    let mut failed = 0;
+    for (i, job) in jobs.iter().enumerate() {
+        println!("[{}/{}] {}", i + 1, jobs.len(), job.out.display());
+        if let Err(e) = job.render(headless, write_manifest).await {
+            eprintln!("failed to render {}: {e}", job.out.display());
+            failed += 1;
+        }
+    }
+    failed
+}
@@ -0,0 +1,193 @@
+//! Frame time statistics for `--benchmark`, which renders a fixed number of frames as fast as
+//! presenting allows and reports how long they took, and for the frame time shown in the title.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Collects the times of a fixed number of frames.
+pub struct Recorder {
+    frames: usize,
+    frame_times: Vec<Duration>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn new(frames: usize) -> Self {
+        Self {
+            frames,
+            frame_times: Vec::with_capacity(frames),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.frame_times.is_empty() {
+            // Don't count the time it took to get to the first frame.
+            self.start = Instant::now() - frame_time;
+        }
+        self.frame_times.push(frame_time);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frame_times.len() >= self.frames
+    }
+
+    pub fn frame_times(&self) -> &[Duration] {
+        &self.frame_times
+    }
+
+    /// Statistics of the frames recorded so far, `None` if there weren't any.
+    pub fn stats(&self) -> Option<FrameStats> {
+        FrameStats::new(&self.frame_times, self.start.elapsed())
+    }
+}
+
+/// Summary of a run of frame times.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    pub frames: usize,
+    /// Wall clock time from the start of the first frame to the end of the last.
+    pub elapsed: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl FrameStats {
+    /// `None` if there are no frame times.
+    pub fn new(frame_times: &[Duration], elapsed: Duration) -> Option<Self> {
+        if frame_times.is_empty() {
+            return None;
+        }
+        let mut sorted = frame_times.to_vec();
+        sorted.sort_unstable();
+        // Nearest rank, so every percentile is a frame time that actually occurred.
+        let percentile = |p: usize| sorted[(sorted.len() * p).div_ceil(100).max(1) - 1];
+        Some(Self {
+            frames: sorted.len(),
+            elapsed,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+        })
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} frames in {:.2?}", self.frames, self.elapsed)?;
+        writeln!(
+            f,
+            "min {:.2?}, mean {:.2?}, max {:.2?}",
+            self.min, self.mean, self.max
+        )?;
+        write!(
+            f,
+            "p50 {:.2?}, p95 {:.2?}, p99 {:.2?}",
+            self.p50, self.p95, self.p99
+        )
+    }
+}
+
+/// Rolling average of the last [`FrameMeter::FRAMES`] frame times, and how many frames ended
+/// within the last second.
+#[derive(Clone, Debug, Default)]
+pub struct FrameMeter {
+    // When each frame ended and how long it took, oldest first. Holds the last second's worth,
+    // but at least `FRAMES`.
+    frames: VecDeque<(Instant, Duration)>,
+}
+
+impl FrameMeter {
+    pub const FRAMES: usize = 60;
+
+    pub fn record(&mut self, end: Instant, frame_time: Duration) {
+        self.frames.push_back((end, frame_time));
+        while self.frames.len() > Self::FRAMES && self.frames[0].0 + Duration::from_secs(1) <= end {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Mean of the recorded frame times, `None` before the first frame.
+    pub fn average(&self) -> Option<Duration> {
+        let count = self.frames.len().min(Self::FRAMES);
+        let sum: Duration = self.frames.iter().rev().take(count).map(|&(_, t)| t).sum();
+        (count > 0).then(|| sum / count as u32)
+    }
+
+    /// Frames that ended in the second before `now`, zero while the window sits idle.
+    pub fn rate(&self, now: Instant) -> usize {
+        let since = now.checked_sub(Duration::from_secs(1));
+        self.frames
+            .iter()
+            .filter(|&&(end, _)| end <= now && since.is_none_or(|since| end > since))
+            .count()
+    }
+
+    /// When [`rate`](Self::rate) drops to zero without further frames.
+    pub fn idle_at(&self) -> Option<Instant> {
+        let &(end, _) = self.frames.back()?;
+        Some(end + Duration::from_secs(1))
+    }
+}
+
+/// Writes one `frame,milliseconds` row per frame time, after a header.
+pub fn write_csv(path: impl AsRef<Path>, frame_times: &[Duration]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "frame,milliseconds")?;
+    for (i, time) in frame_times.iter().enumerate() {
+        writeln!(file, "{i},{}", time.as_secs_f64() * 1e3)?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let times: Vec<_> = (1..=100).rev().map(Duration::from_millis).collect();
+        let stats = FrameStats::new(&times, Duration::from_secs(5)).unwrap();
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(100));
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p95, Duration::from_millis(95));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.mean, Duration::from_micros(50500));
+
+        let single = FrameStats::new(&[Duration::from_millis(7)], Duration::ZERO).unwrap();
+        assert_eq!(single.p50, Duration::from_millis(7));
+        assert_eq!(single.p99, Duration::from_millis(7));
+        assert!(FrameStats::new(&[], Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn frame_meter_forgets_old_frames() {
+        let start = Instant::now();
+        let mut meter = FrameMeter::default();
+        assert_eq!(meter.average(), None);
+        for i in 0..300 {
+            let time = Duration::from_millis(if i < 240 { 100 } else { 5 });
+            meter.record(start + Duration::from_millis(i * 5), time);
+        }
+        assert_eq!(meter.average(), Some(Duration::from_millis(5)));
+        // More frames than the average covers.
+        let last = start + Duration::from_millis(299 * 5);
+        assert_eq!(meter.rate(last), 200);
+        assert_eq!(meter.idle_at(), Some(last + Duration::from_secs(1)));
+        assert_eq!(meter.rate(last + Duration::from_secs(1)), 0);
+    }
+}
@@ -0,0 +1,126 @@
+//! Configuring the explorer from code rather than the command line.
+
+use crate::{
+    cli,
+    gui::{self, FrameCallback, FrameInfo, StateError},
+    uniform::FractalKind,
+};
+
+/// Builds and runs the interactive explorer with options set from code, for embedding it.
+/// [`crate::run`] does the same with the options given on the command line.
+///
+/// Unlike [`crate::run`], this doesn't set up logging, leaving that to the embedder, and returns
+/// errors instead of exiting.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), mandelbrot_gpu::StateError> {
+/// mandelbrot_gpu::ExplorerBuilder::new()
+///     .with_size(1280, 720)
+///     .with_iterations(1024)
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ExplorerBuilder {
+    args: cli::Args,
+    on_frame: Option<FrameCallback>,
+}
+
+impl ExplorerBuilder {
+    /// An explorer with every option at its default.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn from_args(args: cli::Args) -> Self {
+        Self {
+            args,
+            on_frame: None,
+        }
+    }
+
+    /// Inner size of the window in physical pixels. Defaults to 800x600.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.args.window_size = Some([width, height]);
+        self
+    }
+
+    /// Iteration limit to start with. Defaults to that of
+    /// [`MandelbrotUniform::default_view`](crate::MandelbrotUniform::default_view), 128.
+    pub fn with_iterations(mut self, max_iterations: u32) -> Self {
+        self.args.max_iterations = Some(max_iterations);
+        self
+    }
+
+    /// Fractal to start with. Defaults to the Mandelbrot set; Julia sets and the Burning Ship
+    /// start out framed whole.
+    pub fn with_fractal(mut self, fractal: FractalKind) -> Self {
+        self.args.fractal = fractal;
+        self
+    }
+
+    /// How frames are presented. Falls back to the surface's preferred mode, which is also the
+    /// default, if the surface doesn't support it.
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.args.present_mode = Some(present_mode);
+        self
+    }
+
+    /// Graphics APIs to pick an adapter from. Defaults to those in the `WGPU_BACKEND`
+    /// environment variable, or all of them.
+    pub fn with_backend(mut self, backends: wgpu::Backends) -> Self {
+        self.args.backends = Some(backends);
+        self
+    }
+
+    /// Calls `on_frame` after every presented frame. There's no callback by default.
+    pub fn with_frame_callback(mut self, on_frame: impl FnMut(&FrameInfo) + 'static) -> Self {
+        self.on_frame = Some(Box::new(on_frame));
+        self
+    }
+
+    /// Opens the window and runs the explorer until it's closed. Fails if the window can't be
+    /// opened, there's no GPU to draw with, or it lacks the features the explorer needs.
+    pub async fn run(self) -> Result<(), StateError> {
+        gui::run_with_args(self.args, self.on_frame).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MandelbrotUniform;
+
+    #[test]
+    fn options_reach_the_arguments() {
+        let builder = ExplorerBuilder::new()
+            .with_size(640, 480)
+            .with_iterations(1000)
+            .with_fractal(FractalKind::Julia([-0.8, 0.156]))
+            .with_present_mode(wgpu::PresentMode::Mailbox)
+            .with_backend(wgpu::Backends::VULKAN);
+        let args = &builder.args;
+        assert_eq!(args.window_size, Some([640, 480]));
+        assert_eq!(args.present_mode, Some(wgpu::PresentMode::Mailbox));
+        assert_eq!(args.backends(), wgpu::Backends::VULKAN);
+
+        let view = args.start_view(4.0 / 3.0);
+        assert_eq!(view.max_iterations, 1000);
+        assert!(view.flag(MandelbrotUniform::FLAG_JULIA));
+        assert_eq!(view.julia_c, [-0.8, 0.156]);
+        assert_eq!(view.center(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn defaults_start_with_the_whole_set() {
+        let args = ExplorerBuilder::new().args;
+        assert_eq!((args.window_size, args.present_mode), (None, None));
+        let view = args.start_view(1.5);
+        let default = MandelbrotUniform::default_view(1.5);
+        assert!(!view.flag(MandelbrotUniform::FLAG_JULIA));
+        assert_eq!(view.max_iterations, default.max_iterations);
+        assert_eq!(view.center(), default.center());
+    }
+}
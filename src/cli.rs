@@ -0,0 +1,494 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    core::Precision,
+    headless::ImageFormat,
+    location::Location,
+    uniform::{FractalKind, AA_PATTERN_NAMES, BURNING_SHIP_VIEW, JULIA_VIEW},
+    MandelbrotUniform,
+};
+
+/// Command line options understood by [`crate::run`].
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Config file to read instead of [`crate::config::CONFIG_FILE`].
+    pub config: Option<PathBuf>,
+    /// Palette saved as text (see [`crate::palette::to_text`]), taking precedence over
+    /// `palette_image`.
+    pub palette: Option<PathBuf>,
+    /// Image whose top row is used as the color palette.
+    pub palette_image: Option<PathBuf>,
+    /// Render to an extended range surface if the display supports it.
+    pub hdr: bool,
+    /// Seed of a randomly generated palette to use. Without an explicit seed it's taken from
+    /// `seed`.
+    pub random_palette: Option<u64>,
+    /// Makes everything random reproducible: palettes picked without a seed, and those the
+    /// random palette key cycles through, are derived from it instead of the time. Supersampling
+    /// jitter doesn't need it, it only depends on the pixel.
+    pub seed: Option<u64>,
+    /// Don't write a JSON manifest next to screenshots.
+    pub no_manifest: bool,
+    /// Region to show at startup, in any notation [`crate::location::parse`] understands, or
+    /// read from a Kalles Fraktaler file with `--kfr`, which also sets `max_iterations` unless
+    /// it was given first.
+    pub location: Option<Location>,
+    /// Fractal to start with. Julia sets and the Burning Ship are framed whole unless `location`
+    /// is given.
+    pub fractal: FractalKind,
+    /// Center of the view at startup, overriding that of `location`.
+    pub center_x: Option<f64>,
+    pub center_y: Option<f64>,
+    /// Magnification relative to the default view at startup, overriding that of `location`.
+    pub zoom: Option<f64>,
+    /// Render a single image to this path without opening a window, then exit.
+    pub out: Option<PathBuf>,
+    /// Save a 16-bit heightmap of the distance to the set in the start view to this path
+    /// without opening a window, then exit.
+    pub heightmap: Option<PathBuf>,
+    /// Render the start view at the size of this image and compare the two, exiting with
+    /// failure if they differ by more than `max_error`.
+    pub compare_to: Option<PathBuf>,
+    /// Largest mean absolute error `compare_to` accepts, instead of
+    /// [`crate::headless::MAX_GOLDEN_ERROR`].
+    pub max_error: Option<f64>,
+    /// Compare `f64` and double-double escape times (see [`crate::precision_check`]), report the
+    /// result and exit, with failure if they don't match as expected.
+    pub verify_precision: bool,
+    /// Save the contour lines of the start view's escape time as an SVG to this path without
+    /// opening a window, then exit.
+    pub svg: Option<PathBuf>,
+    /// Escape time between contour lines, both those drawn over the view and those of `svg`.
+    pub contour_interval: Option<f32>,
+    /// Leave the interior of the image rendered with `out` transparent, saving PNGs with an
+    /// alpha channel.
+    pub transparent_interior: bool,
+    /// Render the PNG of `out` in strips of this many rows, streaming them to the file, for
+    /// images too large to render or hold at once.
+    pub strip_height: Option<u32>,
+    /// Width and height of the image rendered with `out`, `heightmap` or `svg`, or of the views
+    /// compared by `verify_precision`.
+    pub size: Option<[u32; 2]>,
+    /// Render the images described by this job file (see [`crate::batch`]), then exit.
+    pub batch: Option<PathBuf>,
+    /// Format screenshots are saved in.
+    pub format: ImageFormat,
+    /// Linear RGB color of points inside the set.
+    pub interior_color: Option<[f32; 3]>,
+    /// Random samples averaged per pixel.
+    pub samples_per_pixel: Option<u32>,
+    /// Supersampling pattern of adaptive anti-aliasing, an index into
+    /// [`crate::uniform::AA_PATTERN_NAMES`].
+    pub aa_pattern: u32,
+    /// WGSL shader to draw the main view with, see [`crate::custom_shader`].
+    pub shader: Option<PathBuf>,
+    /// Factor the +/- keys zoom by, at least 2.
+    pub zoom_step: Option<u32>,
+    /// Render this many frames back to back, report their timing and exit.
+    pub benchmark: Option<usize>,
+    /// Also write every frame time of `benchmark` to this CSV file.
+    pub benchmark_csv: Option<PathBuf>,
+    /// Inner size of the window in physical pixels.
+    pub window_size: Option<[u32; 2]>,
+    /// Precision to render in regardless of how deep the view is, instead of switching to
+    /// double-double once it's needed.
+    pub precision: Option<Precision>,
+    /// Iteration limit at startup, instead of the default view's.
+    pub max_iterations: Option<u32>,
+    /// Radius orbits escape at, at least 2, see [`MandelbrotUniform::escape_radius`].
+    pub escape_radius: Option<f32>,
+    /// Keep adjusting the iteration limit so rendering a frame takes about as long as this
+    /// frame rate allows.
+    pub target_fps: Option<f64>,
+    /// How frames are presented, if the surface supports it.
+    pub present_mode: Option<wgpu::PresentMode>,
+    /// Graphics APIs to pick an adapter from, overriding the `WGPU_BACKEND` environment variable.
+    pub backends: Option<wgpu::Backends>,
+    /// Stop panning immediately when a drag is released instead of gliding.
+    pub no_inertia: bool,
+    /// Start zooming in on its own after this long without input.
+    pub idle_delay: Option<Duration>,
+    /// Where to zoom in to when idle, instead of the default location.
+    pub idle_target: Option<Location>,
+}
+
+impl Args {
+    /// Parses the process's arguments, exiting with failure if one that decides what's rendered
+    /// is malformed.
+    pub fn parse() -> Self {
+        Self::parse_from(std::env::args().skip(1)).unwrap_or_else(|e| {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        })
+    }
+
+    /// Parses `args`. Malformed values of the view and output options are errors, as a script
+    /// would otherwise silently get an image of the wrong place or size; the others are
+    /// ignored with a warning.
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().peekable();
+        let mut unseeded_palette = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--palette" => match args.next() {
+                    Some(path) => parsed.palette = Some(path.into()),
+                    None => log::warn!("--palette expects a path"),
+                },
+                "--config" => match args.next() {
+                    Some(path) => parsed.config = Some(path.into()),
+                    None => log::warn!("--config expects a path"),
+                },
+                "--palette-image" => match args.next() {
+                    Some(path) => parsed.palette_image = Some(path.into()),
+                    None => log::warn!("--palette-image expects a path"),
+                },
+                "--hdr" => parsed.hdr = true,
+                "--center-x" => match args.next().map(|x| x.parse()) {
+                    Some(Ok(x)) => parsed.center_x = Some(x),
+                    _ => return Err("--center-x expects a number".into()),
+                },
+                "--center-y" => match args.next().map(|y| y.parse()) {
+                    Some(Ok(y)) => parsed.center_y = Some(y),
+                    _ => return Err("--center-y expects a number".into()),
+                },
+                "--zoom" => match args.next().map(|zoom| zoom.parse()) {
+                    Some(Ok(zoom)) if zoom > 0.0 => parsed.zoom = Some(zoom),
+                    _ => return Err("--zoom expects a positive number".into()),
+                },
+                "--batch" => match args.next() {
+                    Some(path) => parsed.batch = Some(path.into()),
+                    None => log::warn!("--batch expects a path"),
+                },
+                "--out" => match args.next() {
+                    Some(path) => parsed.out = Some(path.into()),
+                    None => return Err("--out expects a path".into()),
+                },
+                "--export-heightmap" => match args.next() {
+                    Some(path) => parsed.heightmap = Some(path.into()),
+                    None => log::warn!("--export-heightmap expects a path"),
+                },
+                "--export-svg" => match args.next() {
+                    Some(path) => parsed.svg = Some(path.into()),
+                    None => log::warn!("--export-svg expects a path"),
+                },
+                "--transparent-interior" => parsed.transparent_interior = true,
+                "--escape-radius" => match args.next().map(|r| r.parse()) {
+                    Some(Ok(r)) if r >= 2.0 => parsed.escape_radius = Some(r),
+                    _ => return Err("--escape-radius expects a number of at least 2".into()),
+                },
+                "--contour-interval" => match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) if n > 0.0 => parsed.contour_interval = Some(n),
+                    _ => log::warn!("--contour-interval expects a positive number"),
+                },
+                "--size" => match args.next().as_deref().and_then(parse_size) {
+                    Some(size) => parsed.size = Some(size),
+                    None => return Err("--size expects WIDTHxHEIGHT".into()),
+                },
+                "--location" => match args.next().as_deref().map(crate::location::parse) {
+                    Some(Ok(location)) => {
+                        log::info!("parsed location as {}", location.notation);
+                        parsed.location = Some(location);
+                    }
+                    Some(Err(e)) => return Err(format!("invalid --location: {e}")),
+                    None => return Err("--location expects a location".into()),
+                },
+                "--kfr" => match args.next() {
+                    Some(path) => match std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| {
+                            crate::location::parse_kfr(&text).map_err(|e| e.to_string())
+                        }) {
+                        Ok(kfr) => {
+                            if kfr.truncated {
+                                log::warn!(
+                                    "{path} is more precise than f64, showing the nearest location"
+                                );
+                            }
+                            parsed.location = Some(kfr.location);
+                            if let Some(iterations) = kfr.iterations.filter(|&n| n > 0) {
+                                parsed.max_iterations.get_or_insert(iterations);
+                            }
+                        }
+                        Err(e) => log::warn!("failed to read {path}: {e}"),
+                    },
+                    None => log::warn!("--kfr expects a path"),
+                },
+                "--strip-height" => match args.next().map(|n| n.parse()) {
+                    Some(Ok(rows)) if rows > 0 => parsed.strip_height = Some(rows),
+                    _ => log::warn!("--strip-height expects a positive number of rows"),
+                },
+                "--format" => match args.next().as_deref().map(ImageFormat::from_extension) {
+                    Some(Some(format)) => parsed.format = format,
+                    _ => log::warn!("--format expects png or exr"),
+                },
+                "--interior-color" => {
+                    match args
+                        .next()
+                        .as_deref()
+                        .and_then(crate::palette::parse_hex_color)
+                    {
+                        Some(color) => parsed.interior_color = Some(color),
+                        None => log::warn!("--interior-color expects a hex color like #102040"),
+                    }
+                }
+                "--samples" => match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) if n > 0 => {
+                        crate::gui::warn_about_samples(n);
+                        parsed.samples_per_pixel = Some(n);
+                    }
+                    _ => log::warn!("--samples expects a positive integer"),
+                },
+                "--aa-pattern" => match args
+                    .next()
+                    .as_deref()
+                    .map(|name| AA_PATTERN_NAMES.iter().position(|&pattern| pattern == name))
+                {
+                    Some(Some(pattern)) => parsed.aa_pattern = pattern as u32,
+                    _ => log::warn!(
+                        "--aa-pattern expects one of {}",
+                        AA_PATTERN_NAMES.join(", ")
+                    ),
+                },
+                "--shader" => match args.next() {
+                    Some(path) => parsed.shader = Some(path.into()),
+                    None => log::warn!("--shader expects a path"),
+                },
+                "--zoom-step" => match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) if n >= 2 => parsed.zoom_step = Some(n),
+                    _ => log::warn!("--zoom-step expects an integer of at least 2"),
+                },
+                "--benchmark" => match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) if n > 0 => parsed.benchmark = Some(n),
+                    _ => log::warn!("--benchmark expects a number of frames"),
+                },
+                "--benchmark-csv" => match args.next() {
+                    Some(path) => parsed.benchmark_csv = Some(path.into()),
+                    None => log::warn!("--benchmark-csv expects a path"),
+                },
+                // Also accepted as `--resolution`, which is what benchmark scripts tend to call it.
+                flag @ ("--window-size" | "--resolution") => {
+                    match args.next().as_deref().and_then(parse_size) {
+                        Some(size) => parsed.window_size = Some(size),
+                        None => log::warn!("{flag} expects WIDTHxHEIGHT, using the default size"),
+                    }
+                }
+                "--iterations" => match args.next().map(|n| n.parse()) {
+                    Some(Ok(n)) if n > 0 => parsed.max_iterations = Some(n),
+                    _ => log::warn!("--iterations expects a positive integer"),
+                },
+                "--precision" => match args.next().as_deref() {
+                    Some("auto") => parsed.precision = None,
+                    Some("f64") => parsed.precision = Some(Precision::F64),
+                    Some("double-double") => parsed.precision = Some(Precision::DoubleDouble),
+                    _ => log::warn!("--precision expects auto, f64 or double-double"),
+                },
+                "--target-fps" => match args.next().map(|fps| fps.parse()) {
+                    Some(Ok(fps)) if fps > 0.0 => parsed.target_fps = Some(fps),
+                    _ => log::warn!("--target-fps expects a positive number"),
+                },
+                "--present-mode" => match args.next().as_deref().and_then(parse_present_mode) {
+                    Some(mode) => parsed.present_mode = Some(mode),
+                    None => log::warn!("--present-mode expects fifo, mailbox or immediate"),
+                },
+                "--backend" => match args.next().as_deref().and_then(parse_backends) {
+                    Some(backends) => parsed.backends = Some(backends),
+                    None => log::warn!(
+                        "--backend expects a comma separated list of vulkan, dx12, metal and gl"
+                    ),
+                },
+                "--verify-precision" => parsed.verify_precision = true,
+                "--compare-to" => match args.next() {
+                    Some(path) => parsed.compare_to = Some(path.into()),
+                    None => log::warn!("--compare-to expects a path"),
+                },
+                "--max-error" => match args.next().map(|e| e.parse()) {
+                    Some(Ok(error)) if error >= 0.0 => parsed.max_error = Some(error),
+                    _ => log::warn!("--max-error expects a number between 0 and 1"),
+                },
+                "--no-manifest" => parsed.no_manifest = true,
+                "--no-inertia" => parsed.no_inertia = true,
+                "--idle-delay" => match args.next().map(|secs| secs.parse()) {
+                    Some(Ok(secs)) if secs >= 0.0 => {
+                        parsed.idle_delay = Some(Duration::from_secs_f64(secs))
+                    }
+                    _ => log::warn!("--idle-delay expects a number of seconds"),
+                },
+                "--idle-target" => match args.next().as_deref().map(crate::location::parse) {
+                    Some(Ok(location)) => parsed.idle_target = Some(location),
+                    Some(Err(e)) => log::warn!("invalid --idle-target: {e}"),
+                    None => log::warn!("--idle-target expects a location"),
+                },
+                "--random-palette" => {
+                    // The seed is optional, pick one once `--seed` may have been seen.
+                    let seed = args.peek().and_then(|seed| seed.parse().ok());
+                    if seed.is_some() {
+                        args.next();
+                    }
+                    parsed.random_palette = seed;
+                    unseeded_palette = seed.is_none();
+                }
+                "--seed" => match args.next().map(|n| n.parse()) {
+                    Some(Ok(seed)) => parsed.seed = Some(seed),
+                    _ => log::warn!("--seed expects an unsigned integer"),
+                },
+                _ => log::warn!("ignoring unknown argument {arg:?}"),
+            }
+        }
+        if unseeded_palette {
+            parsed.random_palette = Some(parsed.seed.unwrap_or_else(crate::palette::time_seed));
+        }
+        Ok(parsed)
+    }
+
+    /// Backends to pick an adapter from: those given with `--backend`, else those in
+    /// `WGPU_BACKEND`, else all of them.
+    pub fn backends(&self) -> wgpu::Backends {
+        self.backends
+            .or_else(wgpu::util::backend_bits_from_env)
+            .unwrap_or(wgpu::Backends::all())
+    }
+
+    /// The view to start with, from `fractal`, `location`, `center_x`, `center_y` and `zoom`,
+    /// using `aa_pattern`, `max_iterations`, `escape_radius`, `interior_color`,
+    /// `samples_per_pixel`, `contour_interval` and `transparent_interior`.
+    pub fn start_view(&self, aspect_ratio: f64) -> MandelbrotUniform {
+        let default = MandelbrotUniform::default_view(aspect_ratio);
+        let mut uniform = MandelbrotUniform {
+            aa_pattern: self.aa_pattern,
+            max_iterations: self.max_iterations.unwrap_or(default.max_iterations),
+            escape_radius: self.escape_radius.unwrap_or(default.escape_radius),
+            samples_per_pixel: self.samples_per_pixel.unwrap_or(default.samples_per_pixel),
+            contour_interval: self.contour_interval.unwrap_or(default.contour_interval),
+            interior_color: match self.interior_color {
+                Some([r, g, b]) => [r, g, b, 1.0],
+                None => default.interior_color,
+            },
+            ..default
+        };
+        uniform.set_flag(
+            MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR,
+            self.transparent_interior,
+        );
+        match self.fractal {
+            FractalKind::Mandelbrot => {}
+            FractalKind::Julia(c) => {
+                uniform.set_flag(MandelbrotUniform::FLAG_JULIA, true);
+                uniform.julia_c = c;
+                let (min, max) = JULIA_VIEW;
+                uniform.fit(min, max);
+            }
+            FractalKind::BurningShip => {
+                uniform.set_flag(MandelbrotUniform::FLAG_BURNING_SHIP, true);
+                let (min, max) = BURNING_SHIP_VIEW;
+                uniform.fit(min, max);
+            }
+        }
+        if let Some(location) = &self.location {
+            uniform.fit(location.min, location.max);
+        }
+        let [x, y] = uniform.center();
+        if let Some(zoom) = self.zoom {
+            uniform.height = MandelbrotUniform::default_view(aspect_ratio).height / zoom;
+        }
+        uniform.set_center([self.center_x.unwrap_or(x), self.center_y.unwrap_or(y)]);
+        uniform
+    }
+
+    /// [`Args::start_view`] for an image of `width` by `height` pixels, rendered in double-double
+    /// if that's deep enough to need it, or pinned to `precision`.
+    pub fn render_view(&self, [width, height]: [u32; 2]) -> MandelbrotUniform {
+        let mut uniform = self.start_view(width as f64 / height as f64);
+        let precision = Precision::required(uniform.center(), uniform.height / height as f64);
+        uniform.set_flag(
+            MandelbrotUniform::FLAG_DOUBLE_DOUBLE,
+            precision.escalate(self.precision) == Precision::DoubleDouble,
+        );
+        uniform
+    }
+}
+
+fn parse_present_mode(s: &str) -> Option<wgpu::PresentMode> {
+    match s.to_ascii_lowercase().as_str() {
+        "fifo" | "vsync" => Some(wgpu::PresentMode::Fifo),
+        "mailbox" => Some(wgpu::PresentMode::Mailbox),
+        "immediate" => Some(wgpu::PresentMode::Immediate),
+        _ => None,
+    }
+}
+
+// Parses a comma separated list of backend names, rejecting unknown ones rather than silently
+// falling back to every backend.
+fn parse_backends(s: &str) -> Option<wgpu::Backends> {
+    s.split(',')
+        .try_fold(wgpu::Backends::empty(), |backends, name| {
+            let backend = match name.trim().to_ascii_lowercase().as_str() {
+                "vulkan" | "vk" => wgpu::Backends::VULKAN,
+                "dx12" | "d3d12" => wgpu::Backends::DX12,
+                "metal" | "mtl" => wgpu::Backends::METAL,
+                "gl" | "opengl" | "gles" => wgpu::Backends::GL,
+                _ => return None,
+            };
+            Some(backends | backend)
+        })
+}
+
+// Parses `WIDTHxHEIGHT`, both nonzero.
+fn parse_size(s: &str) -> Option<[u32; 2]> {
+    let (width, height) = s.split_once(['x', 'X'])?;
+    let size = [width.parse().ok()?, height.parse().ok()?];
+    size.iter().all(|&n| n > 0).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse_from(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn view_and_output_options_are_parsed() {
+        let args = parse(&[
+            "--center-x",
+            "-0.5",
+            "--center-y",
+            "0.25",
+            "--zoom",
+            "8",
+            "--size",
+            "640x480",
+            "--out",
+            "out.png",
+            "--escape-radius",
+            "100",
+        ])
+        .unwrap();
+        assert_eq!((args.center_x, args.center_y), (Some(-0.5), Some(0.25)));
+        assert_eq!(args.zoom, Some(8.0));
+        assert_eq!(args.size, Some([640, 480]));
+        assert_eq!(args.out, Some(PathBuf::from("out.png")));
+        assert_eq!(args.start_view(1.0).escape_radius, 100.0);
+    }
+
+    #[test]
+    fn malformed_view_and_output_options_are_errors() {
+        for args in [
+            &["--center-x", "left"][..],
+            &["--center-y"],
+            &["--zoom", "0"],
+            &["--zoom", "-2"],
+            &["--size", "640"],
+            &["--size", "0x480"],
+            &["--location", "1 2"],
+            &["--out"],
+            &["--escape-radius", "1"],
+            &["--escape-radius", "far"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+        // Other options only warn.
+        assert!(parse(&["--iterations", "many", "--unknown"]).is_ok());
+    }
+}
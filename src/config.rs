@@ -0,0 +1,472 @@
+//! The config file (`--config`, or [`CONFIG_FILE`] in the working directory), an INI style text
+//! file. Its `[keybindings]` section assigns keys to the actions of the explorer, and its
+//! `[window]` section sets the window's title and icon:
+//!
+//! ```text
+//! [keybindings]
+//! # action = key, another key
+//! zoom_in = ctrl+up, +
+//! toggle_contours = l
+//! screenshot = p
+//!
+//! [window]
+//! title = Fractal Kiosk
+//! # An image file, relative to the config file.
+//! icon = icon.png
+//! ```
+//!
+//! Keys are single characters, `up`, `down`, `left` and `right` for the arrow keys or `f1` to
+//! `f12`, optionally prefixed with `ctrl+` (which also matches the command key) and/or `shift+`.
+//! An action listed in the file loses its default keys, so `action =` unbinds it. Actions that
+//! aren't listed keep their defaults, unless the file gives one of those keys to another action.
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use winit::keyboard::{Key, NamedKey, SmolStr};
+
+/// Read at startup if it exists and no other config file is given.
+pub const CONFIG_FILE: &str = "mandelbrot.conf";
+
+/// Everything the explorer does in response to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    ToggleInteriorDetection,
+    ToggleContours,
+    CycleNorm,
+    ToggleAxesGrid,
+    ToggleJulia,
+    ToggleUnderiterated,
+    CycleSamples,
+    ToggleSmooth,
+    ToggleAdaptiveAa,
+    RandomPalette,
+    CyclePalette,
+    Screenshot,
+    ToggleSplit,
+    CycleGrid,
+    ShorterColorPeriod,
+    LongerColorPeriod,
+    ToggleProgressive,
+    ToggleCpu,
+    ZoomIn,
+    ZoomOut,
+    ExportPalette,
+    ImportPalette,
+    RotateLeft,
+    RotateRight,
+    ToggleAspectRatio,
+    Reset,
+    Undo,
+    Redo,
+    ToggleInspector,
+    ToggleLoupe,
+    ToggleSrgbEncoding,
+    ToggleAreaEstimate,
+    ToggleBloom,
+    WeakerBloom,
+    StrongerBloom,
+    ToggleBoundary,
+    ToggleLogPolar,
+    ToggleSmoothColoring,
+    CyclePrecision,
+    MeasurePrecisionLimits,
+    CycleSurfaceFormat,
+    LogView,
+    MoreIterations,
+    FewerIterations,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    PanFarLeft,
+    PanFarRight,
+    PanFarUp,
+    PanFarDown,
+    ToggleHud,
+    ToggleFrameTime,
+    ToggleContinuousRedraw,
+}
+
+/// Every action with its name in the config file and its default keys.
+pub const ACTIONS: [(&str, Action, &[&str]); 55] = [
+    (
+        "toggle_interior_detection",
+        Action::ToggleInteriorDetection,
+        &["i"],
+    ),
+    ("toggle_contours", Action::ToggleContours, &["c"]),
+    ("cycle_norm", Action::CycleNorm, &["k"]),
+    ("toggle_axes_grid", Action::ToggleAxesGrid, &["x"]),
+    ("toggle_julia", Action::ToggleJulia, &["j"]),
+    ("toggle_underiterated", Action::ToggleUnderiterated, &["u"]),
+    ("cycle_samples", Action::CycleSamples, &["p"]),
+    ("toggle_smooth", Action::ToggleSmooth, &["f"]),
+    ("toggle_adaptive_aa", Action::ToggleAdaptiveAa, &["a"]),
+    ("random_palette", Action::RandomPalette, &["n"]),
+    ("cycle_palette", Action::CyclePalette, &["ctrl+n"]),
+    ("screenshot", Action::Screenshot, &["s"]),
+    ("toggle_split", Action::ToggleSplit, &["v"]),
+    ("cycle_grid", Action::CycleGrid, &["t"]),
+    ("shorter_color_period", Action::ShorterColorPeriod, &["["]),
+    ("longer_color_period", Action::LongerColorPeriod, &["]"]),
+    ("toggle_progressive", Action::ToggleProgressive, &["m"]),
+    ("toggle_cpu", Action::ToggleCpu, &["g"]),
+    ("zoom_in", Action::ZoomIn, &["+", "="]),
+    ("zoom_out", Action::ZoomOut, &["-"]),
+    ("export_palette", Action::ExportPalette, &["ctrl+e"]),
+    ("import_palette", Action::ImportPalette, &["ctrl+o"]),
+    ("rotate_left", Action::RotateLeft, &["q"]),
+    ("rotate_right", Action::RotateRight, &["e"]),
+    ("toggle_aspect_ratio", Action::ToggleAspectRatio, &["b"]),
+    ("reset", Action::Reset, &["0"]),
+    ("undo", Action::Undo, &["ctrl+z"]),
+    ("redo", Action::Redo, &["ctrl+y"]),
+    ("toggle_inspector", Action::ToggleInspector, &["d"]),
+    ("toggle_loupe", Action::ToggleLoupe, &["h"]),
+    ("toggle_srgb_encoding", Action::ToggleSrgbEncoding, &["r"]),
+    ("toggle_area_estimate", Action::ToggleAreaEstimate, &["w"]),
+    ("toggle_bloom", Action::ToggleBloom, &["o"]),
+    ("weaker_bloom", Action::WeakerBloom, &[","]),
+    ("stronger_bloom", Action::StrongerBloom, &["."]),
+    ("toggle_boundary", Action::ToggleBoundary, &["y"]),
+    ("toggle_log_polar", Action::ToggleLogPolar, &["ctrl+l"]),
+    (
+        "toggle_smooth_coloring",
+        Action::ToggleSmoothColoring,
+        &["ctrl+f"],
+    ),
+    ("cycle_precision", Action::CyclePrecision, &["ctrl+d"]),
+    (
+        "measure_precision_limits",
+        Action::MeasurePrecisionLimits,
+        &["ctrl+p"],
+    ),
+    (
+        "cycle_surface_format",
+        Action::CycleSurfaceFormat,
+        &["ctrl+r"],
+    ),
+    ("log_view", Action::LogView, &["l"]),
+    ("more_iterations", Action::MoreIterations, &["up"]),
+    ("fewer_iterations", Action::FewerIterations, &["down"]),
+    ("pan_left", Action::PanLeft, &["ctrl+left"]),
+    ("pan_right", Action::PanRight, &["ctrl+right"]),
+    ("pan_up", Action::PanUp, &["ctrl+up"]),
+    ("pan_down", Action::PanDown, &["ctrl+down"]),
+    ("pan_far_left", Action::PanFarLeft, &["shift+left"]),
+    ("pan_far_right", Action::PanFarRight, &["shift+right"]),
+    ("pan_far_up", Action::PanFarUp, &["shift+up"]),
+    ("pan_far_down", Action::PanFarDown, &["shift+down"]),
+    ("toggle_hud", Action::ToggleHud, &["f1"]),
+    ("toggle_frame_time", Action::ToggleFrameTime, &["ctrl+t"]),
+    (
+        "toggle_continuous_redraw",
+        Action::ToggleContinuousRedraw,
+        &["shift+p"],
+    ),
+];
+
+/// A key, with or without Ctrl and Shift held.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    pub key: Key,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Shortcut {
+    /// Parses a key as written in the config file, see the module documentation.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        let (mut ctrl, mut shift, mut name) = (false, false, s.as_str());
+        loop {
+            if let Some(rest) = name
+                .strip_prefix("ctrl+")
+                .or_else(|| name.strip_prefix("cmd+"))
+            {
+                (ctrl, name) = (true, rest);
+            } else if let Some(rest) = name.strip_prefix("shift+") {
+                (shift, name) = (true, rest);
+            } else {
+                break;
+            }
+        }
+        let key = match name {
+            "up" => Key::Named(NamedKey::ArrowUp),
+            "down" => Key::Named(NamedKey::ArrowDown),
+            "left" => Key::Named(NamedKey::ArrowLeft),
+            "right" => Key::Named(NamedKey::ArrowRight),
+            "f1" => Key::Named(NamedKey::F1),
+            "f2" => Key::Named(NamedKey::F2),
+            "f3" => Key::Named(NamedKey::F3),
+            "f4" => Key::Named(NamedKey::F4),
+            "f5" => Key::Named(NamedKey::F5),
+            "f6" => Key::Named(NamedKey::F6),
+            "f7" => Key::Named(NamedKey::F7),
+            "f8" => Key::Named(NamedKey::F8),
+            "f9" => Key::Named(NamedKey::F9),
+            "f10" => Key::Named(NamedKey::F10),
+            "f11" => Key::Named(NamedKey::F11),
+            "f12" => Key::Named(NamedKey::F12),
+            _ if name.chars().count() == 1 => Key::Character(SmolStr::new(name)),
+            _ => return None,
+        };
+        Some(Self { key, ctrl, shift })
+    }
+}
+
+/// Which action each key triggers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keybindings(HashMap<Shortcut, Action>);
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        for (_, action, keys) in ACTIONS {
+            for key in keys {
+                let shortcut = Shortcut::parse(key).expect("default keys are valid");
+                bindings.insert(shortcut, action);
+            }
+        }
+        Self(bindings)
+    }
+}
+
+impl Keybindings {
+    /// The action bound to `key`. With Ctrl or Shift held, keys bound without them count too,
+    /// unless the modifiers give them an action of their own.
+    pub fn action(&self, key: &Key, ctrl: bool, shift: bool) -> Option<Action> {
+        let key = match key {
+            Key::Character(c) => Key::Character(SmolStr::new(c.to_lowercase())),
+            key => key.clone(),
+        };
+        [(ctrl, shift), (ctrl, false), (false, shift), (false, false)]
+            .into_iter()
+            .find_map(|(ctrl, shift)| {
+                let shortcut = Shortcut {
+                    key: key.clone(),
+                    ctrl,
+                    shift,
+                };
+                self.0.get(&shortcut).copied()
+            })
+    }
+}
+
+/// The settings read from a config file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub keybindings: Keybindings,
+    /// Replaces the name at the start of the window title.
+    pub title: Option<String>,
+    /// Image to use as the window icon.
+    pub icon: Option<PathBuf>,
+}
+
+// The sections of a config file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Keybindings,
+    Window,
+}
+
+impl Config {
+    /// Parses a config file, see the module documentation. Lines starting with `#` and blank
+    /// lines are ignored.
+    pub fn from_text(text: &str) -> Result<Self, ParseConfigError> {
+        let mut config = Self::default();
+        let mut section = None;
+        let mut rebound = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |kind| ParseConfigError { line: i + 1, kind };
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(match name.trim() {
+                    "keybindings" => Section::Keybindings,
+                    "window" => Section::Window,
+                    _ => return Err(error(ConfigErrorKind::Section)),
+                });
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(error(ConfigErrorKind::Syntax));
+            };
+            match section {
+                None => return Err(error(ConfigErrorKind::Section)),
+                Some(Section::Window) => {
+                    let value = value.trim().to_owned();
+                    match name.trim() {
+                        "title" => config.title = Some(value),
+                        "icon" => config.icon = Some(value.into()),
+                        _ => return Err(error(ConfigErrorKind::Setting)),
+                    }
+                    continue;
+                }
+                Some(Section::Keybindings) => {}
+            }
+            let &(_, action, _) = ACTIONS
+                .iter()
+                .find(|(n, _, _)| *n == name.trim())
+                .ok_or(error(ConfigErrorKind::Action))?;
+            let keys = rebound.entry(action).or_insert_with(Vec::new);
+            // `,` separates keys, so it can only be bound on its own.
+            let value = value.trim();
+            let names: Vec<_> = match value {
+                "," => vec![value],
+                _ => value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|k| !k.is_empty())
+                    .collect(),
+            };
+            for name in names {
+                keys.push(Shortcut::parse(name).ok_or(error(ConfigErrorKind::Key))?);
+            }
+        }
+
+        let bindings = &mut config.keybindings.0;
+        bindings.retain(|_, action| !rebound.contains_key(action));
+        for (action, keys) in rebound {
+            for key in keys {
+                bindings.insert(key, action);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Reads the config file at `path`, resolving the icon's path relative to it.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let mut config =
+            Self::from_text(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let (Some(icon), Some(dir)) = (&mut config.icon, path.parent()) {
+            *icon = dir.join(&*icon);
+        }
+        Ok(config)
+    }
+}
+
+/// Why [`Config::from_text`] rejected a config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseConfigError {
+    /// The offending line, counting from 1.
+    pub line: usize,
+    pub kind: ConfigErrorKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigErrorKind {
+    Syntax,
+    Section,
+    Action,
+    Setting,
+    Key,
+}
+
+impl fmt::Display for ParseConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        f.write_str(match self.kind {
+            ConfigErrorKind::Syntax => "expected a section like `[keybindings]` or a setting",
+            ConfigErrorKind::Section => {
+                "settings have to be in the `[keybindings]` or `[window]` section"
+            }
+            ConfigErrorKind::Action => "there's no action of that name",
+            ConfigErrorKind::Setting => "the `[window]` section only has `title` and `icon`",
+            ConfigErrorKind::Key => "expected keys like `a`, `ctrl+z` or `shift+up`",
+        })
+    }
+}
+
+impl std::error::Error for ParseConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_replaces_the_defaults() {
+        let config = Config::from_text(
+            "# comment\n[keybindings]\nzoom_in = ctrl+up, +\nscreenshot = i\nreset =\n\
+             [window]\ntitle = Fractal Kiosk\n",
+        )
+        .unwrap();
+        assert_eq!(config.title.as_deref(), Some("Fractal Kiosk"));
+        let bindings = &config.keybindings;
+        let key = |s: &str| Shortcut::parse(s).unwrap().key;
+        assert_eq!(
+            bindings.action(&key("up"), true, false),
+            Some(Action::ZoomIn)
+        );
+        assert_eq!(
+            bindings.action(&key("+"), false, false),
+            Some(Action::ZoomIn)
+        );
+        assert_eq!(bindings.action(&key("="), false, false), None);
+        // `i` moved to the screenshot action, `s` went with it, `0` is unbound.
+        assert_eq!(
+            bindings.action(&key("I"), false, false),
+            Some(Action::Screenshot)
+        );
+        assert_eq!(bindings.action(&key("s"), false, false), None);
+        assert_eq!(bindings.action(&key("0"), false, false), None);
+        // Unlisted actions keep their defaults, also with Ctrl held.
+        assert_eq!(
+            bindings.action(&key("c"), true, false),
+            Some(Action::ToggleContours)
+        );
+        assert_eq!(bindings.action(&key("z"), true, false), Some(Action::Undo));
+        assert_eq!(bindings.action(&key("z"), false, false), None);
+    }
+
+    #[test]
+    fn shift_picks_its_own_binding() {
+        let shortcut = Shortcut::parse("Shift+Ctrl+left").unwrap();
+        assert!(shortcut.ctrl && shortcut.shift);
+        let bindings = Keybindings::default();
+        let key = |s: &str| Shortcut::parse(s).unwrap().key;
+        assert_eq!(
+            bindings.action(&key("left"), false, true),
+            Some(Action::PanFarLeft)
+        );
+        assert_eq!(
+            bindings.action(&key("left"), true, false),
+            Some(Action::PanLeft)
+        );
+        // Keys without a Shift binding ignore it.
+        assert_eq!(
+            bindings.action(&key("+"), false, true),
+            Some(Action::ZoomIn)
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let error = |text| Config::from_text(text).unwrap_err();
+        assert_eq!(
+            error("zoom_in = +"),
+            ParseConfigError {
+                line: 1,
+                kind: ConfigErrorKind::Section
+            }
+        );
+        assert_eq!(
+            error("[keybindings]\nfly = f").kind,
+            ConfigErrorKind::Action
+        );
+        assert_eq!(error("[keybindings]\n\nreset = ctrl+home").line, 3);
+        assert_eq!(error("[colors]").kind, ConfigErrorKind::Section);
+        assert_eq!(
+            error("[window]\nsize = 800x600").kind,
+            ConfigErrorKind::Setting
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Traces contour lines of a scalar field sampled on a grid, such as the smooth escape time of
+//! every pixel, with marching squares, and writes them as an SVG line drawing.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    io,
+    path::Path,
+};
+
+/// A contour line through the field, in pixel coordinates with the origin at the top left
+/// corner of the image and pixel centers at half integers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<[f32; 2]>,
+    /// Whether the last point connects back to the first.
+    pub closed: bool,
+}
+
+/// Traces where the `width * height` field, given row by row starting at the top, crosses
+/// `level`. Samples at or above the level count as inside, so regions of the field that equal the
+/// level are outlined rather than traced through.
+pub fn trace(field: &[f32], width: usize, height: usize, level: f32) -> Vec<Polyline> {
+    assert_eq!(
+        field.len(),
+        width * height,
+        "the field doesn't have the given size"
+    );
+    let inside = |x: usize, y: usize| field[y * width + x] >= level;
+    // Where the contour crosses the edge from sample `(x0, y0)` to `(x1, y1)`.
+    let crossing = |(x0, y0): (usize, usize), (x1, y1): (usize, usize)| {
+        let (a, b) = (field[y0 * width + x0], field[y1 * width + x1]);
+        let t = ((level - a) / (b - a)).clamp(0.0, 1.0);
+        let lerp = |p0: usize, p1: usize| p0 as f32 + 0.5 + (p1 as f32 - p0 as f32) * t;
+        [lerp(x0, x1), lerp(y0, y1)]
+    };
+
+    // Every crossing lies on an edge between two samples, which at most two cells share, so the
+    // segments form chains in which each crossing has at most two neighbours. Crossings are
+    // keyed by their edge: even for the one to the right of a sample, odd for the one below it.
+    let mut points = HashMap::new();
+    let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width.saturating_sub(1) {
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+            let states = corners.map(|(x, y)| inside(x, y));
+            // Top, right, bottom and left edge, each between consecutive corners.
+            let edges = [
+                2 * (y * width + x),
+                2 * (y * width + x + 1) + 1,
+                2 * ((y + 1) * width + x),
+                2 * (y * width + x) + 1,
+            ];
+            let crossed: Vec<usize> = (0..4)
+                .filter(|&i| states[i] != states[(i + 1) % 4])
+                .collect();
+            let segments = match crossed[..] {
+                [a, b] => vec![(a, b)],
+                [_, _, _, _] => {
+                    // A saddle, where opposite corners agree. Resolve it by the cell's average:
+                    // if that agrees with the top left corner, the other two corners are cut off
+                    // on their own, otherwise the top left and bottom right ones are.
+                    let center = corners
+                        .iter()
+                        .map(|&(x, y)| field[y * width + x])
+                        .sum::<f32>()
+                        / 4.0;
+                    if (center >= level) == states[0] {
+                        vec![(0, 1), (2, 3)]
+                    } else {
+                        vec![(3, 0), (1, 2)]
+                    }
+                }
+                _ => Vec::new(),
+            };
+            for (a, b) in segments {
+                for (edge, (from, to)) in [(a, (a, (a + 1) % 4)), (b, (b, (b + 1) % 4))] {
+                    points
+                        .entry(edges[edge])
+                        .or_insert_with(|| crossing(corners[from], corners[to]));
+                }
+                neighbours.entry(edges[a]).or_default().push(edges[b]);
+                neighbours.entry(edges[b]).or_default().push(edges[a]);
+            }
+        }
+    }
+
+    // Follow the open chains from their ends first, then whatever is left are loops.
+    let mut starts: Vec<_> = neighbours.keys().copied().collect();
+    starts.sort_unstable_by_key(|edge| (neighbours[edge].len(), *edge));
+    let mut visited = HashSet::new();
+    let mut lines = Vec::new();
+    for start in starts {
+        if visited.contains(&start) {
+            continue;
+        }
+        let closed = neighbours[&start].len() == 2;
+        let mut chain = vec![start];
+        visited.insert(start);
+        let mut current = start;
+        while let Some(&next) = neighbours[&current]
+            .iter()
+            .find(|next| !visited.contains(*next))
+        {
+            visited.insert(next);
+            chain.push(next);
+            current = next;
+        }
+        lines.push(Polyline {
+            points: chain.iter().map(|edge| points[edge]).collect(),
+            closed,
+        });
+    }
+    lines
+}
+
+/// The multiples of `interval` from `interval` up to, but excluding, `max`.
+pub fn levels(interval: f32, max: f32) -> Vec<f32> {
+    if interval.is_nan() || interval <= 0.0 {
+        return Vec::new();
+    }
+    (1..)
+        .map(|i| i as f32 * interval)
+        .take_while(|&level| level < max)
+        .collect()
+}
+
+/// Writes the contour lines of `field` at every level as an SVG of the same size in pixels, in
+/// one group per level.
+pub fn write_svg(
+    path: impl AsRef<Path>,
+    field: &[f32],
+    width: usize,
+    height: usize,
+    levels: &[f32],
+) -> io::Result<()> {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n\
+         <g fill=\"none\" stroke=\"black\" stroke-width=\"1\" stroke-linejoin=\"round\">\n"
+    );
+    for &level in levels {
+        let _ = writeln!(svg, "<g data-level=\"{level}\">");
+        for line in trace(field, width, height, level) {
+            let element = if line.closed { "polygon" } else { "polyline" };
+            let _ = write!(svg, "<{element} points=\"");
+            for (i, [x, y]) in line.points.iter().enumerate() {
+                let separator = if i == 0 { "" } else { " " };
+                let _ = write!(svg, "{separator}{x:.2},{y:.2}");
+            }
+            svg += "\"/>\n";
+        }
+        svg += "</g>\n";
+    }
+    svg += "</g>\n</svg>\n";
+    std::fs::write(path, svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_a_closed_circle_and_an_open_edge() {
+        let size = 32;
+        let distance =
+            |x: usize, y: usize, cx: f32, cy: f32| (x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy);
+        // Peaks at the center, above 0 within a radius of 10 pixels.
+        let field: Vec<f32> = (0..size * size)
+            .map(|i| 10.0 - distance(i % size, i / size, 16.0, 16.0))
+            .collect();
+        let lines = trace(&field, size, size, 0.0);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].closed);
+        for &[x, y] in &lines[0].points {
+            assert!(((x - 16.0).hypot(y - 16.0) - 10.0).abs() < 0.1, "{x}, {y}");
+        }
+
+        // A circle around the top left corner only crosses the image as an arc.
+        let field: Vec<f32> = (0..size * size)
+            .map(|i| 10.0 - distance(i % size, i / size, 0.0, 0.0))
+            .collect();
+        let lines = trace(&field, size, size, 0.0);
+        assert_eq!(lines.len(), 1);
+        assert!(!lines[0].closed);
+        assert!(lines[0].points.len() > 10);
+    }
+
+    #[test]
+    fn levels_stop_below_the_maximum() {
+        assert_eq!(levels(2.0, 7.0), [2.0, 4.0, 6.0]);
+        assert_eq!(levels(2.0, 6.0), [2.0, 4.0]);
+        assert!(levels(0.0, 6.0).is_empty());
+    }
+}
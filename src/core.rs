@@ -0,0 +1,482 @@
+//! The escape time math behind the renderer, usable on the CPU without any of the GPU or
+//! windowing dependencies (build with `default-features = false`).
+
+/// Squared radius beyond which an orbit is considered escaped by default. Matches the shader.
+pub const BAILOUT_SQR: f64 = 4.0;
+
+/// Smallest escape radius smooth coloring iterates to, same as the shader's. The smooth escape
+/// time assumes `|z|` squares every iteration once the orbit escaped, which only holds well for
+/// large `|z|`, so escaping at radius 2 leaves slight steps where the iteration count changes.
+pub const SMOOTH_ESCAPE_RADIUS: f64 = 16.0;
+
+/// The radius orbits are iterated to for a requested `radius`, the same as the shader's
+/// `escape_radius`: at least 2, and at least [`SMOOTH_ESCAPE_RADIUS`] for smooth coloring.
+pub fn escape_radius(radius: f64, smooth: bool) -> f64 {
+    let radius = radius.max(2.0);
+    if smooth {
+        radius.max(SMOOTH_ESCAPE_RADIUS)
+    } else {
+        radius
+    }
+}
+
+/// Where and how an orbit escaped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Escape {
+    /// Number of iterations before `|z|` exceeded the bailout radius.
+    pub iterations: u32,
+    /// `|z|^2` at the time of escape.
+    pub norm_sqr: f64,
+}
+
+/// Iterates `z = z^2 + c` starting at `z = 0`, returning `None` if the orbit doesn't escape
+/// within `max_iterations`.
+pub fn escape(c: [f64; 2], max_iterations: u32) -> Option<Escape> {
+    escape_with_bailout(c, max_iterations, BAILOUT_SQR)
+}
+
+/// Like [`escape`], but with a custom squared bailout radius, which must be at least
+/// [`BAILOUT_SQR`].
+pub fn escape_with_bailout(c: [f64; 2], max_iterations: u32, bailout_sqr: f64) -> Option<Escape> {
+    iterate([0.0; 2], c, max_iterations, bailout_sqr, false)
+}
+
+/// Iterates `z = z^2 + c` starting at `z` itself, the escape time of `z` in the Julia set of `c`.
+pub fn julia_escape(z: [f64; 2], c: [f64; 2], max_iterations: u32) -> Option<Escape> {
+    julia_escape_with_bailout(z, c, max_iterations, BAILOUT_SQR)
+}
+
+/// Like [`julia_escape`], but with a custom squared bailout radius, which must be at least
+/// [`BAILOUT_SQR`].
+pub fn julia_escape_with_bailout(
+    z: [f64; 2],
+    c: [f64; 2],
+    max_iterations: u32,
+    bailout_sqr: f64,
+) -> Option<Escape> {
+    iterate(z, c, max_iterations, bailout_sqr, false)
+}
+
+/// Iterates `z = (|Re z| + i|Im z|)^2 + c` starting at `z` until `|z|^2` reaches `bailout_sqr`,
+/// which must be at least [`BAILOUT_SQR`]. That's the Burning Ship for `z = 0` and its Julia
+/// sets otherwise.
+pub fn burning_ship_escape(
+    z: [f64; 2],
+    c: [f64; 2],
+    max_iterations: u32,
+    bailout_sqr: f64,
+) -> Option<Escape> {
+    iterate(z, c, max_iterations, bailout_sqr, true)
+}
+
+// Folds `z` into the first quadrant before squaring it if `fold` is set, like the shader does
+// with `FLAG_BURNING_SHIP`.
+fn iterate(
+    z: [f64; 2],
+    c: [f64; 2],
+    max_iterations: u32,
+    bailout_sqr: f64,
+    fold: bool,
+) -> Option<Escape> {
+    let [cx, cy] = c;
+    let [mut x, mut y] = z;
+    for i in 0..max_iterations {
+        let norm_sqr = x * x + y * y;
+        if norm_sqr >= bailout_sqr {
+            return Some(Escape {
+                iterations: i,
+                norm_sqr,
+            });
+        }
+        if fold {
+            (x, y) = (x.abs(), y.abs());
+        }
+        (x, y) = (x * x - y * y + cx, 2.0 * x * y + cy);
+    }
+    None
+}
+
+/// Squared orbit derivative below which interior detection considers an orbit settled into an
+/// attracting cycle in views of the whole set. Matches `INTERIOR_THRESHOLD` in the shader.
+pub const INTERIOR_THRESHOLD: f64 = 1e-6;
+
+/// The interior detection threshold for a view `view_height` tall, the same as the shader's
+/// `interior_threshold`. Exterior orbits can pass close enough to 0 to briefly shrink their
+/// derivative, the more so the closer they start to the boundary, so deeper views need a smaller
+/// threshold. It shrinks with the cube of the size of a pixel of a view about a thousand pixels
+/// tall, which in the seahorse valley keeps points more than a pixel or so outside the set from
+/// being taken for interior.
+pub fn interior_threshold(view_height: f64) -> f64 {
+    let pixel_size = view_height / 1000.0;
+    INTERIOR_THRESHOLD.min(pixel_size.powi(3))
+}
+
+/// How [`classify`] ended an orbit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrbitFate {
+    Escaped(Escape),
+    /// Settled into an attracting cycle, noticed after this many iterations.
+    Interior(u32),
+    /// Neither happened within the iteration limit.
+    Undecided,
+}
+
+/// Like [`escape`], with the shader's interior detection: the orbit counts as interior once the
+/// squared derivative of `z_n` with respect to `z_1` drops below `threshold`. The derivative with
+/// respect to `z_0` would be useless, as every orbit starts at the critical point 0.
+pub fn classify(c: [f64; 2], max_iterations: u32, threshold: f64) -> OrbitFate {
+    let [cx, cy] = c;
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut dz = [1.0, 0.0];
+    for i in 0..max_iterations {
+        let norm_sqr = x * x + y * y;
+        if norm_sqr >= BAILOUT_SQR {
+            return OrbitFate::Escaped(Escape {
+                iterations: i,
+                norm_sqr,
+            });
+        }
+        if i > 0 {
+            let [dx, dy] = dz;
+            dz = [2.0 * (x * dx - y * dy), 2.0 * (x * dy + y * dx)];
+            if dz[0] * dz[0] + dz[1] * dz[1] < threshold {
+                return OrbitFate::Interior(i);
+            }
+        }
+        (x, y) = (x * x - y * y + cx, 2.0 * x * y + cy);
+    }
+    OrbitFate::Undecided
+}
+
+/// Continuous escape time of `c`, or `max_iterations` if it doesn't escape.
+///
+/// See [`smooth_iterations`] for how the fractional part is normalized.
+pub fn escape_iterations(c: [f64; 2], max_iterations: u32) -> f64 {
+    match escape(c, max_iterations) {
+        Some(escape) => smooth_iterations(escape),
+        None => max_iterations as f64,
+    }
+}
+
+/// The continuous escape time of an escaped orbit, `n + 1 - log2(ln|z| / ln(2))`.
+///
+/// The log term is normalized against the default bailout radius rather than the one the orbit
+/// escaped at. Every iteration past escape doubles `ln|z|`, so a larger bailout's extra
+/// iterations are cancelled out and the result doesn't depend on the bailout, which keeps
+/// palettes consistent. With the default bailout it lies between `n` and `n + 1`.
+pub fn smooth_iterations(escape: Escape) -> f64 {
+    let ratio = escape.norm_sqr.ln() / BAILOUT_SQR.ln();
+    escape.iterations as f64 + 1.0 - ratio.log2()
+}
+
+/// A round spacing of `1`, `2` or `5` times a power of ten that divides `span` into about
+/// `ticks` intervals, like the tick marks on a plot's axis.
+pub fn tick_spacing(span: f64, ticks: f64) -> f64 {
+    let raw = span / ticks;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let nice = match raw / magnitude {
+        n if n < 1.5 => 1.0,
+        n if n < 3.5 => 2.0,
+        n if n < 7.5 => 5.0,
+        _ => 10.0,
+    };
+    nice * magnitude
+}
+
+/// The number representation needed to tell neighbouring pixels apart, from cheapest to most
+/// expensive. The GPU renderer goes up to [`Precision::DoubleDouble`] when asked to, deeper views
+/// still turn blocky. GPUs without `f64` shaders only get to [`Precision::F32`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precision {
+    F32,
+    F64,
+    DoubleDouble,
+    Perturbation,
+}
+
+impl Precision {
+    // Mantissa bits each representation can spend on resolving pixels, leaving some headroom for
+    // the rounding errors the iteration accumulates.
+    const F32_BITS: f64 = 22.0;
+    const F64_BITS: f64 = 45.0;
+    const DOUBLE_DOUBLE_BITS: f64 = 98.0;
+
+    /// The cheapest precision resolving pixels of size `pixel_size` around `center`.
+    pub fn required(center: [f64; 2], pixel_size: f64) -> Self {
+        let magnitude = center[0].abs().max(center[1].abs()).max(pixel_size);
+        match (magnitude / pixel_size).log2() {
+            bits if bits < Self::F32_BITS => Self::F32,
+            bits if bits < Self::F64_BITS => Self::F64,
+            bits if bits < Self::DOUBLE_DOUBLE_BITS => Self::DoubleDouble,
+            _ => Self::Perturbation,
+        }
+    }
+
+    /// The precision to render a view needing `self` in: `pinned` if given, else the cheapest
+    /// one that suffices, capped at [`Precision::DoubleDouble`] as nothing renders deeper yet.
+    /// Renderers computing in `f64` never go below it.
+    pub fn escalate(self, pinned: Option<Self>) -> Self {
+        pinned.unwrap_or(self).clamp(Self::F64, Self::DoubleDouble)
+    }
+}
+
+impl std::fmt::Display for Precision {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+            Self::DoubleDouble => "double-double",
+            Self::Perturbation => "perturbation",
+        })
+    }
+}
+
+/// A point of the complex plane kept to about 106 bits, as the unevaluated sum of two `f64`s per
+/// coordinate (double-double). Panning by offsets far below `f64` resolution accumulates exactly
+/// instead of being rounded away, so the point can serve as the reference that views store their
+/// deltas against once a renderer can iterate relative to it. Its exponent range is still that of
+/// `f64`, so views deeper than about 1e-290 would need an arbitrary precision center instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExtendedPoint {
+    hi: [f64; 2],
+    lo: [f64; 2],
+}
+
+impl ExtendedPoint {
+    pub fn new(point: [f64; 2]) -> Self {
+        Self {
+            hi: point,
+            lo: [0.0; 2],
+        }
+    }
+
+    /// The point `hi + lo`, where `lo` is at most half an ulp of `hi` in each coordinate.
+    pub fn from_parts(hi: [f64; 2], lo: [f64; 2]) -> Self {
+        let mut point = Self::new(hi);
+        point.translate(lo);
+        point
+    }
+
+    /// The high and low order parts of the coordinates, see [`Self::from_parts`].
+    pub fn parts(self) -> ([f64; 2], [f64; 2]) {
+        (self.hi, self.lo)
+    }
+
+    /// The point rounded to `f64`.
+    pub fn to_f64(self) -> [f64; 2] {
+        [self.hi[0] + self.lo[0], self.hi[1] + self.lo[1]]
+    }
+
+    /// Moves the point by `delta`, keeping all the bits of both.
+    pub fn translate(&mut self, delta: [f64; 2]) {
+        for ((hi, lo), d) in self.hi.iter_mut().zip(&mut self.lo).zip(delta) {
+            let (sum, err) = two_sum(*hi, d);
+            (*hi, *lo) = fast_two_sum(sum, err + *lo);
+        }
+    }
+
+    /// `self - other`, rounded to `f64`. Exact enough for views where the two are close.
+    pub fn offset_from(self, other: Self) -> [f64; 2] {
+        [0, 1].map(|i| (self.hi[i] - other.hi[i]) + (self.lo[i] - other.lo[i]))
+    }
+}
+
+/// Like [`escape`], but iterating in double-double, so points closer together than `f64` can
+/// resolve still get their own orbits.
+pub fn escape_extended(c: ExtendedPoint, max_iterations: u32) -> Option<Escape> {
+    let [cx, cy] = [0, 1].map(|i| DoubleDouble(c.hi[i], c.lo[i]));
+    let (mut x, mut y) = (DoubleDouble::ZERO, DoubleDouble::ZERO);
+    for i in 0..max_iterations {
+        let (xx, yy) = (x.mul(x), y.mul(y));
+        let norm_sqr = xx.add(yy).0;
+        if norm_sqr >= BAILOUT_SQR {
+            return Some(Escape {
+                iterations: i,
+                norm_sqr,
+            });
+        }
+        let xy = x.mul(y);
+        (x, y) = (xx.sub(yy).add(cx), xy.add(xy).add(cy));
+    }
+    None
+}
+
+// An unevaluated sum `hi + lo` with `|lo|` at most half an ulp of `hi`.
+#[derive(Clone, Copy)]
+struct DoubleDouble(f64, f64);
+
+impl DoubleDouble {
+    const ZERO: Self = Self(0.0, 0.0);
+
+    fn add(self, other: Self) -> Self {
+        let (sum, err) = two_sum(self.0, other.0);
+        let (hi, lo) = fast_two_sum(sum, err + self.1 + other.1);
+        Self(hi, lo)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        self.add(Self(-other.0, -other.1))
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let product = self.0 * other.0;
+        // Exact thanks to the single rounding of the fused multiply-add.
+        let err = self.0.mul_add(other.0, -product);
+        let (hi, lo) = fast_two_sum(product, err + self.0 * other.1 + self.1 * other.0);
+        Self(hi, lo)
+    }
+}
+
+// The rounded sum of `a` and `b` and its rounding error.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_rounded = sum - a;
+    let err = (a - (sum - b_rounded)) + (b - b_rounded);
+    (sum, err)
+}
+
+// Like `two_sum`, for `|a| >= |b|`.
+fn fast_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    (sum, b - (sum - a))
+}
+
+/// Maps a (smooth) escape time to a linear RGB color, using the same hue sweep as the default
+/// palette. Points that didn't escape are black.
+pub fn smooth_color(iterations: f64, max_iterations: u32) -> [f32; 3] {
+    if iterations >= max_iterations as f64 {
+        return [0.0; 3];
+    }
+    hsv2rgb((iterations / max_iterations as f64) as f32, 1.0, 1.0)
+}
+
+/// Converts a hue, saturation, value triple (all in `[0, 1]`) to linear RGB.
+pub fn hsv2rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
+    [1.0, 2.0 / 3.0, 1.0 / 3.0].map(|k| {
+        let p = ((h + k).fract() * 6.0 - 3.0).abs();
+        v * (1.0 + ((p - 1.0).clamp(0.0, 1.0) - 1.0) * s)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_iterations_ignore_bailout() {
+        let max_iterations = 1000;
+        // A grid over the view of the whole set, skipping points that don't escape.
+        for i in 0..64 {
+            for j in 0..48 {
+                let c = [-2.5 + 3.5 * i as f64 / 63.0, -1.25 + 2.5 * j as f64 / 47.0];
+                let Some(low) = escape_with_bailout(c, max_iterations, 1e4) else {
+                    continue;
+                };
+                let high = escape_with_bailout(c, max_iterations, 1e8).unwrap();
+                let (low, high) = (smooth_iterations(low), smooth_iterations(high));
+                assert!((low - high).abs() < 1e-3, "{c:?}: {low} != {high}");
+            }
+        }
+    }
+
+    #[test]
+    fn escape_radius_is_at_least_two_and_larger_for_smooth_coloring() {
+        assert_eq!(escape_radius(1.0, false), 2.0);
+        assert_eq!(escape_radius(100.0, false), 100.0);
+        assert_eq!(escape_radius(4.0, true), SMOOTH_ESCAPE_RADIUS);
+        assert_eq!(escape_radius(100.0, true), 100.0);
+    }
+
+    #[test]
+    fn interior_detection_classifies_known_points() {
+        let threshold = interior_threshold(2.5);
+        // The centers of the main cardioid and the period 2 and 4 bulbs, and points inside them.
+        for c in [
+            [0.0, 0.0],
+            [-1.0, 0.0],
+            [-1.3107, 0.0],
+            [-0.1, 0.1],
+            [-0.9, 0.1],
+        ] {
+            assert!(
+                matches!(classify(c, 10_000, threshold), OrbitFate::Interior(_)),
+                "{c:?}"
+            );
+        }
+        // Inside, close to the cusp, converging slowly.
+        assert!(matches!(
+            classify([0.2499, 0.0], 100_000, interior_threshold(1e-6)),
+            OrbitFate::Interior(_)
+        ));
+        // Outside, in the seahorse valley and just past the cusp, where orbits linger near 0 for
+        // thousands of iterations. Each point is 10 pixels from the set in a view 1000 pixels
+        // tall, the fixed threshold of the whole set's view took most of them for interior.
+        for distance in [1e-3, 1e-4, 1e-5] {
+            let height = distance * 100.0;
+            for c in [[-0.75, distance], [0.25 + distance, 0.0]] {
+                assert_eq!(
+                    classify(c, 1_000_000, interior_threshold(height)),
+                    escape(c, 1_000_000).map_or(OrbitFate::Undecided, OrbitFate::Escaped),
+                    "{c:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tick_spacing_is_round() {
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * b;
+        assert!(close(tick_spacing(2.0, 8.0), 0.2));
+        assert!(close(tick_spacing(3.5, 8.0), 0.5));
+        assert!(close(tick_spacing(9.0, 10.0), 1.0));
+        assert!(close(tick_spacing(2e-9, 8.0), 2e-10));
+    }
+
+    #[test]
+    fn extended_point_keeps_tiny_pans() {
+        let start = ExtendedPoint::new([-0.75, 0.1]);
+        let mut point = start;
+        // Each step alone is below half an ulp of the coordinates and would vanish in f64.
+        for _ in 0..1000 {
+            point.translate([1e-18, -1e-18]);
+        }
+        assert_eq!(point.to_f64(), [-0.75 + 1e-15, 0.1 - 1e-15]);
+        let [dx, dy] = point.offset_from(start);
+        assert!((dx - 1e-15).abs() < 1e-27 && (dy + 1e-15).abs() < 1e-27);
+    }
+
+    #[test]
+    fn extended_escape_matches_f64() {
+        for i in 0..64 {
+            for j in 0..48 {
+                let c = [-2.5 + 3.5 * i as f64 / 63.0, -1.25 + 2.5 * j as f64 / 47.0];
+                let f64_escape = escape(c, 100).map(|e| e.iterations);
+                let extended = escape_extended(ExtendedPoint::new(c), 100).map(|e| e.iterations);
+                assert_eq!(f64_escape, extended, "{c:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn precision_escalates_with_zoom() {
+        let center = [-0.75, 0.1];
+        assert_eq!(Precision::required(center, 2.5 / 1080.0), Precision::F32);
+        assert_eq!(Precision::required(center, 1e-9), Precision::F64);
+        assert_eq!(Precision::required(center, 1e-15), Precision::DoubleDouble);
+        assert_eq!(Precision::required(center, 1e-40), Precision::Perturbation);
+        // Coordinates near the origin have precision to spare, even in f32.
+        assert_eq!(Precision::required([0.0, 0.0], 1e-40), Precision::F32);
+    }
+
+    #[test]
+    fn rendering_precision_escalates_unless_pinned() {
+        use Precision::*;
+        assert_eq!(F32.escalate(None), F64);
+        assert_eq!(F64.escalate(None), F64);
+        assert_eq!(DoubleDouble.escalate(None), DoubleDouble);
+        // Nothing renders with perturbation, double-double gets closest.
+        assert_eq!(Perturbation.escalate(None), DoubleDouble);
+        assert_eq!(DoubleDouble.escalate(Some(F64)), F64);
+        assert_eq!(F64.escalate(Some(DoubleDouble)), DoubleDouble);
+    }
+}
@@ -0,0 +1,307 @@
+//! A CPU implementation of the fragment shader, for checking and comparing against the GPU.
+
+use rayon::prelude::*;
+
+use crate::{
+    core,
+    palette::{Palette, PALETTE_SIZE},
+    MandelbrotUniform,
+};
+
+/// An escape time fractal the CPU renderer can draw, so adding one doesn't touch the render
+/// loop.
+pub trait Fractal: Sync {
+    /// How the orbit of the pixel at `point` escapes past the squared radius `bailout_sqr`, or
+    /// `None` if it doesn't within `max_iterations`.
+    fn iterate(
+        &self,
+        point: [f64; 2],
+        max_iterations: u32,
+        bailout_sqr: f64,
+    ) -> Option<core::Escape>;
+}
+
+/// The Mandelbrot set, iterating `z = z^2 + point` from 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mandelbrot;
+
+impl Fractal for Mandelbrot {
+    fn iterate(
+        &self,
+        point: [f64; 2],
+        max_iterations: u32,
+        bailout_sqr: f64,
+    ) -> Option<core::Escape> {
+        core::escape_with_bailout(point, max_iterations, bailout_sqr)
+    }
+}
+
+/// The Julia set of `c`, iterating `z = z^2 + c` from `point`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Julia {
+    pub c: [f64; 2],
+}
+
+impl Fractal for Julia {
+    fn iterate(
+        &self,
+        point: [f64; 2],
+        max_iterations: u32,
+        bailout_sqr: f64,
+    ) -> Option<core::Escape> {
+        core::julia_escape_with_bailout(point, self.c, max_iterations, bailout_sqr)
+    }
+}
+
+/// The Burning Ship, iterating `z = (|Re z| + i|Im z|)^2 + point` from 0, or its Julia set of
+/// `julia_c` iterating from `point`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BurningShip {
+    pub julia_c: Option<[f64; 2]>,
+}
+
+impl Fractal for BurningShip {
+    fn iterate(
+        &self,
+        point: [f64; 2],
+        max_iterations: u32,
+        bailout_sqr: f64,
+    ) -> Option<core::Escape> {
+        match self.julia_c {
+            Some(c) => core::burning_ship_escape(point, c, max_iterations, bailout_sqr),
+            None => core::burning_ship_escape([0.0; 2], point, max_iterations, bailout_sqr),
+        }
+    }
+}
+
+/// The fractal `uniform` shows, chosen by its flags the way the shader's `orbit_start` and
+/// `fold` do.
+pub fn fractal(uniform: &MandelbrotUniform) -> Box<dyn Fractal> {
+    let julia_c = uniform
+        .flag(MandelbrotUniform::FLAG_JULIA)
+        .then_some(uniform.julia_c);
+    match julia_c {
+        _ if uniform.flag(MandelbrotUniform::FLAG_BURNING_SHIP) => {
+            Box::new(BurningShip { julia_c })
+        }
+        Some(c) => Box::new(Julia { c }),
+        None => Box::new(Mandelbrot),
+    }
+}
+
+/// The squared radius `uniform`'s orbits escape at, see [`core::escape_radius`].
+pub fn bailout_sqr(uniform: &MandelbrotUniform) -> f64 {
+    let smooth = uniform.flag(MandelbrotUniform::FLAG_SMOOTH_COLORING);
+    core::escape_radius(uniform.escape_radius as f64, smooth).powi(2)
+}
+
+/// Renders the view described by `uniform` into `width * height` linear RGBA pixels, row by
+/// row starting at the top. Interior detection, contour lines, derivative anti-aliasing, escape
+/// norms other than the Euclidean one, the under-iteration tint and random supersampling aren't
+/// mirrored, so interior points are plain `interior_color`. [`MandelbrotUniform::FLAG_BOUNDARY`]
+/// compares each pixel with its right and lower neighbours, like the shader's adaptive
+/// anti-aliasing does.
+pub fn render(
+    uniform: &MandelbrotUniform,
+    palette: &Palette,
+    width: u32,
+    height: u32,
+) -> Vec<[f32; 4]> {
+    render_fractal(&*fractal(uniform), uniform, palette, width, height)
+}
+
+/// Like [`render`], but draws `fractal` whatever `uniform`'s flags select.
+pub fn render_fractal(
+    fractal: &dyn Fractal,
+    uniform: &MandelbrotUniform,
+    palette: &Palette,
+    width: u32,
+    height: u32,
+) -> Vec<[f32; 4]> {
+    let max_its = uniform.max_iterations;
+    let bailout_sqr = bailout_sqr(uniform);
+    let mut escapes = vec![None; width as usize * height as usize];
+    escapes
+        .par_chunks_mut(width as usize)
+        .enumerate()
+        .for_each(|(y, row)| {
+            // Sample pixel centers with y pointing up, like the shader's uv coordinates.
+            let v = 1.0 - (y as f64 + 0.5) / height as f64;
+            for (x, escape) in row.iter_mut().enumerate() {
+                let u = (x as f64 + 0.5) / width as f64;
+                *escape = fractal.iterate(uniform.uv_to_complex(u, v), max_its, bailout_sqr);
+            }
+        });
+    if uniform.flag(MandelbrotUniform::FLAG_BOUNDARY) {
+        return boundary(&escapes, width as usize, max_its);
+    }
+
+    escapes
+        .par_iter()
+        .map(|&escape| {
+            let [r, g, b] = match escape {
+                Some(escape) => {
+                    let smooth = core::smooth_iterations(escape) as f32;
+                    let its = if uniform.flag(MandelbrotUniform::FLAG_SMOOTH_COLORING) {
+                        smooth
+                    } else {
+                        escape.iterations as f32
+                    };
+                    let t = its / max_its as f32;
+                    let color_t = if uniform.color_period > 0.0 {
+                        (smooth / uniform.color_period).fract()
+                    } else {
+                        t
+                    };
+                    let boost = 1.0 + (uniform.hdr_peak - 1.0) * t * t;
+                    sample_palette(palette, color_t).map(|c| c * boost)
+                }
+                None => {
+                    let [r, g, b, _] = uniform.interior_color;
+                    [r, g, b]
+                }
+            };
+            [r, g, b, 1.0]
+        })
+        .collect()
+}
+
+// Escape time change between neighboring pixels above which a boundary line is drawn, as in
+// the shader.
+const BOUNDARY_ITS_JUMP: f32 = 4.0;
+
+// Line art of the boundary like the shader's `neighbour_boundary_color`: black where the right
+// or lower neighbour of a pixel is on the other side of the boundary, or where the escape time
+// changes sharply, and white elsewhere. The last column and row compare with the one before.
+fn boundary(escapes: &[Option<core::Escape>], width: usize, max_its: u32) -> Vec<[f32; 4]> {
+    let height = escapes.len() / width;
+    let smooth =
+        |i: usize| escapes[i].map_or(max_its as f32, |e| core::smooth_iterations(e) as f32);
+    (0..escapes.len())
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let neighbour = |n: usize, len: usize, step: usize| match n {
+                _ if n + 1 < len => i + step,
+                0 => i,
+                _ => i - step,
+            };
+            let (right, below) = (neighbour(x, width, 1), neighbour(y, height, width));
+            let inside = |j: usize| escapes[j].is_none() as u8;
+            let crosses = inside(right) != inside(i) || inside(below) != inside(i);
+            let its_width = (smooth(right) - smooth(i)).abs() + (smooth(below) - smooth(i)).abs();
+            let t = ((its_width / BOUNDARY_ITS_JUMP - 0.5) / 0.5).clamp(0.0, 1.0);
+            let line = if crosses {
+                1.0
+            } else {
+                t * t * (3.0 - 2.0 * t)
+            };
+            let shade = 1.0 - line;
+            [shade, shade, shade, 1.0]
+        })
+        .collect()
+}
+
+// Same as `sample_palette` in the shader.
+fn sample_palette(palette: &Palette, t: f32) -> [f32; 3] {
+    let x = t.clamp(0.0, 1.0) * (PALETTE_SIZE - 1) as f32;
+    let i = x as usize;
+    let j = (i + 1).min(PALETTE_SIZE - 1);
+    let f = x.fract();
+    std::array::from_fn(|c| palette[i][c] + (palette[j][c] - palette[i][c]) * f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_select_the_fractal() {
+        let mut uniform = MandelbrotUniform::default_view(1.0);
+        let (c, z) = ([-1.8, -0.05], [0.3, -0.2]);
+        let escape_of = |uniform: &MandelbrotUniform, point| {
+            fractal(uniform).iterate(point, 256, core::BAILOUT_SQR)
+        };
+
+        assert_eq!(escape_of(&uniform, c), core::escape(c, 256));
+        uniform.set_flag(MandelbrotUniform::FLAG_BURNING_SHIP, true);
+        assert_eq!(
+            escape_of(&uniform, c),
+            core::burning_ship_escape([0.0; 2], c, 256, core::BAILOUT_SQR)
+        );
+        // Unlike the Mandelbrot set, the Burning Ship isn't symmetric about the real axis.
+        assert_eq!(escape_of(&uniform, [-0.5, -0.5]), None);
+        assert!(escape_of(&uniform, [-0.5, 0.5]).is_some());
+
+        uniform.julia_c = [-0.5, 0.6];
+        uniform.set_flag(MandelbrotUniform::FLAG_JULIA, true);
+        assert_eq!(
+            escape_of(&uniform, z),
+            core::burning_ship_escape(z, uniform.julia_c, 256, core::BAILOUT_SQR)
+        );
+        uniform.set_flag(MandelbrotUniform::FLAG_BURNING_SHIP, false);
+        assert_eq!(
+            escape_of(&uniform, z),
+            core::julia_escape(z, uniform.julia_c, 256)
+        );
+    }
+
+    #[test]
+    fn smooth_coloring_is_continuous_across_iteration_counts() {
+        // The largest jump of the smooth escape time where the iteration count changes along the
+        // real axis right of the set, found by bisecting between points 1e-3 apart.
+        let worst_step = |uniform: &MandelbrotUniform| {
+            let (fractal, bailout_sqr) = (fractal(uniform), bailout_sqr(uniform));
+            let escape = |x: f64| fractal.iterate([x, 0.0], 1000, bailout_sqr).unwrap();
+            let mut worst: f64 = 0.0;
+            for i in 0..1000 {
+                let (mut a, mut b) = (0.3 + i as f64 * 1e-3, 0.3 + (i + 1) as f64 * 1e-3);
+                let its = escape(a).iterations;
+                if escape(b).iterations == its {
+                    continue;
+                }
+                while b - a > 1e-12 {
+                    let mid = (a + b) / 2.0;
+                    if escape(mid).iterations == its {
+                        a = mid;
+                    } else {
+                        b = mid;
+                    }
+                }
+                let step = core::smooth_iterations(escape(a)) - core::smooth_iterations(escape(b));
+                worst = worst.max(step.abs());
+            }
+            worst
+        };
+        let mut uniform = MandelbrotUniform::default_view(1.0);
+        // Escaping at radius 2 leaves visible steps.
+        assert!(worst_step(&uniform) > 0.1);
+        uniform.set_flag(MandelbrotUniform::FLAG_SMOOTH_COLORING, true);
+        assert!(worst_step(&uniform) < 0.005);
+    }
+
+    #[test]
+    fn boundary_lines_follow_the_set() {
+        let (width, height) = (64, 48);
+        let mut uniform = MandelbrotUniform::default_view(width as f64 / height as f64);
+        uniform.set_flag(MandelbrotUniform::FLAG_BOUNDARY, true);
+        let pixels = render(&uniform, &[[0.0; 4]; PALETTE_SIZE], width, height);
+        let at = |[x, y]: [f64; 2]| {
+            let u = (x - uniform.min_x) / uniform.width();
+            let v = (y - uniform.min_y) / uniform.height;
+            let (col, row) = (
+                (u * width as f64) as usize,
+                ((1.0 - v) * height as f64) as usize,
+            );
+            pixels[row * width as usize + col][0]
+        };
+        // Inside the main cardioid and far outside the set there's nothing to draw.
+        assert_eq!(at([-0.2, 0.0]), 1.0);
+        assert_eq!(at([0.9, 1.1]), 1.0);
+        // The cusp of the cardioid is on the boundary.
+        assert_eq!(at([0.25, 0.0]), 0.0);
+        assert!(pixels
+            .iter()
+            .all(|p| (0.0..=1.0).contains(&p[0]) && p[0] == p[1]));
+    }
+}
@@ -0,0 +1,71 @@
+//! Loading a user supplied WGSL shader (`--shader path.wgsl`) to draw the main view with instead
+//! of the built-in `fs_main`.
+//!
+//! # Contract
+//!
+//! The shader is compiled with the `SHADER_F64` feature enabled, so it may use `f64`, unless the
+//! GPU doesn't support it. The uniform then has every `f64` rounded to `f32`, like the
+//! `MandelbrotUniform` struct of `shader.wgsl` with its `float` alias set to `f32`. It has to
+//! provide two entry points:
+//!
+//! - `vs_main`, a vertex shader without vertex buffers. The host draws a 4 vertex triangle strip
+//!   per frame, with `@builtin(vertex_index)` running from 0 to 3, and doesn't cull faces. The
+//!   built-in `vs_main` can be copied verbatim.
+//! - `fs_main`, a fragment shader returning the color as `@location(0) vec4<f32>`. The target is
+//!   the window surface, in linear color if it's an sRGB format; alpha is ignored.
+//!
+//! Group 0 holds the same resources as for the built-in shader. Both are optional, but when
+//! declared they must match exactly:
+//!
+//! - `@group(0) @binding(0) var<uniform>`: the view, a [`crate::MandelbrotUniform`] with the
+//!   layout of the `MandelbrotUniform` struct in `shader.wgsl`.
+//! - `@group(0) @binding(1) var<uniform> palette: array<vec4<f32>, 256>`: the palette colors in
+//!   linear RGB, alpha unused.
+//!
+//! No other groups are bound. Only the main view (including the comparison split and grid) uses
+//! the custom shader; adaptive anti-aliasing, progressive and CPU rendering, screenshots and
+//! headless rendering keep using the built-in one.
+
+use std::{fmt, io, path::Path};
+
+use crate::gpu;
+
+#[derive(Debug)]
+pub enum CustomShaderError {
+    Read(io::Error),
+    /// The shader didn't compile, or its entry points or bindings don't fit the contract.
+    Invalid(wgpu::Error),
+}
+
+impl fmt::Display for CustomShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "failed to read the shader: {e}"),
+            Self::Invalid(e) => write!(f, "the shader doesn't fit the contract: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CustomShaderError {}
+
+/// Compiles the shader at `path` and checks that a main view pipeline with `layout` and
+/// `format` can be built from it.
+pub async fn load(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    path: &Path,
+) -> Result<wgpu::ShaderModule, CustomShaderError> {
+    let source = std::fs::read_to_string(path).map_err(CustomShaderError::Read)?;
+    // Catch the validation errors instead of letting the uncaptured error handler panic.
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: path.to_str(),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    gpu::create_pipeline(device, layout, &shader, "fs_main", format);
+    match device.pop_error_scope().await {
+        Some(e) => Err(CustomShaderError::Invalid(e)),
+        None => Ok(shader),
+    }
+}
@@ -0,0 +1,210 @@
+//! A minimal OpenEXR encoder for 32-bit float images, writing a single part of uncompressed
+//! scanlines.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+// Version 2 with no flags set, i.e. a single part scanline image.
+const VERSION: u32 = 2;
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+/// Writes `width * height` images, one per named channel with its texels row by row starting at
+/// the top, into a single EXR file.
+pub fn write(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    channels: &[(&str, &[f32])],
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    encode(&mut file, width, height, channels)?;
+    file.flush()
+}
+
+/// Like [`write`], into any writer.
+pub fn encode(
+    mut out: impl Write,
+    width: u32,
+    height: u32,
+    channels: &[(&str, &[f32])],
+) -> io::Result<()> {
+    let mut channels = channels.to_vec();
+    // Readers expect the channel list and the data in each scanline sorted by name.
+    channels.sort_by_key(|&(name, _)| name);
+    for (name, data) in &channels {
+        assert_eq!(
+            data.len(),
+            width as usize * height as usize,
+            "channel {name} has the wrong size"
+        );
+    }
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for (name, _) in &channels {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+        // pLinear and three reserved bytes.
+        chlist.extend_from_slice(&[0; 4]);
+        // x and y sampling.
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    attribute(&mut header, "channels", "chlist", &chlist);
+    // No compression.
+    attribute(&mut header, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y, i.e. top to bottom.
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    // Uncompressed files store one scanline per chunk, each prefixed with its y coordinate and
+    // size and listed in an offset table right after the header.
+    let line_size = width as usize * channels.len() * 4;
+    let chunk_size = 8 + line_size;
+    let first_chunk = header.len() + height as usize * 8;
+
+    out.write_all(&header)?;
+    for y in 0..height as usize {
+        out.write_all(&((first_chunk + y * chunk_size) as u64).to_le_bytes())?;
+    }
+    for y in 0..height as usize {
+        out.write_all(&(y as i32).to_le_bytes())?;
+        out.write_all(&(line_size as i32).to_le_bytes())?;
+        for (_, data) in &channels {
+            let row = &data[y * width as usize..][..width as usize];
+            for value in row {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, ty: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(ty.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads back what `encode` wrote, following the layout the OpenEXR spec describes rather
+    // than the encoder's own offsets: the attributes, the channel list and each scanline's chunk
+    // at the offset the table gives for it.
+    fn decode(bytes: &[u8]) -> (i32, i32, Vec<(String, Vec<f32>)>) {
+        fn take<'a>(rest: &mut &'a [u8], n: usize) -> &'a [u8] {
+            let (head, tail) = rest.split_at(n);
+            *rest = tail;
+            head
+        }
+        fn string(rest: &mut &[u8]) -> String {
+            let end = rest.iter().position(|&c| c == 0).unwrap();
+            let s = String::from_utf8(take(rest, end).to_vec()).unwrap();
+            take(rest, 1);
+            s
+        }
+        let i32_at = |b: &[u8], i: usize| i32::from_le_bytes(b[i..i + 4].try_into().unwrap());
+
+        let mut rest = bytes;
+        assert_eq!(take(&mut rest, 4), MAGIC);
+        assert_eq!(take(&mut rest, 4), VERSION.to_le_bytes());
+        let (mut names, mut window) = (Vec::new(), None);
+        loop {
+            let name = string(&mut rest);
+            if name.is_empty() {
+                break;
+            }
+            let ty = string(&mut rest);
+            let size = i32_at(take(&mut rest, 4), 0) as usize;
+            let mut value = take(&mut rest, size);
+            match (name.as_str(), ty.as_str()) {
+                ("channels", "chlist") => {
+                    while value[0] != 0 {
+                        names.push(string(&mut value));
+                        assert_eq!(i32_at(take(&mut value, 16), 0), PIXEL_TYPE_FLOAT);
+                    }
+                }
+                ("dataWindow", "box2i") => window = Some([0, 4, 8, 12].map(|i| i32_at(value, i))),
+                ("compression", _) => assert_eq!(value, [0]),
+                _ => {}
+            }
+        }
+        let [x0, y0, x1, y1] = window.unwrap();
+        let (width, height) = (x1 - x0 + 1, y1 - y0 + 1);
+
+        let mut channels: Vec<_> = names.into_iter().map(|n| (n, Vec::new())).collect();
+        for line in 0..height {
+            let offset = u64::from_le_bytes(take(&mut rest, 8).try_into().unwrap()) as usize;
+            assert_eq!(i32_at(bytes, offset), line);
+            let size = i32_at(bytes, offset + 4) as usize;
+            let mut data = bytes[offset + 8..][..size].chunks_exact(4);
+            for (_, values) in &mut channels {
+                for _ in 0..width {
+                    let value = data.next().unwrap().try_into().unwrap();
+                    values.push(f32::from_le_bytes(value));
+                }
+            }
+            assert!(data.next().is_none());
+        }
+        (width, height, channels)
+    }
+
+    #[test]
+    fn encoded_images_read_back() {
+        let (width, height) = (3, 2);
+        let iterations = [0.5, 1.0, 2.5, 100.0, f32::MAX, 0.0];
+        let red = [1.0, 0.0, 0.25, -1.0, 1e-20, 3.0];
+        let mut bytes = Vec::new();
+        encode(
+            &mut bytes,
+            width,
+            height,
+            &[("iterations", &iterations), ("R", &red)],
+        )
+        .unwrap();
+
+        let (w, h, channels) = decode(&bytes);
+        assert_eq!((w, h), (3, 2));
+        // Sorted by name.
+        assert_eq!(
+            channels,
+            [
+                ("R".to_owned(), red.to_vec()),
+                ("iterations".to_owned(), iterations.to_vec())
+            ]
+        );
+    }
+}
@@ -0,0 +1,606 @@
+//! GPU resources shared between the windowed and the headless renderer.
+
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::{exr, palette::Palette, uniform::MandelbrotUniformF32, MandelbrotUniform};
+
+/// The float type `shader.wgsl` computes coordinates in. `f64` needs `SHADER_F64`, which many
+/// integrated GPUs and all of WebGPU lack. Without it everything is computed in `f32`, which
+/// turns blocky at zooms of around 1e5 already.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderFloat {
+    F64,
+    F32,
+}
+
+impl ShaderFloat {
+    /// `F64` if `adapter` supports it.
+    pub fn for_adapter(adapter: &wgpu::Adapter) -> Self {
+        if adapter.features().contains(wgpu::Features::SHADER_F64) {
+            Self::F64
+        } else {
+            Self::F32
+        }
+    }
+
+    /// Logs which one is used, warning about the limited zoom with `F32`.
+    pub fn log_choice(self) {
+        match self {
+            Self::F64 => log::info!("computing in f64"),
+            Self::F32 => log::warn!(
+                "the GPU doesn't support f64 in shaders, computing in f32, which only zooms about \
+                 a hundred thousand times in"
+            ),
+        }
+    }
+
+    /// What the device has to be requested with.
+    pub fn features(self) -> wgpu::Features {
+        match self {
+            Self::F64 => wgpu::Features::SHADER_F64,
+            Self::F32 => wgpu::Features::empty(),
+        }
+    }
+
+    /// `shader.wgsl`, with its `float` and `float2` aliases switched to `f32` for `F32`.
+    fn source(self) -> Cow<'static, str> {
+        let source = include_str!("shader.wgsl");
+        match self {
+            Self::F64 => source.into(),
+            Self::F32 => [
+                ("alias float = f64;", "alias float = f32;"),
+                ("alias float2 = vec2<f64>;", "alias float2 = vec2<f32>;"),
+            ]
+            .into_iter()
+            .fold(source.to_owned(), |source, (from, to)| {
+                assert!(source.contains(from), "shader.wgsl lacks `{from}`");
+                source.replacen(from, to, 1)
+            })
+            .into(),
+        }
+    }
+
+    /// Compiles [`Self::source`].
+    pub fn shader(self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(self.source()),
+        })
+    }
+
+    /// `uniform` laid out like the `MandelbrotUniform` struct of [`Self::shader`].
+    pub fn uniform_bytes(self, uniform: &MandelbrotUniform) -> Vec<u8> {
+        match self {
+            Self::F64 => bytemuck::bytes_of(uniform).to_vec(),
+            Self::F32 => bytemuck::bytes_of(&MandelbrotUniformF32::from(uniform)).to_vec(),
+        }
+    }
+}
+
+impl std::fmt::Display for ShaderFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::F64 => "f64",
+            Self::F32 => "f32",
+        })
+    }
+}
+
+/// The uniform and palette buffers every entry point of `shader.wgsl` reads from group 0.
+pub struct ViewBindings {
+    pub float: ShaderFloat,
+    pub uniform_buffer: wgpu::Buffer,
+    pub palette_buffer: wgpu::Buffer,
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl ViewBindings {
+    pub fn new(
+        device: &wgpu::Device,
+        float: ShaderFloat,
+        uniform: &MandelbrotUniform,
+        palette: &Palette,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mandelbrot Buffer"),
+            contents: &float.uniform_bytes(uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let palette_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Palette Buffer"),
+            contents: bytemuck::cast_slice(palette),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bind_group_layout"),
+            entries: &[uniform_entry(0), uniform_entry(1)],
+        });
+
+        let bind_group = bind_uniform(device, &layout, &uniform_buffer, &palette_buffer);
+
+        Self {
+            float,
+            uniform_buffer,
+            palette_buffer,
+            layout,
+            bind_group,
+        }
+    }
+
+    /// Creates a uniform buffer for drawing another view alongside the main one, bound together
+    /// with the shared palette.
+    pub fn create_secondary(
+        &self,
+        device: &wgpu::Device,
+        uniform: &MandelbrotUniform,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Secondary Mandelbrot Buffer"),
+            contents: &self.float.uniform_bytes(uniform),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = bind_uniform(device, &self.layout, &uniform_buffer, &self.palette_buffer);
+        (uniform_buffer, bind_group)
+    }
+}
+
+fn bind_uniform(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    palette_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: palette_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+/// Format images are rendered in for saving to disk.
+pub const IMAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Draws the fullscreen quad with `pipeline` (targeting [`IMAGE_FORMAT`]) into an offscreen
+/// texture and reads the result back.
+pub fn render_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    width: u32,
+    height: u32,
+) -> image::RgbImage {
+    // Alpha is always 1 unless the interior is transparent, so drop it.
+    let rgba = render_rgba_image(device, queue, pipeline, bind_group, width, height);
+    image::DynamicImage::ImageRgba8(rgba).into_rgb8()
+}
+
+/// Like [`render_image`], but keeping the alpha channel, for views with
+/// [`MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR`](crate::MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR).
+pub fn render_rgba_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    width: u32,
+    height: u32,
+) -> image::RgbaImage {
+    let data = render_texture(
+        device,
+        queue,
+        pipeline,
+        bind_group,
+        IMAGE_FORMAT,
+        width,
+        height,
+    );
+    image::RgbaImage::from_raw(width, height, data).expect("readback has the image's size")
+}
+
+/// Draws the fullscreen quad with `pipeline` into an offscreen texture of the given format and
+/// reads back its texels, row by row starting at the top.
+pub fn render_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Image Texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    // Rows copied out of a texture have to be aligned to 256 bytes.
+    let texel_size = format
+        .block_copy_size(None)
+        .expect("color formats have a texel size");
+    let unpadded_row = width * texel_size;
+    let padded_row = unpadded_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Image Readback Buffer"),
+        size: u64::from(padded_row) * u64::from(height),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Image Encoder"),
+    });
+    draw_quad(&mut encoder, pipeline, bind_group, &view);
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: None,
+            },
+        },
+        size,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    read_buffer(device, &readback)
+        .chunks_exact(padded_row as usize)
+        .flat_map(|row| &row[..unpadded_row as usize])
+        .copied()
+        .collect()
+}
+
+/// Records a pass drawing the fullscreen quad with `pipeline` over all of `view`.
+pub fn draw_quad(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    view: &wgpu::TextureView,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Image Pass"),
+        timestamp_writes: None,
+        occlusion_query_set: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..4, 0..1);
+}
+
+/// Pipelines rendering the linear color and the smooth escape time as 32-bit floats, for saving
+/// the full range of both to EXR files.
+pub struct FloatPipelines {
+    color: wgpu::RenderPipeline,
+    smooth: wgpu::RenderPipeline,
+}
+
+impl FloatPipelines {
+    /// `layout` must bind [`ViewBindings`] to group 0 and nothing else.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+    ) -> Self {
+        Self {
+            color: create_pipeline(
+                device,
+                layout,
+                shader,
+                "fs_linear",
+                wgpu::TextureFormat::Rgba32Float,
+            ),
+            smooth: create_pipeline(
+                device,
+                layout,
+                shader,
+                "fs_smooth",
+                wgpu::TextureFormat::R32Float,
+            ),
+        }
+    }
+
+    /// Renders the smooth escape time of every pixel of the view bound by `bind_group`, row by
+    /// row starting at the top. Points that didn't escape hold the iteration limit.
+    pub fn smooth_iterations(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group: &wgpu::BindGroup,
+        width: u32,
+        height: u32,
+    ) -> Vec<f32> {
+        let format = wgpu::TextureFormat::R32Float;
+        let data = render_texture(
+            device,
+            queue,
+            &self.smooth,
+            bind_group,
+            format,
+            width,
+            height,
+        );
+        // The mapped bytes aren't necessarily aligned for f32.
+        bytemuck::pod_collect_to_vec(&data)
+    }
+
+    /// Renders the view bound by `bind_group` and saves it as an EXR with the linear color in
+    /// the R, G and B channels and the smooth escape time in an `iterations` channel.
+    pub fn write_exr(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group: &wgpu::BindGroup,
+        width: u32,
+        height: u32,
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<()> {
+        let render = |pipeline, format| {
+            let data = render_texture(device, queue, pipeline, bind_group, format, width, height);
+            bytemuck::pod_collect_to_vec::<u8, f32>(&data)
+        };
+        let color = render(&self.color, wgpu::TextureFormat::Rgba32Float);
+        let iterations = self.smooth_iterations(device, queue, bind_group, width, height);
+        let channel = |i| color.iter().skip(i).step_by(4).copied().collect::<Vec<_>>();
+        let (r, g, b) = (channel(0), channel(1), channel(2));
+        exr::write(
+            path,
+            width,
+            height,
+            &[("R", &r), ("G", &g), ("B", &b), ("iterations", &iterations)],
+        )
+    }
+}
+
+/// Creates a pipeline drawing the fullscreen quad with the given fragment entry point.
+pub fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fs_entry_point: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    // Formats like R32Float aren't blendable, and the fullscreen quad overwrites everything
+    // anyways.
+    let blendable =
+        format.sample_type(None, None) == Some(wgpu::TextureSampleType::Float { filterable: true });
+    create_blend_pipeline(
+        device,
+        layout,
+        shader,
+        fs_entry_point,
+        format,
+        blendable.then_some(wgpu::BlendState::REPLACE),
+    )
+}
+
+/// Like [`create_pipeline`], but blending the quad onto the target with `blend`.
+pub fn create_blend_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fs_entry_point: &str,
+    format: wgpu::TextureFormat,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(fs_entry_point),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fs_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Culling can't save anything on a fullscreen quad, it would only make the whole
+            // image disappear if `vs_main` ever emitted the vertices in the wrong order.
+            cull_mode: None,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+/// Size of the largest buffer `device` can bind as storage.
+pub fn max_storage_buffer_size(device: &wgpu::Device) -> u64 {
+    let limits = device.limits();
+    u64::from(limits.max_storage_buffer_binding_size).min(limits.max_buffer_size)
+}
+
+/// Measures how long a render pass takes on the GPU with timestamp queries, which unlike timing
+/// submissions on the CPU leaves out vsync and whatever else the GPU was busy with.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback: Arc<wgpu::Buffer>,
+    // Whether `readback` is mapped or waiting to be, so the next timestamps can't be copied
+    // there yet.
+    busy: Arc<AtomicBool>,
+    // Nanoseconds per timestamp tick.
+    period: f64,
+}
+
+impl GpuTimer {
+    /// A timer for `device`, if it was requested with `TIMESTAMP_QUERY`.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timer Queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let buffer = |label, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: 2 * std::mem::size_of::<u64>() as u64,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        Some(Self {
+            query_set,
+            resolve_buffer: buffer(
+                "Timer Resolve Buffer",
+                wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            ),
+            readback: Arc::new(buffer(
+                "Timer Readback Buffer",
+                wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            )),
+            busy: Arc::new(AtomicBool::new(false)),
+            period: queue.get_timestamp_period() as f64,
+        })
+    }
+
+    /// What to time a render pass with, or `None` while the last measurement is still being
+    /// read. A pass timed with them has to be followed by [`Self::resolve`] and [`Self::read`].
+    pub fn timestamp_writes(&self) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        (!self.busy.load(Ordering::Acquire)).then_some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        })
+    }
+
+    /// Copies the timestamps of the timed pass to where [`Self::read`] reads them from.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        let size = self.resolve_buffer.size();
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback, 0, size);
+    }
+
+    /// Once the submission resolving the timestamps is done, stores how long the pass took,
+    /// times `scale`, in `nanos`.
+    pub fn read(&self, scale: u32, nanos: Arc<AtomicU64>) {
+        self.busy.store(true, Ordering::Release);
+        let (readback, busy, period) = (self.readback.clone(), self.busy.clone(), self.period);
+        self.readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    let [start, end]: [u64; 2] =
+                        bytemuck::pod_read_unaligned(&readback.slice(..).get_mapped_range());
+                    readback.unmap();
+                    // Some drivers occasionally report the end before the start.
+                    let elapsed = end.saturating_sub(start) as f64 * period;
+                    nanos.store(elapsed as u64 * u64::from(scale), Ordering::Relaxed);
+                }
+                busy.store(false, Ordering::Release);
+            });
+    }
+}
+
+/// Maps a `MAP_READ` buffer and copies out its contents, blocking until the GPU is done.
+pub fn read_buffer(device: &wgpu::Device, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("map_async callback was dropped")
+        .expect("failed to map buffer");
+    let data = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_shader_variants_validate() {
+        // The f32 variant must not need SHADER_F64 after all.
+        let capabilities = naga::valid::Capabilities::all();
+        for (float, capabilities) in [
+            (ShaderFloat::F64, capabilities),
+            (
+                ShaderFloat::F32,
+                capabilities - naga::valid::Capabilities::FLOAT64,
+            ),
+        ] {
+            let source = float.source();
+            let module = naga::front::wgsl::parse_str(&source)
+                .unwrap_or_else(|e| panic!("{float}: {}", e.emit_to_string(&source)));
+            naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+                .validate(&module)
+                .unwrap_or_else(|e| panic!("{float}: {}", e.emit_to_string(&source)));
+        }
+    }
+}
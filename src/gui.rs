@@ -0,0 +1,3801 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    keyboard::{Key, NamedKey},
+    window::{Fullscreen, Icon, Window, WindowBuilder},
+};
+
+use crate::{
+    area::AreaEstimate,
+    batch, benchmark,
+    builder::ExplorerBuilder,
+    cli,
+    config::{self, Action, Config, Keybindings},
+    core, cpu, custom_shader, gpu,
+    headless::{self, Headless, ImageFormat},
+    hud,
+    manifest::Manifest,
+    palette, precision_check,
+    uniform::{JULIA_VIEW, NORM_NAMES},
+    MandelbrotUniform,
+};
+
+struct State {
+    pub window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    // Set once the device is gone, e.g. after a driver update or GPU reset. Everything created
+    // from it is unusable then, so the event loop rebuilds the whole state.
+    device_lost: Arc<AtomicBool>,
+    // What the shader computes in, which decides the layout uniforms are written in.
+    shader_float: gpu::ShaderFloat,
+    config: wgpu::SurfaceConfiguration,
+    // Everything the surface could be configured with, cycled through with Ctrl+R.
+    surface_formats: Vec<wgpu::TextureFormat>,
+    // Picked with Ctrl+R, kept for the state recreated after suspending.
+    chosen_surface_format: Option<wgpu::TextureFormat>,
+    // The extended range format the surface uses for HDR output, if asked for and available.
+    hdr_format: Option<wgpu::TextureFormat>,
+    // What `surface_pipelines` are built from, for rebuilding them when Ctrl+R switches formats.
+    pipeline_sources: PipelineSources,
+    surface_pipelines: SurfacePipelines,
+    size: winit::dpi::PhysicalSize<u32>,
+    mandelbrot_uniform: MandelbrotUniform,
+    mandelbrot_buffer: wgpu::Buffer,
+    palette_buffer: wgpu::Buffer,
+    mandelbrot_bind_group: wgpu::BindGroup,
+    cursor_pos: winit::dpi::PhysicalPosition<f64>,
+    dragging: bool,
+    // Whether the view keeps gliding after a drag is released.
+    inertia: bool,
+    // Panning velocity in view widths/heights per second, tracked while dragging and decaying
+    // after release.
+    pan_velocity: (f64, f64),
+    // Log of the factor touchpad scrolling still has to zoom by. It's spread over the next
+    // frames rather than applied at once, see `scroll_zoom_step`.
+    pending_scroll_zoom: f64,
+    last_drag: Instant,
+    last_update: Instant,
+    escape_pipeline: wgpu::RenderPipeline,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    escape_view: wgpu::TextureView,
+    escape_bind_group: wgpu::BindGroup,
+    adaptive_aa: bool,
+    screenshot_pipeline: wgpu::RenderPipeline,
+    float_pipelines: gpu::FloatPipelines,
+    screenshot_format: ImageFormat,
+    // Whether screenshots get a JSON file describing the view next to them.
+    write_manifest: bool,
+    // Split screen comparison: the left side shows the live settings, the right side those from
+    // when the split was enabled. The value is the divider position as a fraction of the width.
+    split: Option<f64>,
+    dragging_divider: bool,
+    compare_uniform: MandelbrotUniform,
+    compare_buffer: wgpu::Buffer,
+    compare_bind_group: wgpu::BindGroup,
+    // Cross-fade after changing coloring settings: the uniform with the old settings and when
+    // the change happened. The new settings are drawn over the old ones with `fade_pipeline`,
+    // at an opacity of `mode_blend`.
+    fade: Option<(MandelbrotUniform, Instant)>,
+    mode_blend: f64,
+    fade_buffer: wgpu::Buffer,
+    fade_bind_group: wgpu::BindGroup,
+    // Comparison grid: the view drawn as this many tiles per side, each with a different
+    // iteration count. Uniforms for the largest grid are created up front.
+    grid: Option<u32>,
+    grid_tiles: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+    progressive: bool,
+    progressive_pipeline: wgpu::ComputePipeline,
+    progressive_layout: wgpu::BindGroupLayout,
+    progressive_target: Option<ProgressiveTarget>,
+    // Iterations accumulated in `progressive_target` so far, reset whenever the view changes.
+    progressive_iterations: u32,
+    // Texture the CPU renderer's output is uploaded to, recreated when the viewport changes size.
+    cpu_target: Option<(wgpu::Texture, wgpu::BindGroup)>,
+    cpu_rendering: bool,
+    // Precision the current view needs, to report when it changes.
+    precision: core::Precision,
+    // Precision to render in regardless of the view, for benchmarking. Without one the GPU
+    // renderer switches to double-double once the view needs it.
+    pinned_precision: Option<core::Precision>,
+    palette: palette::Palette,
+    // When set, the fractal is drawn letterboxed at this width / height ratio.
+    target_aspect: Option<f64>,
+    modifiers: winit::keyboard::ModifiersState,
+    // After `idle_delay` without input the view starts slowly zooming towards `idle_target` on
+    // its own, until the next input.
+    idle_delay: Option<Duration>,
+    idle_target: [f64; 2],
+    last_input: Instant,
+    // Views to step back and forth between with Ctrl+Z and Ctrl+Y, oldest first.
+    undo_history: Vec<MandelbrotUniform>,
+    redo_history: Vec<MandelbrotUniform>,
+    last_history_entry: Option<Instant>,
+    // Animates the view towards the target (from, to, start time), e.g. when undoing.
+    glide: Option<(MandelbrotUniform, MandelbrotUniform, Instant)>,
+    on_frame: Option<FrameCallback>,
+    frame_number: u64,
+    start_time: Instant,
+    // Last title set by `update_title`, and the name it starts with.
+    title: String,
+    window_title: String,
+    // Whether the window was resized to zero, leaving the surface at its previous size.
+    minimized: bool,
+    // Whether `mandelbrot_uniform` changed without being written to the GPU yet.
+    uniform_dirty: bool,
+    // Factor the +/- keys zoom by.
+    zoom_step: u32,
+    // Frame times of `--benchmark`, which keeps redrawing until it has enough of them.
+    benchmark: Option<benchmark::Recorder>,
+    // Recent frame times, shown in the title with Ctrl+T. The title is refreshed at most every
+    // FRAME_TIME_REFRESH, and `frame_time_shown` is when it last was.
+    frame_meter: benchmark::FrameMeter,
+    show_frame_time: bool,
+    frame_time_shown: Instant,
+    // How long the last full resolution render took on the GPU, in nanoseconds. Views that take
+    // longer than `HEAVY_RENDER_TIME` are rendered in the background instead. Measured with
+    // `timer` where the GPU supports timestamp queries, else by waiting for the GPU to finish.
+    render_time: Arc<AtomicU64>,
+    timer: Option<gpu::GpuTimer>,
+    // Time a frame may take with `--target-fps`, and the render time `max_iterations` was last
+    // adjusted for, so each measurement is only acted on once.
+    frame_budget: Option<Duration>,
+    budgeted_render_time: Duration,
+    background: Option<BackgroundRender>,
+    background_buffer: wgpu::Buffer,
+    background_bind_group: wgpu::BindGroup,
+    offscreen_pipeline: wgpu::RenderPipeline,
+    // Readout of the pixel under the cursor, shown in the title while `inspecting`. Each pixel
+    // is rendered on its own with `inspect_buffer` and read back by `inspect_probe`.
+    inspecting: bool,
+    inspection: Option<Inspection>,
+    inspect_probe: InspectProbe,
+    inspect_buffer: wgpu::Buffer,
+    inspect_bind_group: wgpu::BindGroup,
+    inspect_pipeline: wgpu::RenderPipeline,
+    // Whether the title shows how colors are encoded for the window, once that was toggled.
+    show_output_encoding: bool,
+    keybindings: Keybindings,
+    // `--seed`, which the random palette key's palettes are derived from, and how many of them
+    // were picked so far.
+    seed: Option<u64>,
+    random_palettes: u64,
+    // What resetting the view goes back to: the fractal, region and iteration limit the command
+    // line asked for, or the defaults.
+    start_view: MandelbrotUniform,
+    // Index into `palette::BUILTIN_NAMES` of the built-in palette last cycled to.
+    builtin_palette: usize,
+    // Zooming in towards the cursor every frame while the right mouse button is held.
+    warping: bool,
+    // Redrawing back to back even without input, toggled with Shift+P, for anything that
+    // animates.
+    continuous: bool,
+    // Glow around the bright parts of the view. They're rendered at 1 / BLOOM_SCALE resolution
+    // into the first of `bloom_targets`, blurred there and back, and added on top of the view,
+    // weighted by `bloom_intensity`.
+    bloom: bool,
+    bloom_intensity: f64,
+    bloom_targets: Option<([u32; 2], [OffscreenTarget; 2])>,
+    bloom_extract_pipeline: wgpu::RenderPipeline,
+    bloom_blur_pipelines: [wgpu::RenderPipeline; 2],
+    // Estimate of the set's area within the view it was taken for, refined a little every frame
+    // the view stays the same. `None` while it isn't shown.
+    area_estimate: Option<(MandelbrotUniform, AreaEstimate)>,
+    // Whether the title shows the center, once it was nudged with Ctrl+arrows.
+    show_center: bool,
+    // Text overlay in the top left corner, toggled with F1. `hud_target` holds the lines
+    // rasterized at the scale in `hud_drawn`, and is only redrawn when either changes.
+    hud: bool,
+    hud_target: Option<(wgpu::Texture, wgpu::BindGroup)>,
+    hud_drawn: (Vec<String>, u32),
+    // Magnifier drawn around the cursor while it's over the window, with its own uniform.
+    loupe: bool,
+    hovering: bool,
+    loupe_buffer: wgpu::Buffer,
+    loupe_bind_group: wgpu::BindGroup,
+}
+
+/// Information about a frame that was just presented, passed to the frame callback.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameInfo {
+    /// The uniform the frame was rendered with.
+    pub uniform: MandelbrotUniform,
+    /// Number of frames rendered before this one.
+    pub frame_number: u64,
+    /// Time spent recording, submitting and presenting this frame.
+    pub frame_time: Duration,
+    /// Time since the renderer was created.
+    pub elapsed: Duration,
+}
+
+pub type FrameCallback = Box<dyn FnMut(&FrameInfo)>;
+
+/// Why the explorer couldn't start drawing to its window, or a headless mode failed.
+#[derive(Debug)]
+pub enum StateError {
+    EventLoop(winit::error::EventLoopError),
+    CreateWindow(winit::error::OsError),
+    CreateSurface(wgpu::CreateSurfaceError),
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    Shader(std::path::PathBuf, custom_shader::CustomShaderError),
+    /// What went wrong rendering, comparing or checking without a window.
+    Headless(String),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EventLoop(e) => write!(f, "failed to run the event loop: {e}"),
+            Self::CreateWindow(e) => write!(f, "failed to open a window: {e}"),
+            Self::CreateSurface(e) => write!(f, "failed to draw to the window: {e}"),
+            Self::NoAdapter => write!(f, "no compatible GPU found"),
+            Self::RequestDevice(e) => write!(f, "failed to open the GPU device: {e}"),
+            Self::Shader(path, e) => write!(f, "failed to load {}: {e}", path.display()),
+            Self::Headless(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+impl State {
+    // Creating some of the wgpu types requires async code
+    async fn new(
+        window: Arc<Window>,
+        palette: &palette::Palette,
+        settings: &Config,
+        args: &cli::Args,
+        chosen_surface_format: Option<wgpu::TextureFormat>,
+        on_frame: Option<FrameCallback>,
+    ) -> Result<Self, StateError> {
+        let size = window.inner_size();
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: args.backends(),
+            ..Default::default()
+        });
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(StateError::CreateSurface)?;
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptionsBase {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or(StateError::NoAdapter)?;
+        log::info!("using the {:?} backend", adapter.get_info().backend);
+        let shader_float = gpu::ShaderFloat::for_adapter(&adapter);
+        shader_float.log_choice();
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    // Timestamp queries are optional, see `render_time`.
+                    required_features: shader_float.features()
+                        | (adapter.features() & wgpu::Features::TIMESTAMP_QUERY),
+                    #[cfg(not(target_arch = "wasm32"))]
+                    required_limits: wgpu::Limits::default(),
+                    #[cfg(target_arch = "wasm32")]
+                    required_limits: wgpu::Limits::downlevel_webgl2_defaults(),
+                    label: None,
+                },
+                None,
+            )
+            .await
+            .map_err(StateError::RequestDevice)?;
+        let timer = gpu::GpuTimer::new(&device, &queue);
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost = device_lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            log::error!("GPU device lost ({reason:?}): {message}");
+            lost.store(true, Ordering::Relaxed);
+        });
+        let lost = device_lost.clone();
+        device.on_uncaptured_error(Box::new(move |e| {
+            // Using the lost device errors until it's replaced, anything else is a bug.
+            if !lost.load(Ordering::Relaxed) {
+                panic!("wgpu error: {e}");
+            }
+            log::warn!("ignoring error on lost device: {e}");
+        }));
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        // Rgba16Float surfaces are extended range linear (scRGB) where HDR is available.
+        let hdr_format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .find(|&f| args.hdr && f == wgpu::TextureFormat::Rgba16Float);
+        if args.hdr {
+            match hdr_format {
+                Some(_) => log::info!("using an HDR surface"),
+                None => log::warn!("HDR output isn't supported, falling back to SDR"),
+            }
+        }
+        let surface_format = chosen_surface_format
+            .filter(|f| surface_caps.formats.contains(f))
+            .or(hdr_format)
+            .unwrap_or_else(|| {
+                surface_caps
+                    .formats
+                    .iter()
+                    .copied()
+                    .find(|f| f.is_srgb())
+                    .unwrap_or(surface_caps.formats[0])
+            });
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width,
+            height: size.height,
+            present_mode: match args.present_mode {
+                Some(mode) if surface_caps.present_modes.contains(&mode) => mode,
+                Some(mode) => {
+                    log::warn!("{mode:?} presentation isn't supported, using the default");
+                    surface_caps.present_modes[0]
+                }
+                None => surface_caps.present_modes[0],
+            },
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 1,
+        };
+        surface.configure(&device, &config);
+
+        let shader = shader_float.shader(&device);
+
+        // let mandelbrot_uniform = MandelbrotUniform {
+        //     min_x: -0.749488,
+        //     min_y: 0.031567533,
+        //     height: 0.000141897,
+        //     ..MandelbrotUniform::default_view(size.width as f64 / size.height as f64)
+        // };
+        let mandelbrot_uniform = MandelbrotUniform {
+            hdr_peak: if hdr_format == Some(surface_format) {
+                HDR_PEAK
+            } else {
+                1.0
+            },
+            ..args.start_view(size.width as f64 / size.height as f64)
+        };
+
+        let bindings = gpu::ViewBindings::new(&device, shader_float, &mandelbrot_uniform, palette);
+        let (compare_buffer, compare_bind_group) =
+            bindings.create_secondary(&device, &mandelbrot_uniform);
+        let (fade_buffer, fade_bind_group) =
+            bindings.create_secondary(&device, &mandelbrot_uniform);
+        let (background_buffer, background_bind_group) =
+            bindings.create_secondary(&device, &mandelbrot_uniform);
+        let (loupe_buffer, loupe_bind_group) =
+            bindings.create_secondary(&device, &mandelbrot_uniform);
+        let (inspect_buffer, inspect_bind_group) =
+            bindings.create_secondary(&device, &mandelbrot_uniform);
+        let grid_tiles = (0..GRID_MAX_SIZE * GRID_MAX_SIZE)
+            .map(|_| bindings.create_secondary(&device, &mandelbrot_uniform))
+            .collect();
+        let gpu::ViewBindings {
+            uniform_buffer: mandelbrot_buffer,
+            palette_buffer,
+            layout: bind_group_layout,
+            bind_group,
+            ..
+        } = bindings;
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        // The main view can be drawn with a user supplied shader instead.
+        let user_shader = match &args.shader {
+            Some(path) => Some(
+                custom_shader::load(&device, &render_pipeline_layout, config.format, path)
+                    .await
+                    .map_err(|e| StateError::Shader(path.clone(), e))?,
+            ),
+            None => None,
+        };
+        let main_shader = user_shader.as_ref().unwrap_or(&shader);
+
+        // Used both for the escape values of adaptive anti-aliasing and for CPU rendered images.
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+
+        // Adaptive anti-aliasing first renders the escape values to an offscreen target, then
+        // supersamples only the pixels whose neighbours escaped at very different iterations.
+        let escape_pipeline = gpu::create_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_escape",
+            ESCAPE_FORMAT,
+        );
+
+        let texture_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, &texture_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let screenshot_pipeline = gpu::create_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_image",
+            gpu::IMAGE_FORMAT,
+        );
+        let float_pipelines = gpu::FloatPipelines::new(&device, &render_pipeline_layout, &shader);
+
+        // Heavy views are rendered to offscreen targets over several frames, showing a low
+        // resolution preview scaled up with `SurfacePipelines::smooth_blit` until they're done.
+        let offscreen_pipeline = gpu::create_pipeline(
+            &device,
+            &render_pipeline_layout,
+            main_shader,
+            "fs_main",
+            OFFSCREEN_FORMAT,
+        );
+
+        let bloom_extract_pipeline = gpu::create_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_bloom_extract",
+            OFFSCREEN_FORMAT,
+        );
+        let bloom_blur_pipelines = ["fs_bloom_blur_x", "fs_bloom_blur_y"].map(|entry_point| {
+            gpu::create_pipeline(
+                &device,
+                &texture_pipeline_layout,
+                &shader,
+                entry_point,
+                OFFSCREEN_FORMAT,
+            )
+        });
+
+        let inspect_pipeline = gpu::create_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            "fs_inspect",
+            INSPECT_FORMAT,
+        );
+        let inspect_probe = InspectProbe::new(&device);
+
+        // Progressive rendering spreads the iterations over many frames, keeping each orbit's
+        // state in a storage buffer in between.
+        let storage_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let progressive_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("progressive_layout"),
+                entries: &[
+                    storage_entry(3, wgpu::BufferBindingType::Storage { read_only: false }),
+                    storage_entry(4, wgpu::BufferBindingType::Uniform),
+                ],
+            });
+        let progressive_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, &progressive_layout],
+                push_constant_ranges: &[],
+            });
+        let progressive_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("cs_progressive"),
+                layout: Some(&progressive_pipeline_layout),
+                module: &shader,
+                entry_point: "cs_progressive",
+            });
+
+        let pipeline_sources = PipelineSources {
+            shader,
+            user_shader,
+            layout: render_pipeline_layout,
+            texture_layout: texture_pipeline_layout,
+            progressive_layout: progressive_pipeline_layout,
+        };
+        let surface_pipelines = SurfacePipelines::new(&device, &pipeline_sources, config.format);
+
+        let (escape_view, escape_bind_group) =
+            create_escape_target(&device, &texture_bind_group_layout, size);
+
+        let mut state = Self {
+            cursor_pos: winit::dpi::PhysicalPosition::new(0.0, 0.0),
+            surface,
+            device,
+            queue,
+            device_lost,
+            shader_float,
+            config,
+            surface_formats: surface_caps.formats,
+            chosen_surface_format,
+            hdr_format,
+            pipeline_sources,
+            surface_pipelines,
+            size,
+            window,
+            mandelbrot_uniform,
+            mandelbrot_buffer,
+            palette_buffer,
+            mandelbrot_bind_group: bind_group,
+            dragging: false,
+            inertia: !args.no_inertia,
+            pan_velocity: (0.0, 0.0),
+            pending_scroll_zoom: 0.0,
+            last_drag: Instant::now(),
+            last_update: Instant::now(),
+            escape_pipeline,
+            texture_bind_group_layout,
+            escape_view,
+            escape_bind_group,
+            adaptive_aa: false,
+            screenshot_pipeline,
+            float_pipelines,
+            screenshot_format: args.format,
+            write_manifest: !args.no_manifest,
+            split: None,
+            dragging_divider: false,
+            compare_uniform: mandelbrot_uniform,
+            compare_buffer,
+            compare_bind_group,
+            fade: None,
+            mode_blend: 1.0,
+            fade_buffer,
+            fade_bind_group,
+            grid: None,
+            grid_tiles,
+            cpu_target: None,
+            progressive: false,
+            progressive_pipeline,
+            progressive_layout,
+            progressive_target: None,
+            progressive_iterations: 0,
+            cpu_rendering: false,
+            precision: core::Precision::F64,
+            pinned_precision: args.precision,
+            palette: *palette,
+            target_aspect: None,
+            modifiers: Default::default(),
+            idle_delay: args.idle_delay,
+            idle_target: args.idle_target.map_or(IDLE_TARGET, |location| {
+                let ([x0, y0], [x1, y1]) = (location.min, location.max);
+                [(x0 + x1) / 2.0, (y0 + y1) / 2.0]
+            }),
+            last_input: Instant::now(),
+            undo_history: Vec::new(),
+            redo_history: Vec::new(),
+            last_history_entry: None,
+            glide: None,
+            on_frame,
+            frame_number: 0,
+            start_time: Instant::now(),
+            title: String::new(),
+            minimized: false,
+            uniform_dirty: false,
+            benchmark: args.benchmark.map(benchmark::Recorder::new),
+            zoom_step: args.zoom_step.unwrap_or(DEFAULT_ZOOM_STEP),
+            render_time: Arc::new(AtomicU64::new(0)),
+            timer,
+            frame_budget: args
+                .target_fps
+                .map(|fps| Duration::from_secs_f64(1.0 / fps)),
+            budgeted_render_time: Duration::ZERO,
+            background: None,
+            background_buffer,
+            background_bind_group,
+            offscreen_pipeline,
+            inspecting: false,
+            inspection: None,
+            inspect_probe,
+            inspect_buffer,
+            inspect_bind_group,
+            inspect_pipeline,
+            show_output_encoding: false,
+            frame_meter: benchmark::FrameMeter::default(),
+            show_frame_time: false,
+            frame_time_shown: Instant::now(),
+            keybindings: settings.keybindings.clone(),
+            window_title: settings
+                .title
+                .clone()
+                .unwrap_or_else(|| WINDOW_TITLE.to_owned()),
+            seed: args.seed,
+            random_palettes: 0,
+            start_view: mandelbrot_uniform,
+            builtin_palette: 0,
+            warping: false,
+            continuous: false,
+            bloom: false,
+            bloom_intensity: BLOOM_DEFAULT_INTENSITY,
+            bloom_targets: None,
+            bloom_extract_pipeline,
+            bloom_blur_pipelines,
+            area_estimate: None,
+            show_center: false,
+            hud: true,
+            hud_target: None,
+            hud_drawn: (Vec::new(), 0),
+            loupe: false,
+            hovering: false,
+            loupe_buffer,
+            loupe_bind_group,
+        };
+        state.update_title();
+        Ok(state)
+    }
+
+    fn benchmark_done(&self) -> bool {
+        self.benchmark
+            .as_ref()
+            .is_some_and(benchmark::Recorder::is_done)
+    }
+
+    // Prints the statistics of a finished benchmark, and writes its frame times to
+    // `--benchmark-csv`.
+    fn report_benchmark(&self, args: &cli::Args) {
+        let Some(benchmark) = &self.benchmark else {
+            return;
+        };
+        if let Some(stats) = benchmark.stats() {
+            println!("{stats}");
+        }
+        if let Some(path) = &args.benchmark_csv {
+            if let Err(e) = benchmark::write_csv(path, benchmark.frame_times()) {
+                eprintln!("failed to write {}: {e}", path.display());
+            }
+        }
+    }
+
+    fn device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    // Remembers the current view before the user changes it. Changes in quick succession, like
+    // the events of one drag or scroll, only record the view from before the first of them.
+    fn record_history(&mut self) {
+        self.glide = None;
+        let debounced = self
+            .last_history_entry
+            .is_some_and(|t| t.elapsed() < HISTORY_DEBOUNCE);
+        self.last_history_entry = Some(Instant::now());
+        let unchanged = self
+            .undo_history
+            .last()
+            .is_some_and(|view| same_region(view, &self.mandelbrot_uniform));
+        if debounced || unchanged {
+            return;
+        }
+        if self.undo_history.len() == HISTORY_LENGTH {
+            self.undo_history.remove(0);
+        }
+        self.undo_history.push(self.mandelbrot_uniform);
+        self.redo_history.clear();
+    }
+
+    // Glides back to the previous view (or forward again if `redo`) in the history.
+    fn step_history(&mut self, redo: bool) {
+        let (from, to) = if redo {
+            (&mut self.undo_history, &mut self.redo_history)
+        } else {
+            (&mut self.redo_history, &mut self.undo_history)
+        };
+        let Some(target) = to.pop() else {
+            return;
+        };
+        // Whatever a running glide was heading for is the view being left.
+        from.push(self.glide.map_or(self.mandelbrot_uniform, |(_, to, _)| to));
+        self.last_history_entry = None;
+
+        // Only the region is restored, keeping the window's aspect ratio, which may have changed
+        // since, and the current iteration and coloring settings.
+        let mut view = MandelbrotUniform {
+            height: target.height,
+            rotation: target.rotation,
+            ..self.mandelbrot_uniform
+        };
+        view.set_center_extended(target.center_extended());
+        self.pan_velocity = (0.0, 0.0);
+        self.pending_scroll_zoom = 0.0;
+        self.glide = Some((self.mandelbrot_uniform, view, Instant::now()));
+        self.window.request_redraw();
+    }
+
+    fn update_uniform(&mut self) {
+        debug_assert!(
+            self.mandelbrot_uniform.height > 0.0,
+            "view collapsed: {:?}",
+            self.mandelbrot_uniform
+        );
+        self.uniform_dirty = false;
+        self.progressive_iterations = 0;
+        if self
+            .mandelbrot_uniform
+            .flag(MandelbrotUniform::FLAG_AXES_GRID)
+        {
+            self.mandelbrot_uniform.grid_spacing =
+                core::tick_spacing(self.mandelbrot_uniform.height, AXES_GRID_LINES);
+        }
+        if self.inspecting {
+            self.inspect();
+        }
+        let precision =
+            core::Precision::required(self.mandelbrot_uniform.center(), self.pixel_size());
+        self.mandelbrot_uniform.set_flag(
+            MandelbrotUniform::FLAG_DOUBLE_DOUBLE,
+            precision.escalate(self.pinned_precision) == core::Precision::DoubleDouble,
+        );
+        if precision != self.precision {
+            // Views deeper than the renderer goes can only be reported.
+            match self.rendering_precision() {
+                rendering if precision <= rendering => log::info!("precision: {precision}"),
+                rendering => {
+                    log::warn!("precision: view needs {precision}, rendering in {rendering}")
+                }
+            }
+            self.precision = precision;
+        }
+        self.update_title();
+        self.queue.write_buffer(
+            &self.mandelbrot_buffer,
+            0,
+            &self.shader_float.uniform_bytes(&self.mandelbrot_uniform),
+        );
+        if self.split.is_some() {
+            // Both sides always show the same region.
+            self.compare_uniform = with_region(&self.compare_uniform, &self.mandelbrot_uniform);
+            self.queue.write_buffer(
+                &self.compare_buffer,
+                0,
+                &self.shader_float.uniform_bytes(&self.compare_uniform),
+            );
+        }
+        if let Some((old, _)) = &mut self.fade {
+            *old = with_region(old, &self.mandelbrot_uniform);
+            self.queue
+                .write_buffer(&self.fade_buffer, 0, &self.shader_float.uniform_bytes(old));
+        }
+        if let Some(size) = self.grid {
+            for (i, (buffer, _)) in self
+                .grid_tiles
+                .iter()
+                .take((size * size) as usize)
+                .enumerate()
+            {
+                let uniform = MandelbrotUniform {
+                    max_iterations: grid_iterations(size, i as u32),
+                    ..self.mandelbrot_uniform
+                };
+                self.queue
+                    .write_buffer(buffer, 0, &self.shader_float.uniform_bytes(&uniform));
+            }
+        }
+        self.window.request_redraw();
+    }
+
+    // Warns when the view is deep enough for pixels to merge in the rendering precision, or a few
+    // bits before they actually start to.
+    fn precision_warning(&self) -> Option<String> {
+        let rendering = self.rendering_precision();
+        if self.precision > rendering {
+            Some(format!("beyond {rendering} precision"))
+        } else if core::Precision::required(
+            self.mandelbrot_uniform.center(),
+            self.pixel_size() * PRECISION_WARNING_MARGIN,
+        ) > rendering
+        {
+            Some(format!("near the {rendering} precision limit"))
+        } else {
+            None
+        }
+    }
+
+    // Size of a pixel in the complex plane.
+    fn pixel_size(&self) -> f64 {
+        self.mandelbrot_uniform.height / self.size.height.max(1) as f64
+    }
+
+    // Shows the zoom, iterations and rendering modes in the title, which stays visible while the
+    // overlay is hidden and has room for the modes the overlay leaves out.
+    fn update_title(&mut self) {
+        let uniform = &self.mandelbrot_uniform;
+        let default_height = MandelbrotUniform::default_view(uniform.aspect_ratio).height;
+        let mut parts = vec![format!(
+            "zoom {} (steps of {}×)",
+            format_zoom(default_height / uniform.height),
+            self.zoom_step
+        )];
+        parts.push(match self.grid {
+            Some(size) => grid_labels(size),
+            None if self.progressive && !self.cpu_rendering => format!(
+                "{} / {} iterations",
+                self.progressive_iterations, uniform.max_iterations
+            ),
+            None => format!("{} iterations", uniform.max_iterations),
+        });
+        parts.extend(self.precision_warning());
+        if let Some(precision) = self.pinned_precision {
+            parts.push(format!("{precision} pinned"));
+        }
+        if let Some(inspection) = &self.inspection {
+            parts.push(inspection.to_string());
+        }
+        if self.show_center {
+            parts.push(format!(
+                "center {}",
+                format_point(uniform.center(), self.pixel_size())
+            ));
+        }
+        if uniform.samples_per_pixel > 1 {
+            parts.push(format!("{} samples per pixel", uniform.samples_per_pixel));
+        }
+        if uniform.flag(MandelbrotUniform::FLAG_JULIA) {
+            let [x, y] = uniform.julia_c;
+            parts.push(format!("Julia set of {x:.6}{y:+.6}i"));
+        }
+        if self.cpu_rendering {
+            parts.push("CPU".to_owned());
+        } else if self.shader_float == gpu::ShaderFloat::F32 {
+            parts.push("f32".to_owned());
+        }
+        if let Some((view, estimate)) = &self.area_estimate {
+            let region = view.height * view.height * view.aspect_ratio;
+            parts.push(format!(
+                "area {:.4e} ± {:.1e} ({} samples)",
+                estimate.area(region),
+                estimate.standard_error(region),
+                estimate.samples
+            ));
+        }
+        if self.bloom {
+            parts.push(format!("bloom {:.0}%", self.bloom_intensity * 100.0));
+        }
+        if self.show_frame_time {
+            let now = Instant::now();
+            if let Some(average) = self.frame_meter.average() {
+                let rate = match self.frame_meter.rate(now) {
+                    0 => "idle".to_owned(),
+                    rate => format!("{rate} fps"),
+                };
+                parts.push(format!("frame {average:.2?}, {rate}"));
+            }
+            self.frame_time_shown = now;
+        }
+        if self.show_output_encoding {
+            let shader = if uniform.flag(MandelbrotUniform::FLAG_ENCODE_SRGB) {
+                "sRGB"
+            } else {
+                "linear"
+            };
+            let surface = if self.config.format.is_srgb() {
+                "sRGB"
+            } else {
+                "linear"
+            };
+            parts.push(format!("{shader} output to {surface} surface"));
+        }
+
+        let title = format!("{} ({})", self.window_title, parts.join(", "));
+        if title != self.title {
+            self.window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    // Starts cross-fading from the current coloring settings to whatever they're changed to
+    // next. A fade that's still running continues from where it is.
+    fn fade_coloring(&mut self) {
+        let old = match self.fade {
+            Some((old, _)) if self.mode_blend < 0.5 => old,
+            _ => self.mandelbrot_uniform,
+        };
+        self.fade = Some((old, Instant::now()));
+        self.mode_blend = 0.0;
+    }
+
+    // The divider's x coordinate in physical pixels.
+    fn divider_x(&self, split: f64) -> f64 {
+        let (x, _, width, _) = self.viewport();
+        (x + width * split).round()
+    }
+
+    fn set_palette(&mut self, palette: palette::Palette) {
+        self.palette = palette;
+        self.queue
+            .write_buffer(&self.palette_buffer, 0, bytemuck::cast_slice(&self.palette));
+        self.progressive_iterations = 0;
+        // Finished background renders don't record the palette they were drawn with.
+        self.background = None;
+        self.window.request_redraw();
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // Minimizing resizes to zero on some platforms, which the surface can't be configured to.
+        self.minimized = new_size.width == 0 || new_size.height == 0;
+        if !self.minimized {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.surface.configure(&self.device, &self.config);
+            (self.escape_view, self.escape_bind_group) =
+                create_escape_target(&self.device, &self.texture_bind_group_layout, new_size);
+            self.update_aspect_ratio();
+            if self.progressive && !self.progressive_fits() {
+                self.progressive = false;
+                self.update_title();
+            }
+        }
+    }
+
+    // Reconfigures the surface with `format` and rebuilds the pipelines drawing to it, keeping
+    // the rest of the state.
+    fn set_surface_format(&mut self, format: wgpu::TextureFormat) {
+        log::info!("switching the surface to {format:?}");
+        self.chosen_surface_format = Some(format);
+        self.config.format = format;
+        if !self.minimized {
+            self.surface.configure(&self.device, &self.config);
+        }
+        self.surface_pipelines =
+            SurfacePipelines::new(&self.device, &self.pipeline_sources, format);
+        // The peak brightness goes with the surface, as when the state is created.
+        self.mandelbrot_uniform.hdr_peak = if self.hdr_format == Some(format) {
+            HDR_PEAK
+        } else {
+            1.0
+        };
+        self.update_uniform();
+    }
+
+    // Whether the orbits of every pixel of the viewport fit into a storage buffer, which
+    // progressive rendering keeps them in. Logs a warning if they don't.
+    fn progressive_fits(&self) -> bool {
+        let (_, _, width, height) = self.viewport();
+        let size = width as u64 * height as u64 * PROGRESSIVE_PIXEL_SIZE;
+        let max_size = gpu::max_storage_buffer_size(&self.device);
+        if size > max_size {
+            log::warn!(
+                "progressive rendering needs {size} bytes at this window size, more than the GPU's \
+                 limit of {max_size}, rendering normally instead"
+            );
+        }
+        size <= max_size
+    }
+
+    // Area of the surface the fractal is drawn to, as (x, y, width, height) in physical pixels.
+    // Anything outside of it is letterboxed when the aspect ratio is locked.
+    fn viewport(&self) -> (f64, f64, f64, f64) {
+        let (width, height) = (self.size.width as f64, self.size.height as f64);
+        match self.target_aspect {
+            Some(aspect) if width / height > aspect => {
+                let w = (height * aspect).round();
+                (((width - w) / 2.0).round(), 0.0, w, height)
+            }
+            Some(aspect) => {
+                let h = (width / aspect).round();
+                (0.0, ((height - h) / 2.0).round(), width, h)
+            }
+            None => (0.0, 0.0, width, height),
+        }
+    }
+
+    // Saves the current view as an image in the working directory, along with its manifest.
+    fn screenshot(&self) {
+        let (_, _, width, height) = self.viewport();
+        let (width, height) = (width as u32, height as u32);
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = std::path::PathBuf::from(screenshot_name(
+            &self.mandelbrot_uniform,
+            timestamp,
+            self.screenshot_format.extension(),
+        ));
+        let result = match self.screenshot_format {
+            ImageFormat::Png => gpu::render_image(
+                &self.device,
+                &self.queue,
+                &self.screenshot_pipeline,
+                &self.mandelbrot_bind_group,
+                width,
+                height,
+            )
+            .save(&path)
+            .map_err(std::io::Error::other),
+            ImageFormat::Exr => self.float_pipelines.write_exr(
+                &self.device,
+                &self.queue,
+                &self.mandelbrot_bind_group,
+                width,
+                height,
+                &path,
+            ),
+        };
+        if let Err(e) = result {
+            log::error!("failed to save screenshot to {}: {e}", path.display());
+            return;
+        }
+        println!("saved screenshot to {}", path.display());
+
+        if self.write_manifest {
+            let manifest_path = path.with_extension("json");
+            if let Err(e) = Manifest::new(&self.mandelbrot_uniform).write(&manifest_path) {
+                log::error!("failed to write {}: {e}", manifest_path.display());
+            }
+        }
+    }
+
+    // Logs how deep the view can zoom around its center before turning blocky, see
+    // `precision_check::max_zoom`, in `f64` and double-double on both the GPU and the CPU. The
+    // GPU is probed right away, the CPU in the background, as it's much slower.
+    fn measure_precision_limits(&self) {
+        let view = self.mandelbrot_uniform;
+        let center = view.center();
+        let max_iterations = view.max_iterations;
+        let start = self.pixel_size();
+        let (default_height, height) = (
+            MandelbrotUniform::default_view(1.0).height,
+            self.size.height.max(1) as f64,
+        );
+        let report = move |renderer: &str, limit: precision_check::ZoomLimit| {
+            let zoom = default_height / (limit.pixel_size() * height);
+            log::info!("{renderer}: zoom {} ({limit})", format_zoom(zoom));
+        };
+
+        let gpu_precisions: &[_] = match self.shader_float {
+            gpu::ShaderFloat::F64 => &[core::Precision::F64, core::Precision::DoubleDouble],
+            gpu::ShaderFloat::F32 => &[core::Precision::F32],
+        };
+        for &precision in gpu_precisions {
+            let limit = precision_check::max_zoom(start, max_iterations, |pixel_size| {
+                let mut probe = MandelbrotUniform {
+                    aspect_ratio: 1.0,
+                    height: pixel_size * precision_check::PROBE_SIZE as f64,
+                    ..view
+                };
+                probe.set_center(center);
+                probe.set_flag(
+                    MandelbrotUniform::FLAG_DOUBLE_DOUBLE,
+                    precision == core::Precision::DoubleDouble,
+                );
+                self.queue.write_buffer(
+                    &self.inspect_buffer,
+                    0,
+                    &self.shader_float.uniform_bytes(&probe),
+                );
+                let size = precision_check::PROBE_SIZE;
+                self.float_pipelines
+                    .smooth_iterations(
+                        &self.device,
+                        &self.queue,
+                        &self.inspect_bind_group,
+                        size,
+                        size,
+                    )
+                    .into_iter()
+                    .map(f64::from)
+                    .collect()
+            });
+            report(&format!("GPU {precision}"), limit);
+        }
+
+        std::thread::spawn(move || {
+            for precision in [core::Precision::F64, core::Precision::DoubleDouble] {
+                let limit = precision_check::max_zoom(start, max_iterations, |pixel_size| {
+                    precision_check::cpu_probe(precision, center, pixel_size, max_iterations)
+                });
+                report(&format!("CPU {precision}"), limit);
+            }
+        });
+    }
+
+    // Saves the palette as text, see `palette::to_text`.
+    fn export_palette(&self, path: impl AsRef<std::path::Path>) {
+        let path = path.as_ref();
+        match palette::save(&self.palette, path) {
+            Ok(()) => println!("saved palette to {}", path.display()),
+            Err(e) => log::error!("failed to save palette to {}: {e}", path.display()),
+        }
+    }
+
+    // Switches to a palette saved with `export_palette`, keeping the current one on failure.
+    fn import_palette(&mut self, path: impl AsRef<std::path::Path>) {
+        let path = path.as_ref();
+        match palette::load(path) {
+            Ok(palette) => {
+                println!("loaded palette from {}", path.display());
+                self.set_palette(palette);
+            }
+            Err(e) => log::error!("failed to load palette from {}: {e}", path.display()),
+        }
+    }
+
+    // Converts a cursor position to surface pixels, see `surface_position`.
+    fn to_surface_pixels(
+        &self,
+        position: impl Into<winit::dpi::Position>,
+    ) -> winit::dpi::PhysicalPosition<f64> {
+        surface_position(
+            position.into(),
+            self.window.scale_factor(),
+            self.window.inner_size(),
+            self.size,
+        )
+    }
+
+    fn update_aspect_ratio(&mut self) {
+        let (_, _, width, height) = self.viewport();
+        self.mandelbrot_uniform.aspect_ratio = width / height;
+        self.update_uniform();
+    }
+
+    // The precision the view is rendered in, which the CPU renderer and log-polar views don't
+    // take beyond f64. `f32` shaders don't even get that far.
+    fn rendering_precision(&self) -> core::Precision {
+        let uniform = &self.mandelbrot_uniform;
+        if self.shader_float == gpu::ShaderFloat::F32 && !self.cpu_rendering {
+            core::Precision::F32
+        } else if uniform.flag(MandelbrotUniform::FLAG_DOUBLE_DOUBLE)
+            && !uniform.flag(MandelbrotUniform::FLAG_LOG_POLAR)
+            && !self.cpu_rendering
+        {
+            core::Precision::DoubleDouble
+        } else {
+            core::Precision::F64
+        }
+    }
+
+    // When the view starts zooming on its own, if that's enabled.
+    fn idle_deadline(&self) -> Option<Instant> {
+        self.idle_delay.map(|delay| self.last_input + delay)
+    }
+
+    // When the frame time in the title has to switch to idle, if it doesn't say so yet.
+    fn frame_time_deadline(&self) -> Option<Instant> {
+        if !self.show_frame_time || self.frame_meter.rate(self.frame_time_shown) == 0 {
+            return None;
+        }
+        self.frame_meter.idle_at()
+    }
+
+    fn idle(&self) -> bool {
+        self.idle_deadline()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if matches!(
+            event,
+            WindowEvent::KeyboardInput { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::CursorMoved { .. }
+                | WindowEvent::Touch(_)
+        ) {
+            self.last_input = Instant::now();
+        }
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.dragging = false;
+                self.warping = false;
+                self.hovering = false;
+                if self.loupe {
+                    self.window.request_redraw();
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                let on_divider = self.split.is_some_and(|split| {
+                    (self.cursor_pos.x - self.divider_x(split)).abs() <= DIVIDER_GRAB_DISTANCE
+                });
+                self.dragging_divider = pressed && on_divider;
+                self.dragging = pressed && !on_divider;
+                if self.dragging {
+                    self.record_history();
+                }
+                // Only glide if the cursor was still moving when the button was released.
+                if pressed || !self.inertia || self.last_drag.elapsed() > INERTIA_RELEASE_WINDOW {
+                    self.pan_velocity = (0.0, 0.0);
+                } else {
+                    self.last_update = Instant::now();
+                    self.window.request_redraw();
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => {
+                self.warping = *state == ElementState::Pressed;
+                if self.warping {
+                    self.record_history();
+                    self.pan_velocity = (0.0, 0.0);
+                    self.last_update = Instant::now();
+                    self.window.request_redraw();
+                }
+                false
+            }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                // Make sure the surface matches the new physical size before further cursor
+                // events arrive, otherwise they'd be mapped against the stale size.
+                self.resize(self.window.inner_size());
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = &self.to_surface_pixels(*position);
+                if self.dragging_divider {
+                    let (x, _, width, _) = self.viewport();
+                    self.split = Some(((position.x - x) / width).clamp(0.0, 1.0));
+                    self.window.request_redraw();
+                } else if self.dragging {
+                    let (_, _, width, height) = self.viewport();
+                    let dx = position.x - self.cursor_pos.x;
+                    let dy = position.y - self.cursor_pos.y;
+                    let (du, dv) = (dx / width, -dy / height);
+                    self.mandelbrot_uniform.pan(du, dv);
+                    // Cursor events can arrive many times per frame, so the buffers are only
+                    // written once before the next frame is drawn.
+                    self.uniform_dirty = true;
+                    self.window.request_redraw();
+
+                    let dt = self.last_drag.elapsed().as_secs_f64();
+                    self.last_drag = Instant::now();
+                    if dt > 0.0 {
+                        // Smooth out the jitter of individual cursor events.
+                        let (vu, vv) = self.pan_velocity;
+                        self.pan_velocity = ((vu + du / dt) / 2.0, (vv + dv / dt) / 2.0);
+                    }
+                } else if self.modifiers.shift_key()
+                    && self.mandelbrot_uniform.flag(MandelbrotUniform::FLAG_JULIA)
+                {
+                    // Holding Shift explores the family of Julia sets, taking the constant from
+                    // the point under the cursor.
+                    let (u, v) = screen_to_uv(*position, self.viewport());
+                    self.mandelbrot_uniform.julia_c = self.mandelbrot_uniform.uv_to_complex(u, v);
+                    self.uniform_dirty = true;
+                    self.window.request_redraw();
+                }
+                self.cursor_pos = *position;
+                self.hovering = true;
+                if self.inspecting {
+                    self.inspect();
+                    self.update_title();
+                }
+                if self.loupe {
+                    self.window.request_redraw();
+                }
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (lines, touchpad) = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => (*y as f64, false),
+                    MouseScrollDelta::PixelDelta(pos) => (pixel_scroll_lines(pos.y), true),
+                };
+                let scale = wheel_scale(lines);
+                if self.modifiers.control_key() || self.modifiers.shift_key() {
+                    // Adjust the detail instead of zooming, by as much as the zoom would have
+                    // changed the height.
+                    let iterations = &mut self.mandelbrot_uniform.max_iterations;
+                    *iterations = ((*iterations as f64 / scale).round() as u32).max(MIN_ITERATIONS);
+                    self.update_uniform();
+                    return true;
+                }
+                self.record_history();
+                if touchpad {
+                    // Touchpads send many small events, including momentum after the fingers
+                    // lift, which `update` turns into a smooth zoom.
+                    self.pending_scroll_zoom = (self.pending_scroll_zoom + scale.ln())
+                        .clamp(-MAX_PENDING_SCROLL_ZOOM, MAX_PENDING_SCROLL_ZOOM);
+                    self.window.request_redraw();
+                    return true;
+                }
+                let (u, v) = screen_to_uv(self.cursor_pos, self.viewport());
+                self.mandelbrot_uniform.zoom_at(u, v, scale);
+                self.update_uniform();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(k @ (NamedKey::F11 | NamedKey::Enter)),
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if *k == NamedKey::F11 || self.modifiers.alt_key() => {
+                // The surface is reconfigured by the resize event that follows.
+                let fullscreen = match self.window.fullscreen() {
+                    Some(_) => None,
+                    None => Some(Fullscreen::Borderless(None)),
+                };
+                self.window.set_fullscreen(fullscreen);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key,
+                        ..
+                    },
+                ..
+            } => {
+                let ctrl = self.modifiers.control_key() || self.modifiers.super_key();
+                let shift = self.modifiers.shift_key();
+                match self.keybindings.action(logical_key, ctrl, shift) {
+                    Some(action) => {
+                        self.perform(action);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    // Does what a key bound to `action` in `keybindings` does.
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::ToggleInteriorDetection => {
+                self.fade_coloring();
+                self.mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_INTERIOR_DETECTION);
+                self.update_uniform();
+            }
+            Action::ToggleContours => {
+                self.fade_coloring();
+                self.mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_CONTOURS);
+                self.update_uniform();
+            }
+            Action::CycleNorm => {
+                let norm = &mut self.mandelbrot_uniform.norm_type;
+                *norm = (*norm + 1) % NORM_NAMES.len() as u32;
+                log::info!("escape norm: {}", NORM_NAMES[*norm as usize]);
+                self.update_uniform();
+            }
+            Action::ToggleAxesGrid => {
+                self.mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_AXES_GRID);
+                self.update_uniform();
+            }
+            Action::ToggleJulia => {
+                // Switches to the Julia set of the point at the center of the view, and back to
+                // that point in the Mandelbrot set.
+                let uniform = &mut self.mandelbrot_uniform;
+                if uniform.toggle_flag(MandelbrotUniform::FLAG_JULIA) {
+                    uniform.julia_c = uniform.center();
+                    let (min, max) = JULIA_VIEW;
+                    uniform.fit(min, max);
+                } else {
+                    uniform.height = MandelbrotUniform::default_view(1.0).height;
+                    uniform.set_center(uniform.julia_c);
+                }
+                self.pan_velocity = (0.0, 0.0);
+                self.pending_scroll_zoom = 0.0;
+                self.update_uniform();
+            }
+            Action::ToggleUnderiterated => {
+                self.mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_SHOW_UNDERITERATED);
+                self.update_uniform();
+            }
+            Action::CycleSamples => {
+                // Cycles through 1, 4, 16 and 64 random samples per pixel.
+                let samples = &mut self.mandelbrot_uniform.samples_per_pixel;
+                *samples = if *samples >= MAX_SAMPLES_PER_PIXEL {
+                    1
+                } else {
+                    *samples * 4
+                };
+                warn_about_samples(*samples);
+                self.update_uniform();
+            }
+            Action::ToggleSmooth => {
+                self.fade_coloring();
+                let on = self
+                    .mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_AA_FWIDTH);
+                log::info!("derivative anti-aliasing: {on}");
+                self.update_uniform();
+            }
+            Action::ToggleAdaptiveAa => {
+                self.adaptive_aa = !self.adaptive_aa;
+                log::info!("adaptive anti-aliasing: {}", self.adaptive_aa);
+                self.window.request_redraw();
+            }
+            Action::RandomPalette => {
+                let seed = match self.seed {
+                    Some(seed) => palette::nth_seed(seed, self.random_palettes),
+                    None => palette::time_seed(),
+                };
+                self.random_palettes += 1;
+                println!("random palette seed: {seed}");
+                self.set_palette(palette::random(seed));
+            }
+            Action::CyclePalette => {
+                self.builtin_palette = (self.builtin_palette + 1) % palette::BUILTIN_NAMES.len();
+                log::info!("palette: {}", palette::BUILTIN_NAMES[self.builtin_palette]);
+                self.set_palette(palette::builtin(self.builtin_palette));
+            }
+            Action::Screenshot => self.screenshot(),
+            Action::ToggleSplit => {
+                self.split = match self.split {
+                    Some(_) => None,
+                    None => {
+                        self.compare_uniform = self.mandelbrot_uniform;
+                        Some(0.5)
+                    }
+                };
+                self.update_uniform();
+            }
+            Action::CycleGrid => {
+                // Cycles through the grid sizes, then back to the normal view.
+                self.grid = match self.grid {
+                    None => Some(2),
+                    Some(size) if size < GRID_MAX_SIZE => Some(size + 1),
+                    Some(_) => None,
+                };
+                self.update_uniform();
+            }
+            Action::ShorterColorPeriod | Action::LongerColorPeriod => {
+                self.fade_coloring();
+                let period = &mut self.mandelbrot_uniform.color_period;
+                *period = match (action, *period) {
+                    (_, 0.0) => COLOR_PERIOD_START,
+                    (Action::LongerColorPeriod, period) => period * 2.0,
+                    // Halving below one iteration goes back to a single palette cycle.
+                    (_, period) if period > 1.0 => period / 2.0,
+                    _ => 0.0,
+                };
+                match *period {
+                    0.0 => log::info!("color period: max iterations"),
+                    period => log::info!("color period: {period} iterations"),
+                }
+                self.update_uniform();
+            }
+            Action::ToggleProgressive => {
+                self.progressive = !self.progressive && self.progressive_fits();
+                self.progressive_iterations = 0;
+                log::info!("progressive rendering: {}", self.progressive);
+                self.update_title();
+                self.window.request_redraw();
+            }
+            Action::ToggleCpu => {
+                self.cpu_rendering = !self.cpu_rendering;
+                let mode = if self.cpu_rendering { "CPU" } else { "GPU" };
+                log::info!("rendering on the {mode}");
+                self.update_title();
+                self.window.request_redraw();
+            }
+            Action::ZoomIn | Action::ZoomOut => {
+                // Zooms about the center to the next whole power of the zoom step, so a series
+                // of presses gives reproducible magnifications.
+                let steps = if action == Action::ZoomOut { -1 } else { 1 };
+                self.record_history();
+                let uniform = &mut self.mandelbrot_uniform;
+                let default_height = MandelbrotUniform::default_view(1.0).height;
+                let center = uniform.center_extended();
+                uniform.height = step_zoom(uniform.height, default_height, self.zoom_step, steps);
+                uniform.set_center_extended(center);
+                self.pan_velocity = (0.0, 0.0);
+                self.update_uniform();
+            }
+            Action::ExportPalette => self.export_palette(PALETTE_FILE),
+            Action::ImportPalette => self.import_palette(PALETTE_FILE),
+            Action::RotateLeft | Action::RotateRight => {
+                let step = std::f64::consts::PI / 36.0;
+                let step = if action == Action::RotateLeft {
+                    step
+                } else {
+                    -step
+                };
+                self.record_history();
+                self.mandelbrot_uniform.rotation =
+                    (self.mandelbrot_uniform.rotation + step) % std::f64::consts::TAU;
+                self.update_uniform();
+            }
+            Action::ToggleAspectRatio => {
+                self.target_aspect = match self.target_aspect {
+                    Some(_) => None,
+                    None => Some(16.0 / 9.0),
+                };
+                self.update_aspect_ratio();
+            }
+            Action::Reset => {
+                // The iteration limit goes back to where it started too, deep views that needed
+                // more are left behind.
+                self.record_history();
+                self.mandelbrot_uniform.reset_to(&self.start_view);
+                self.update_uniform();
+            }
+            Action::Undo => self.step_history(false),
+            Action::Redo => self.step_history(true),
+            Action::ToggleInspector => {
+                self.inspecting = !self.inspecting;
+                if self.inspecting {
+                    self.inspect();
+                } else {
+                    self.inspection = None;
+                }
+                self.update_title();
+            }
+            Action::ToggleLoupe => {
+                self.loupe = !self.loupe;
+                log::info!("loupe: {}", self.loupe);
+                self.window.request_redraw();
+            }
+            Action::ToggleSrgbEncoding => {
+                // A debugging aid for colors that look wrong: an sRGB surface encodes whatever
+                // the shader outputs, so encoding it there too brightens it.
+                self.mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_ENCODE_SRGB);
+                self.show_output_encoding = true;
+                self.update_uniform();
+            }
+            Action::ToggleAreaEstimate => {
+                self.area_estimate = match self.area_estimate {
+                    Some(_) => None,
+                    None => Some((self.mandelbrot_uniform, AreaEstimate::default())),
+                };
+                self.update_title();
+                self.window.request_redraw();
+            }
+            Action::ToggleBloom => {
+                self.bloom = !self.bloom;
+                self.update_title();
+                self.window.request_redraw();
+            }
+            Action::WeakerBloom | Action::StrongerBloom => {
+                let step = if action == Action::StrongerBloom {
+                    BLOOM_INTENSITY_STEP
+                } else {
+                    -BLOOM_INTENSITY_STEP
+                };
+                // Blend constants only go up to 1.
+                self.bloom_intensity =
+                    (self.bloom_intensity + step).clamp(BLOOM_INTENSITY_STEP, 1.0);
+                self.bloom = true;
+                self.update_title();
+                self.window.request_redraw();
+            }
+            Action::ToggleBoundary => {
+                self.fade_coloring();
+                self.mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_BOUNDARY);
+                self.update_uniform();
+            }
+            Action::ToggleLogPolar => {
+                self.mandelbrot_uniform.toggle_log_polar();
+                self.pan_velocity = (0.0, 0.0);
+                self.pending_scroll_zoom = 0.0;
+                self.update_uniform();
+            }
+            Action::ToggleSmoothColoring => {
+                self.fade_coloring();
+                let on = self
+                    .mandelbrot_uniform
+                    .toggle_flag(MandelbrotUniform::FLAG_SMOOTH_COLORING);
+                log::info!("smooth coloring: {on}");
+                self.update_uniform();
+            }
+            Action::CyclePrecision => {
+                self.pinned_precision = match self.pinned_precision {
+                    None => Some(core::Precision::F64),
+                    Some(core::Precision::F64) => Some(core::Precision::DoubleDouble),
+                    Some(_) => None,
+                };
+                match self.pinned_precision {
+                    Some(precision) => log::info!("precision: pinned to {precision}"),
+                    None => log::info!("precision: automatic"),
+                }
+                self.update_uniform();
+            }
+            Action::MeasurePrecisionLimits => self.measure_precision_limits(),
+            Action::CycleSurfaceFormat => {
+                // A debugging aid like the sRGB toggle, for telling apart what the shader outputs
+                // from what the surface makes of it.
+                let formats = &self.surface_formats;
+                let current = formats.iter().position(|&f| f == self.config.format);
+                let next = formats[current.map_or(0, |i| (i + 1) % formats.len())];
+                self.set_surface_format(next);
+            }
+            Action::LogView => log::info!("{:?}", self.mandelbrot_uniform),
+            Action::ToggleContinuousRedraw => {
+                self.continuous = !self.continuous;
+                log::info!(
+                    "continuous redraw {}",
+                    if self.continuous { "on" } else { "off" }
+                );
+                self.last_update = Instant::now();
+                self.window.request_redraw();
+            }
+            Action::ToggleFrameTime => {
+                self.show_frame_time = !self.show_frame_time;
+                self.update_title();
+            }
+            Action::ToggleHud => {
+                self.hud = !self.hud;
+                self.window.request_redraw();
+            }
+            Action::MoreIterations | Action::FewerIterations => {
+                let uniform = &mut self.mandelbrot_uniform;
+                if action == Action::MoreIterations {
+                    uniform.max_iterations += 128;
+                } else {
+                    uniform.max_iterations = uniform
+                        .max_iterations
+                        .saturating_sub(128)
+                        .max(MIN_ITERATIONS);
+                };
+                log::debug!("max_iterations = {}", uniform.max_iterations);
+                self.update_uniform();
+            }
+            Action::PanLeft | Action::PanRight | Action::PanUp | Action::PanDown => {
+                // Moves the center by exactly one pixel, for lining up shots.
+                let (_, _, width, height) = self.viewport();
+                let (du, dv) = match action {
+                    Action::PanLeft => (1.0 / width, 0.0),
+                    Action::PanRight => (-1.0 / width, 0.0),
+                    Action::PanUp => (0.0, -1.0 / height),
+                    _ => (0.0, 1.0 / height),
+                };
+                self.record_history();
+                self.mandelbrot_uniform.pan(du, dv);
+                self.pan_velocity = (0.0, 0.0);
+                self.show_center = true;
+                self.update_uniform();
+            }
+            Action::PanFarLeft | Action::PanFarRight | Action::PanFarUp | Action::PanFarDown => {
+                // A fixed share of the view height, so a press covers the same screen distance
+                // in both directions and at any zoom.
+                let uniform = &self.mandelbrot_uniform;
+                let step = KEY_PAN_STEP * uniform.height;
+                let (du, dv) = match action {
+                    Action::PanFarLeft => (step / uniform.width(), 0.0),
+                    Action::PanFarRight => (-step / uniform.width(), 0.0),
+                    Action::PanFarUp => (0.0, -KEY_PAN_STEP),
+                    _ => (0.0, KEY_PAN_STEP),
+                };
+                self.record_history();
+                self.mandelbrot_uniform.pan(du, dv);
+                self.pan_velocity = (0.0, 0.0);
+                self.show_center = true;
+                self.update_uniform();
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        if self.uniform_dirty {
+            self.update_uniform();
+        }
+
+        // Clamp the step so a long idle period doesn't turn into a jump.
+        let dt = self.last_update.elapsed().as_secs_f64().min(0.05);
+        self.last_update = Instant::now();
+
+        let (vu, vv) = self.pan_velocity;
+        if !self.dragging && (vu != 0.0 || vv != 0.0) {
+            self.mandelbrot_uniform.pan(vu * dt, vv * dt);
+            let decay = (-INERTIA_DECAY * dt).exp();
+            self.pan_velocity = (vu * decay, vv * decay);
+            if vu.hypot(vv) * decay < INERTIA_MIN_SPEED {
+                self.pan_velocity = (0.0, 0.0);
+            }
+            self.update_uniform();
+        }
+
+        if self.pending_scroll_zoom != 0.0 {
+            let step = scroll_zoom_step(self.pending_scroll_zoom, dt);
+            self.pending_scroll_zoom -= step;
+            let (u, v) = screen_to_uv(self.cursor_pos, self.viewport());
+            self.mandelbrot_uniform.zoom_at(u, v, step.exp());
+            self.update_uniform();
+        }
+
+        if self.warping {
+            // Keeps the point under the cursor in place, flying into it.
+            let (u, v) = screen_to_uv(self.cursor_pos, self.viewport());
+            let scale = (-WARP_ZOOM_RATE * dt).exp();
+            self.mandelbrot_uniform.zoom_at(u, v, scale);
+            self.update_uniform();
+        }
+
+        if self.idle() {
+            let uniform = &mut self.mandelbrot_uniform;
+            let [x, y] = uniform.center();
+            let [tx, ty] = self.idle_target;
+            let approach = 1.0 - (-IDLE_APPROACH_RATE * dt).exp();
+            uniform.height *= (-IDLE_ZOOM_RATE * dt).exp();
+            uniform.set_center([x + (tx - x) * approach, y + (ty - y) * approach]);
+            // Start over before running out of precision.
+            if uniform.height < IDLE_MIN_HEIGHT {
+                uniform.fit_whole_set();
+            }
+            self.update_uniform();
+        }
+
+        if let Some((_, start)) = self.fade {
+            let t = start.elapsed().as_secs_f64() / FADE_DURATION.as_secs_f64();
+            if t >= 1.0 {
+                self.fade = None;
+                self.mode_blend = 1.0;
+            } else {
+                self.mode_blend = t;
+            }
+            self.window.request_redraw();
+        }
+
+        if let Some((from, to, start)) = self.glide {
+            let t = start.elapsed().as_secs_f64() / GLIDE_DURATION.as_secs_f64();
+            if t >= 1.0 {
+                self.mandelbrot_uniform = to;
+                self.glide = None;
+            } else {
+                // Ease in and out.
+                let t = t * t * (3.0 - 2.0 * t);
+                self.mandelbrot_uniform = from.zoom_towards(&to, t);
+            }
+            self.update_uniform();
+        }
+
+        if self.refine_area_estimate() {
+            self.update_title();
+            self.window.request_redraw();
+        }
+
+        self.fit_iterations_to_budget();
+    }
+
+    // Adjusts `max_iterations` to the last GPU render time, see `budgeted_iterations`. Each
+    // measurement is only acted on once.
+    fn fit_iterations_to_budget(&mut self) {
+        let Some(budget) = self.frame_budget else {
+            return;
+        };
+        let time = self.render_time();
+        if time.is_zero() || time == self.budgeted_render_time || self.cpu_rendering {
+            return;
+        }
+        self.budgeted_render_time = time;
+        let iterations = self.mandelbrot_uniform.max_iterations;
+        let scaled = budgeted_iterations(iterations, time, budget);
+        if scaled != iterations {
+            self.mandelbrot_uniform.max_iterations = scaled;
+            self.update_uniform();
+        }
+    }
+
+    // Takes another batch of samples for the area estimate, starting over if the view changed
+    // since the last one. Returns whether there were any left to take.
+    fn refine_area_estimate(&mut self) -> bool {
+        let Some((view, estimate)) = &mut self.area_estimate else {
+            return false;
+        };
+        if !same_uniform(view, &self.mandelbrot_uniform) {
+            *view = self.mandelbrot_uniform;
+            *estimate = AreaEstimate::default();
+        }
+        if estimate.samples >= AREA_MAX_SAMPLES {
+            return false;
+        }
+        let view = *view;
+        let fractal = cpu::fractal(&view);
+        let count = (AREA_ITERATION_BUDGET / u64::from(view.max_iterations.max(1))).max(1);
+        estimate.sample(count, |[u, v]| {
+            let point = view.uv_to_complex(u, v);
+            // Whether a point is in the set doesn't depend on the escape radius.
+            fractal
+                .iterate(point, view.max_iterations, core::BAILOUT_SQR)
+                .is_none()
+        });
+        true
+    }
+
+    // Renders the current view on the CPU and uploads it to `cpu_target`.
+    fn render_cpu(&mut self, width: u32, height: u32) {
+        let start = Instant::now();
+        let pixels = cpu::render(&self.mandelbrot_uniform, &self.palette, width, height);
+        log::info!("CPU render took {:?}", start.elapsed());
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        if !matches!(&self.cpu_target, Some((texture, _)) if texture.size() == size) {
+            self.cpu_target = Some(create_cpu_target(
+                &self.device,
+                &self.texture_bind_group_layout,
+                size,
+            ));
+        }
+        let (texture, _) = self.cpu_target.as_ref().unwrap();
+        self.queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&pixels),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width * std::mem::size_of::<[f32; 4]>() as u32),
+                rows_per_image: None,
+            },
+            size,
+        );
+    }
+
+    // Rasterizes the overlay into `hud_target` if its text changed, and returns its size.
+    fn update_hud(&mut self) -> Option<(f32, f32)> {
+        let uniform = &self.mandelbrot_uniform;
+        let default_height = MandelbrotUniform::default_view(uniform.aspect_ratio).height;
+        let decimals = decimals(self.pixel_size());
+        let mut lines = vec![
+            format!("x     {:+.decimals$}", uniform.min_x),
+            format!("y     {:+.decimals$}", uniform.min_y),
+            format!("zoom  {:.3e}", default_height / uniform.height),
+            format!("iters {}", uniform.max_iterations),
+            match self.pinned_precision {
+                Some(_) => format!("prec  {} pinned", self.rendering_precision()),
+                None => format!("prec  {}", self.rendering_precision()),
+            },
+        ];
+        if uniform.flag(MandelbrotUniform::FLAG_AXES_GRID) {
+            lines.push(format!("grid  {:e}", uniform.grid_spacing));
+        }
+        lines.extend(self.precision_warning());
+        let scale = (HUD_SCALE * self.window.scale_factor()).round().max(1.0) as u32;
+        if self.hud_target.is_none() || self.hud_drawn != (lines.clone(), scale) {
+            let (width, height, pixels) = hud::rasterize(&lines, scale);
+            let size = wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            };
+            if !matches!(&self.hud_target, Some((texture, _)) if texture.size() == size) {
+                self.hud_target = Some(create_cpu_target(
+                    &self.device,
+                    &self.texture_bind_group_layout,
+                    size,
+                ));
+            }
+            let (texture, _) = self.hud_target.as_ref().unwrap();
+            self.queue.write_texture(
+                texture.as_image_copy(),
+                bytemuck::cast_slice(&pixels),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * std::mem::size_of::<[f32; 4]>() as u32),
+                    rows_per_image: None,
+                },
+                size,
+            );
+            self.hud_drawn = (lines, scale);
+        }
+        let (texture, _) = self.hud_target.as_ref()?;
+        Some((texture.width() as f32, texture.height() as f32))
+    }
+
+    // Advances progressive rendering by another batch of iterations, if it isn't done yet.
+    fn progressive_pass(&mut self, encoder: &mut wgpu::CommandEncoder, width: u32, height: u32) {
+        if !matches!(&self.progressive_target, Some(t) if t.size == [width, height]) {
+            self.progressive_target = Some(ProgressiveTarget::new(
+                &self.device,
+                &self.progressive_layout,
+                [width, height],
+            ));
+            self.progressive_iterations = 0;
+        }
+        let target = self.progressive_target.as_ref().unwrap();
+        if self.progressive_iterations == 0 {
+            encoder.clear_buffer(&target.state, 0, None);
+        }
+
+        let max_iterations = self.mandelbrot_uniform.max_iterations;
+        if self.progressive_iterations >= max_iterations {
+            return;
+        }
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Progressive Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.progressive_pipeline);
+            pass.set_bind_group(0, &self.mandelbrot_bind_group, &[]);
+            pass.set_bind_group(1, &target.bind_group, &[]);
+            pass.dispatch_workgroups(width.div_ceil(8), height.div_ceil(8), 1);
+        }
+        self.progressive_iterations =
+            (self.progressive_iterations + PROGRESSIVE_ITERATIONS_PER_PASS).min(max_iterations);
+        self.update_title();
+        // Keep going until all iterations are done.
+        self.window.request_redraw();
+    }
+
+    // Renders the pixel under the cursor on its own and starts reading back its color and escape
+    // time, which `poll_inspection` puts into `inspection`. Clears it if the cursor isn't over
+    // the view. While a readback is still on its way, the pixel is only inspected once it's done.
+    fn inspect(&mut self) {
+        if self.inspect_probe.pending.is_some() {
+            self.inspect_probe.stale = true;
+            return;
+        }
+        let viewport = self.viewport();
+        // The center of the pixel, like the shader samples it.
+        let pixel = winit::dpi::PhysicalPosition::new(
+            self.cursor_pos.x.floor() + 0.5,
+            self.cursor_pos.y.floor() + 0.5,
+        );
+        let (u, v) = screen_to_uv(pixel, viewport);
+        if !self.hovering || !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            self.inspection = None;
+            return;
+        }
+        let mut uniform = self.mandelbrot_uniform;
+        let point = uniform.uv_to_complex(u, v);
+        uniform.aspect_ratio = 1.0;
+        uniform.height /= viewport.3;
+        uniform.set_center(point);
+        self.queue.write_buffer(
+            &self.inspect_buffer,
+            0,
+            &self.shader_float.uniform_bytes(&uniform),
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Inspect Encoder"),
+            });
+        let probe = &mut self.inspect_probe;
+        for (pipeline, target) in [
+            (&self.screenshot_pipeline, &probe.color),
+            (&self.inspect_pipeline, &probe.escape),
+        ] {
+            let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            gpu::draw_quad(&mut encoder, pipeline, &self.inspect_bind_group, &view);
+        }
+        probe.copy_to_readback(&mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        probe
+            .readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        let inspection = Inspection {
+            point,
+            resolution: uniform.height,
+            smooth_iterations: 0.0,
+            iterations: 0,
+            max_iterations: uniform.max_iterations,
+            interior: false,
+            rgb: [0; 3],
+        };
+        probe.pending = Some((inspection, receiver));
+    }
+
+    // Picks up the readback `inspect` started if it's done, inspecting the pixel under the
+    // cursor again if it moved on in the meantime. Returns whether a readback is still pending.
+    fn poll_inspection(&mut self) -> bool {
+        let Some((_, receiver)) = &self.inspect_probe.pending else {
+            return false;
+        };
+        self.device.poll(wgpu::Maintain::Poll);
+        let result = match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => return true,
+            result => result,
+        };
+        let probe = &mut self.inspect_probe;
+        let (mut inspection, _) = probe.pending.take().unwrap();
+        match result {
+            Ok(Ok(())) => {
+                let data = probe.readback.slice(..).get_mapped_range();
+                let color = &data[..4];
+                let [smooth_iterations, iterations, interior, _]: [f32; 4] =
+                    bytemuck::pod_read_unaligned(&data[INSPECT_ROW..INSPECT_ROW + 16]);
+                inspection.smooth_iterations = smooth_iterations;
+                inspection.iterations = iterations as u32;
+                inspection.interior = interior != 0.0;
+                inspection.rgb = [color[0], color[1], color[2]];
+                drop(data);
+                probe.readback.unmap();
+                if self.inspecting {
+                    self.inspection = Some(inspection);
+                }
+            }
+            Ok(Err(e)) => log::warn!("failed to read back the inspected pixel: {e}"),
+            Err(_) => log::warn!("the inspected pixel's readback was dropped"),
+        }
+        if std::mem::take(&mut self.inspect_probe.stale) && self.inspecting {
+            self.inspect();
+        }
+        self.update_title();
+        self.inspect_probe.pending.is_some()
+    }
+
+    // Where to draw the loupe as (x, y, size) in surface pixels, with its uniform written to
+    // `loupe_buffer`, or `None` if it's hidden. The loupe is kept inside the surface, so near the
+    // edges the cursor isn't at its center, but it still magnifies the point under the cursor.
+    fn loupe_viewport(&self) -> Option<(f32, f32, f32)> {
+        if !self.loupe || !self.hovering {
+            return None;
+        }
+        let viewport = self.viewport();
+        let (u, v) = screen_to_uv(self.cursor_pos, viewport);
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        let size = LOUPE_SIZE
+            .min(self.size.width as f64)
+            .min(self.size.height as f64);
+        let x = (self.cursor_pos.x - size / 2.0).clamp(0.0, self.size.width as f64 - size);
+        let y = (self.cursor_pos.y - size / 2.0).clamp(0.0, self.size.height as f64 - size);
+
+        let mut uniform = self.mandelbrot_uniform;
+        let point = uniform.uv_to_complex(u, v);
+        uniform.aspect_ratio = 1.0;
+        uniform.height *= size / viewport.3 / LOUPE_MAGNIFICATION;
+        uniform.set_center(point);
+        self.queue.write_buffer(
+            &self.loupe_buffer,
+            0,
+            &self.shader_float.uniform_bytes(&uniform),
+        );
+        Some((x as f32, y as f32, size as f32))
+    }
+
+    fn render_time(&self) -> Duration {
+        Duration::from_nanos(self.render_time.load(Ordering::Relaxed))
+    }
+
+    // Renders the next band of the current view to the back target of `background` in its own
+    // submission, and a preview of the view with `encoder` unless the front target already
+    // shows it.
+    fn background_pass(&mut self, encoder: &mut wgpu::CommandEncoder, width: u32, height: u32) {
+        if !matches!(&self.background, Some(b) if b.size == [width, height]) {
+            self.background = Some(BackgroundRender::new(
+                &self.device,
+                &self.texture_bind_group_layout,
+                [width, height],
+            ));
+        }
+        let uniform = self.mandelbrot_uniform;
+        let background = self.background.as_mut().unwrap();
+        if background.done.swap(false, Ordering::Acquire) {
+            if let Some(job) = background.job.take() {
+                background.front = 1 - background.front;
+                background.front_uniform = Some(job.uniform);
+            }
+        }
+        if background.finished(&uniform) {
+            return;
+        }
+
+        // Start over when the view changed, unless the last band is already on its way.
+        let stale = match &background.job {
+            Some(job) => job.band < job.bands && !same_uniform(&job.uniform, &uniform),
+            None => true,
+        };
+        if stale {
+            let bands =
+                self.render_time.load(Ordering::Relaxed) / BACKGROUND_BAND_TIME.as_nanos() as u64;
+            background.job = Some(BackgroundJob {
+                uniform,
+                band: 0,
+                bands: (bands as u32).clamp(1, MAX_BACKGROUND_BANDS.min(height)),
+            });
+            self.queue.write_buffer(
+                &self.background_buffer,
+                0,
+                &self.shader_float.uniform_bytes(&uniform),
+            );
+        }
+        let job = background.job.as_mut().unwrap();
+        if job.band < job.bands {
+            let top = height * job.band / job.bands;
+            let bottom = height * (job.band + 1) / job.bands;
+            let mut band_encoder =
+                self.device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Background Encoder"),
+                    });
+            let timestamp_writes = self.timer.as_ref().and_then(|t| t.timestamp_writes());
+            let timed = timestamp_writes.is_some();
+            {
+                let mut pass = band_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Background Pass"),
+                    timestamp_writes,
+                    occlusion_query_set: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &background.targets[1 - background.front].view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                pass.set_scissor_rect(0, top, width, bottom - top);
+                pass.set_pipeline(&self.offscreen_pipeline);
+                pass.set_bind_group(0, &self.background_bind_group, &[]);
+                pass.draw(0..4, 0..1);
+            }
+            if let (true, Some(timer)) = (timed, &self.timer) {
+                timer.resolve(&mut band_encoder);
+            }
+            let start = Instant::now();
+            self.queue.submit(std::iter::once(band_encoder.finish()));
+
+            job.band += 1;
+            match &self.timer {
+                Some(timer) if timed => timer.read(job.bands, self.render_time.clone()),
+                Some(_) => {}
+                None => {
+                    // Bands are short enough to wait for.
+                    self.device.poll(wgpu::Maintain::Wait);
+                    let elapsed = start.elapsed() * job.bands;
+                    self.render_time
+                        .store(elapsed.as_nanos() as u64, Ordering::Relaxed);
+                }
+            }
+            if job.band == job.bands {
+                let done = background.done.clone();
+                self.queue.on_submitted_work_done(move || {
+                    done.store(true, Ordering::Release);
+                });
+            }
+        }
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Preview Pass"),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &background.preview.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&self.offscreen_pipeline);
+            pass.set_bind_group(0, &self.mandelbrot_bind_group, &[]);
+            pass.draw(0..4, 0..1);
+        }
+        // Nothing else wakes the event loop when the render finishes.
+        self.device.poll(wgpu::Maintain::Poll);
+        self.window.request_redraw();
+    }
+
+    // Renders the bright parts of the view into the first of `bloom_targets` and blurs them.
+    fn bloom_pass(&mut self, encoder: &mut wgpu::CommandEncoder, width: u32, height: u32) {
+        let size = [width, height].map(|n| (n / BLOOM_SCALE).max(1));
+        if !matches!(&self.bloom_targets, Some((s, _)) if *s == size) {
+            let layout = &self.texture_bind_group_layout;
+            let targets = [0, 1].map(|_| OffscreenTarget::new(&self.device, layout, size));
+            self.bloom_targets = Some((size, targets));
+        }
+        let (_, targets) = self.bloom_targets.as_ref().unwrap();
+        let passes = [
+            (&self.bloom_extract_pipeline, None, &targets[0]),
+            (
+                &self.bloom_blur_pipelines[0],
+                Some(&targets[0]),
+                &targets[1],
+            ),
+            (
+                &self.bloom_blur_pipelines[1],
+                Some(&targets[1]),
+                &targets[0],
+            ),
+        ];
+        for (pipeline, input, output) in passes {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Bloom Pass"),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &output.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &self.mandelbrot_bind_group, &[]);
+            if let Some(input) = input {
+                pass.set_bind_group(1, &input.bind_group, &[]);
+            }
+            pass.draw(0..4, 0..1);
+        }
+    }
+
+    // Draws the live settings left of the divider and the comparison settings right of it.
+    fn draw_split<'pass>(&'pass self, render_pass: &mut wgpu::RenderPass<'pass>, split: f64) {
+        let (x, y, width, height) = self.viewport();
+        let (x, y, width, height) = (x as u32, y as u32, width as u32, height as u32);
+        let divider = self.divider_x(split) as u32;
+        let sides = [
+            (
+                x,
+                divider,
+                &self.mandelbrot_bind_group,
+                &self.surface_pipelines.main,
+            ),
+            (
+                divider,
+                x + width,
+                &self.compare_bind_group,
+                &self.surface_pipelines.main,
+            ),
+            (
+                divider.saturating_sub(DIVIDER_WIDTH / 2).max(x),
+                (divider + DIVIDER_WIDTH / 2).min(x + width),
+                &self.mandelbrot_bind_group,
+                &self.surface_pipelines.divider,
+            ),
+        ];
+        for (left, right, bind_group, pipeline) in sides {
+            if right > left {
+                render_pass.set_scissor_rect(left, y, right - left, height);
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+            }
+        }
+    }
+
+    // Draws the view once per grid tile, shrunk to fit, row by row starting at the top left.
+    fn draw_grid<'pass>(&'pass self, render_pass: &mut wgpu::RenderPass<'pass>, size: u32) {
+        let (x, y, width, height) = self.viewport();
+        let (tile_width, tile_height) = (width / size as f64, height / size as f64);
+        render_pass.set_pipeline(&self.surface_pipelines.main);
+        for (i, (_, bind_group)) in self
+            .grid_tiles
+            .iter()
+            .take((size * size) as usize)
+            .enumerate()
+        {
+            let (row, col) = (i as u32 / size, i as u32 % size);
+            let left = (x + tile_width * col as f64).round();
+            let top = (y + tile_height * row as f64).round();
+            let right = (x + tile_width * (col + 1) as f64).round();
+            let bottom = (y + tile_height * (row + 1) as f64).round();
+            let (w, h) = (right - left, bottom - top);
+            render_pass.set_viewport(left as f32, top as f32, w as f32, h as f32, 0.0, 1.0);
+            render_pass.set_scissor_rect(left as u32, top as u32, w as u32, h as u32);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..4, 0..1);
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        // There's nothing to draw to, and acquiring a texture would only fail until the window
+        // is restored.
+        if self.minimized {
+            return Ok(());
+        }
+        let frame_start = Instant::now();
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+        let (vx, vy, vw, vh) = self.viewport();
+        let (vx, vy, vw, vh) = (vx as f32, vy as f32, vw as f32, vh as f32);
+        if self.cpu_rendering {
+            self.render_cpu(vw as u32, vh as u32);
+        }
+        let grid = self
+            .grid
+            .filter(|_| !self.cpu_rendering && !self.progressive);
+        let split = self
+            .split
+            .filter(|_| !self.cpu_rendering && !self.progressive && grid.is_none());
+        let progressive = self.progressive && !self.cpu_rendering;
+        if progressive {
+            self.progressive_pass(&mut encoder, vw as u32, vh as u32);
+        }
+        let adaptive_aa = self.adaptive_aa
+            && !self.cpu_rendering
+            && !progressive
+            && split.is_none()
+            && grid.is_none();
+        if adaptive_aa {
+            let mut escape_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Escape Pass"),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.escape_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            escape_pass.set_viewport(vx, vy, vw, vh, 0.0, 1.0);
+            escape_pass.set_pipeline(&self.escape_pipeline);
+            escape_pass.set_bind_group(0, &self.mandelbrot_bind_group, &[]);
+            escape_pass.draw(0..4, 0..1);
+        }
+        let plain = !self.cpu_rendering
+            && !progressive
+            && !adaptive_aa
+            && split.is_none()
+            && grid.is_none()
+            && self.fade.is_none();
+        let background = plain && self.render_time() > HEAVY_RENDER_TIME;
+        if background {
+            self.background_pass(&mut encoder, vw as u32, vh as u32);
+        }
+        let bloom =
+            self.bloom && !self.cpu_rendering && !progressive && split.is_none() && grid.is_none();
+        if bloom {
+            self.bloom_pass(&mut encoder, vw as u32, vh as u32);
+        }
+        let loupe = self.loupe_viewport();
+        let hud = self.hud.then(|| self.update_hud()).flatten();
+        let [interior_r, interior_g, interior_b, _] =
+            self.mandelbrot_uniform.interior_color.map(f64::from);
+        // Only plain full resolution renders say how long the view takes.
+        let measure = plain && !background;
+        let timestamp_writes = match &self.timer {
+            Some(timer) if measure => timer.timestamp_writes(),
+            _ => None,
+        };
+        let timed = timestamp_writes.is_some();
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                timestamp_writes,
+                occlusion_query_set: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Matches the interior, so letterboxing and frames presented before the
+                        // first draw don't flash a different color.
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: interior_r,
+                            g: interior_g,
+                            b: interior_b,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_viewport(vx, vy, vw, vh, 0.0, 1.0);
+            if let Some(size) = grid {
+                self.draw_grid(&mut render_pass, size);
+            } else if let Some(split) = split {
+                self.draw_split(&mut render_pass, split);
+            } else if let (true, Some((_, bind_group))) = (self.cpu_rendering, &self.cpu_target) {
+                render_pass.set_pipeline(&self.surface_pipelines.blit);
+                render_pass.set_bind_group(1, bind_group, &[]);
+            } else if let (true, Some(target)) = (progressive, &self.progressive_target) {
+                render_pass.set_pipeline(&self.surface_pipelines.progressive_display);
+                render_pass.set_bind_group(1, &target.bind_group, &[]);
+            } else if let (true, Some(target)) = (background, &self.background) {
+                if target.finished(&self.mandelbrot_uniform) {
+                    render_pass.set_pipeline(&self.surface_pipelines.blit);
+                    render_pass.set_bind_group(1, &target.targets[target.front].bind_group, &[]);
+                } else {
+                    render_pass.set_pipeline(&self.surface_pipelines.smooth_blit);
+                    render_pass.set_bind_group(1, &target.preview.bind_group, &[]);
+                }
+            } else if adaptive_aa {
+                render_pass.set_pipeline(&self.surface_pipelines.aa);
+                render_pass.set_bind_group(1, &self.escape_bind_group, &[]);
+            } else if self.fade.is_some() {
+                render_pass.set_pipeline(&self.surface_pipelines.main);
+                render_pass.set_bind_group(0, &self.fade_bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+                render_pass.set_pipeline(&self.surface_pipelines.fade);
+                let blend = self.mode_blend;
+                render_pass.set_blend_constant(wgpu::Color {
+                    r: blend,
+                    g: blend,
+                    b: blend,
+                    a: blend,
+                });
+            } else {
+                render_pass.set_pipeline(&self.surface_pipelines.main);
+            }
+            if split.is_none() && grid.is_none() {
+                render_pass.set_bind_group(0, &self.mandelbrot_bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+            }
+            if let (true, Some((_, targets))) = (bloom, &self.bloom_targets) {
+                render_pass.set_pipeline(&self.surface_pipelines.bloom_composite);
+                render_pass.set_bind_group(0, &self.mandelbrot_bind_group, &[]);
+                render_pass.set_bind_group(1, &targets[0].bind_group, &[]);
+                let intensity = self.bloom_intensity;
+                render_pass.set_blend_constant(wgpu::Color {
+                    r: intensity,
+                    g: intensity,
+                    b: intensity,
+                    a: intensity,
+                });
+                render_pass.draw(0..4, 0..1);
+            }
+            if let Some((x, y, size)) = loupe {
+                render_pass.set_viewport(x, y, size, size, 0.0, 1.0);
+                render_pass.set_pipeline(&self.surface_pipelines.loupe);
+                render_pass.set_bind_group(0, &self.loupe_bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+            }
+            if let (Some((width, height)), Some((_, bind_group))) = (hud, &self.hud_target) {
+                // Left out rather than squashed when the view is too small for it.
+                let margin = HUD_MARGIN * self.window.scale_factor() as f32;
+                if margin + width <= vw && margin + height <= vh {
+                    render_pass.set_viewport(vx + margin, vy + margin, width, height, 0.0, 1.0);
+                    render_pass.set_pipeline(&self.surface_pipelines.blit);
+                    render_pass.set_bind_group(0, &self.mandelbrot_bind_group, &[]);
+                    render_pass.set_bind_group(1, bind_group, &[]);
+                    render_pass.draw(0..4, 0..1);
+                }
+            }
+        }
+
+        if let (true, Some(timer)) = (timed, &self.timer) {
+            timer.resolve(&mut encoder);
+        }
+        let start = Instant::now();
+        self.queue.submit(std::iter::once(encoder.finish()));
+        match &self.timer {
+            Some(timer) if timed => timer.read(1, self.render_time.clone()),
+            None if measure => {
+                // Waiting is the only way to tell GPU time apart from vsync and the frames
+                // still queued. Views slow enough for that to hurt render in the background.
+                self.device.poll(wgpu::Maintain::Wait);
+                let elapsed = start.elapsed();
+                self.render_time
+                    .store(elapsed.as_nanos() as u64, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+        output.present();
+
+        let frame_time = frame_start.elapsed();
+        if let Some(on_frame) = &mut self.on_frame {
+            on_frame(&FrameInfo {
+                uniform: self.mandelbrot_uniform,
+                frame_number: self.frame_number,
+                frame_time,
+                elapsed: self.start_time.elapsed(),
+            });
+        }
+        if let Some(benchmark) = &mut self.benchmark {
+            benchmark.record(frame_time);
+            self.window.request_redraw();
+        }
+        self.frame_meter.record(Instant::now(), frame_time);
+        if self.show_frame_time && self.frame_time_shown.elapsed() >= FRAME_TIME_REFRESH {
+            self.update_title();
+        }
+        self.frame_number += 1;
+
+        Ok(())
+    }
+}
+
+// The shaders and pipeline layouts the pipelines drawing to the surface are built from.
+struct PipelineSources {
+    shader: wgpu::ShaderModule,
+    // Draws the main view instead of `shader` if given, see `--shader`.
+    user_shader: Option<wgpu::ShaderModule>,
+    // Binds the view to group 0.
+    layout: wgpu::PipelineLayout,
+    // Adds a texture in group 1.
+    texture_layout: wgpu::PipelineLayout,
+    // Adds the orbits of progressive rendering in group 1.
+    progressive_layout: wgpu::PipelineLayout,
+}
+
+// The pipelines drawing to the surface, which are built for its format.
+struct SurfacePipelines {
+    main: wgpu::RenderPipeline,
+    // The main view blended by the constant set with `set_blend_constant`.
+    fade: wgpu::RenderPipeline,
+    aa: wgpu::RenderPipeline,
+    divider: wgpu::RenderPipeline,
+    blit: wgpu::RenderPipeline,
+    smooth_blit: wgpu::RenderPipeline,
+    // Adds the blurred bright parts, scaled up to the view, weighted by the blend constant.
+    bloom_composite: wgpu::RenderPipeline,
+    loupe: wgpu::RenderPipeline,
+    progressive_display: wgpu::RenderPipeline,
+}
+
+impl SurfacePipelines {
+    fn new(device: &wgpu::Device, sources: &PipelineSources, format: wgpu::TextureFormat) -> Self {
+        let shader = &sources.shader;
+        let main_shader = sources.user_shader.as_ref().unwrap_or(shader);
+        let pipeline = |layout, shader, entry_point| {
+            gpu::create_pipeline(device, layout, shader, entry_point, format)
+        };
+        let fade_blend = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+            operation: wgpu::BlendOperation::Add,
+        };
+        Self {
+            main: pipeline(&sources.layout, main_shader, "fs_main"),
+            fade: gpu::create_blend_pipeline(
+                device,
+                &sources.layout,
+                main_shader,
+                "fs_main",
+                format,
+                Some(wgpu::BlendState {
+                    color: fade_blend,
+                    alpha: fade_blend,
+                }),
+            ),
+            aa: pipeline(&sources.texture_layout, shader, "fs_adaptive_aa"),
+            divider: pipeline(&sources.layout, shader, "fs_divider"),
+            blit: pipeline(&sources.texture_layout, shader, "fs_blit"),
+            smooth_blit: pipeline(&sources.texture_layout, shader, "fs_blit_smooth"),
+            bloom_composite: gpu::create_blend_pipeline(
+                device,
+                &sources.texture_layout,
+                shader,
+                "fs_blit_smooth",
+                format,
+                Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Constant,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::OVER,
+                }),
+            ),
+            loupe: pipeline(&sources.layout, shader, "fs_loupe"),
+            progressive_display: pipeline(&sources.progressive_layout, shader, "fs_progressive"),
+        }
+    }
+}
+
+// Format of the offscreen target holding per-pixel escape values for adaptive anti-aliasing,
+// with the smooth escape time in the second channel.
+const ESCAPE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Float;
+
+fn create_escape_target(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> (wgpu::TextureView, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Escape Texture"),
+        size: wgpu::Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ESCAPE_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("escape_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&view),
+        }],
+    });
+    (view, bind_group)
+}
+
+// Factor a scroll by `delta` lines or pixels scales the view's size by. Large deltas, e.g. from
+// high resolution touchpads, would otherwise make it zero or negative.
+fn wheel_scale(delta: f64) -> f64 {
+    (1.0 - delta / 10.0).max(MIN_WHEEL_SCALE)
+}
+
+// Lines of scrolling a touchpad scroll by `pixels` amounts to, limited so a single event never
+// zooms by more than a few wheel clicks.
+fn pixel_scroll_lines(pixels: f64) -> f64 {
+    (pixels / PIXELS_PER_SCROLL_LINE).clamp(-MAX_PIXEL_SCROLL_LINES, MAX_PIXEL_SCROLL_LINES)
+}
+
+// The part of `pending`, the log of a zoom factor, to apply in a frame of `dt` seconds. Small
+// remainders are applied at once so the zoom comes to a stop.
+fn scroll_zoom_step(pending: f64, dt: f64) -> f64 {
+    let step = pending * (1.0 - (-SCROLL_ZOOM_RATE * dt).exp());
+    if (pending - step).abs() < MIN_PENDING_SCROLL_ZOOM {
+        pending
+    } else {
+        step
+    }
+}
+
+// `settings` moved to the region `region` shows.
+fn with_region(settings: &MandelbrotUniform, region: &MandelbrotUniform) -> MandelbrotUniform {
+    let MandelbrotUniform {
+        min_x,
+        min_y,
+        height,
+        aspect_ratio,
+        rotation,
+        ..
+    } = *region;
+    MandelbrotUniform {
+        min_x,
+        min_y,
+        height,
+        aspect_ratio,
+        rotation,
+        ..*settings
+    }
+}
+
+fn same_uniform(a: &MandelbrotUniform, b: &MandelbrotUniform) -> bool {
+    bytemuck::bytes_of(a) == bytemuck::bytes_of(b)
+}
+
+// Whether two uniforms show the same region of the plane.
+fn same_region(a: &MandelbrotUniform, b: &MandelbrotUniform) -> bool {
+    (a.min_x, a.min_y, a.height, a.rotation) == (b.min_x, b.min_y, b.height, b.rotation)
+}
+
+/// Converts a cursor position in logical or physical window pixels to surface pixels, which the
+/// viewport is measured in. Logical positions are scaled by `scale_factor`. Physical window and
+/// surface pixels only differ if the window's physical size doesn't match what the surface was
+/// last configured with, e.g. with fractional scaling or pending resizes.
+fn surface_position(
+    position: winit::dpi::Position,
+    scale_factor: f64,
+    window: winit::dpi::PhysicalSize<u32>,
+    surface: winit::dpi::PhysicalSize<u32>,
+) -> winit::dpi::PhysicalPosition<f64> {
+    let position: winit::dpi::PhysicalPosition<f64> = position.to_physical(scale_factor);
+    if window.width == 0 || window.height == 0 {
+        return position;
+    }
+    winit::dpi::PhysicalPosition::new(
+        position.x * surface.width as f64 / window.width as f64,
+        position.y * surface.height as f64 / window.height as f64,
+    )
+}
+
+/// Maps a position in surface pixels to uv coordinates within `viewport` (x, y, width, height),
+/// where `(0, 0)` is the bottom left and `(1, 1)` the top right corner of the viewport.
+fn screen_to_uv(
+    position: winit::dpi::PhysicalPosition<f64>,
+    (x, y, width, height): (f64, f64, f64, f64),
+) -> (f64, f64) {
+    ((position.x - x) / width, 1.0 - (position.y - y) / height)
+}
+
+// Panning momentum decays by a factor of e every 1 / INERTIA_DECAY seconds, and stops once
+// slower than INERTIA_MIN_SPEED view sizes per second.
+const INERTIA_DECAY: f64 = 4.0;
+const INERTIA_MIN_SPEED: f64 = 0.01;
+// The overlay's font pixels are this many logical pixels wide, and it sits this many logical
+// pixels from the corner of the view.
+const HUD_SCALE: f64 = 2.0;
+const HUD_MARGIN: f32 = 8.0;
+// How often the frame time in the title changes while frames keep coming.
+const FRAME_TIME_REFRESH: Duration = Duration::from_millis(500);
+// Shift+arrow keys move the view by this share of its height per press.
+const KEY_PAN_STEP: f64 = 0.1;
+// A drag released after the cursor rested for longer than this doesn't glide.
+const INERTIA_RELEASE_WINDOW: Duration = Duration::from_millis(50);
+
+// Center of the seahorse valley, which the view zooms towards when idle by default.
+const IDLE_TARGET: [f64; 2] = [-0.743643887037151, 0.131825904205330];
+// While warping, the view's height shrinks by a factor of e every 1 / WARP_ZOOM_RATE seconds.
+const WARP_ZOOM_RATE: f64 = 1.0;
+
+// When idle, the view's height shrinks by a factor of e every 1 / IDLE_ZOOM_RATE seconds and
+// its distance to the target every 1 / IDLE_APPROACH_RATE seconds.
+const IDLE_ZOOM_RATE: f64 = 0.2;
+const IDLE_APPROACH_RATE: f64 = 1.0;
+// Height below which f64 coordinates get too coarse, where the idle zoom starts over.
+const IDLE_MIN_HEIGHT: f64 = 1e-12;
+
+// Bloom is computed at 1 / BLOOM_SCALE of the view's resolution, which also widens the blur.
+// Its intensity starts at BLOOM_DEFAULT_INTENSITY and changes in steps of BLOOM_INTENSITY_STEP.
+const BLOOM_SCALE: u32 = 4;
+const BLOOM_DEFAULT_INTENSITY: f64 = 0.5;
+const BLOOM_INTENSITY_STEP: f64 = 0.1;
+
+// Smallest factor a single scroll event can zoom in by.
+const MIN_WHEEL_SCALE: f64 = 0.1;
+// Touchpad scrolling by PIXELS_PER_SCROLL_LINE pixels zooms like one click of a wheel, at most
+// MAX_PIXEL_SCROLL_LINES clicks per event. The zoom still to be done is limited to a factor of
+// e^MAX_PENDING_SCROLL_ZOOM and catches up by a factor of e every 1 / SCROLL_ZOOM_RATE seconds
+// until it's within e^MIN_PENDING_SCROLL_ZOOM.
+const PIXELS_PER_SCROLL_LINE: f64 = 50.0;
+const MAX_PIXEL_SCROLL_LINES: f64 = 3.0;
+const MAX_PENDING_SCROLL_ZOOM: f64 = 2.0;
+const SCROLL_ZOOM_RATE: f64 = 15.0;
+const MIN_PENDING_SCROLL_ZOOM: f64 = 1e-3;
+
+// How many times larger pixels may get before the title warns about the f64 precision limit.
+const PRECISION_WARNING_MARGIN: f64 = 16.0;
+
+// Iterations the area estimate may spend per frame, at worst, and the number of samples after
+// which it stops refining.
+const AREA_ITERATION_BUDGET: u64 = 2_000_000;
+const AREA_MAX_SAMPLES: u64 = 1 << 22;
+
+// Most random samples per pixel the keyboard cycles through.
+const MAX_SAMPLES_PER_PIXEL: u32 = 64;
+
+// Sample counts from which every frame takes long enough to make interaction sluggish.
+const SLOW_SAMPLES_PER_PIXEL: u32 = 16;
+
+pub(crate) fn warn_about_samples(samples: u32) {
+    if samples >= SLOW_SAMPLES_PER_PIXEL {
+        log::warn!("{samples} samples per pixel iterate every pixel {samples} times per frame");
+    }
+}
+
+// Factor the +/- keys zoom by if `--zoom-step` isn't given.
+const DEFAULT_ZOOM_STEP: u32 = 2;
+
+// The height `steps` zoom steps of `factor` in (or out, if negative) from `height`, landing on a
+// whole power of `factor` relative to `default_height` even if `height` was between two.
+fn step_zoom(height: f64, default_height: f64, factor: u32, steps: i32) -> f64 {
+    let factor = factor as f64;
+    let level = (default_height / height).log(factor);
+    // Heights that are a power of the factor up to rounding count as exactly on it.
+    let level = if steps > 0 {
+        (level + 1e-9).floor() + steps as f64
+    } else {
+        (level - 1e-9).ceil() + steps as f64
+    };
+    default_height / factor.powf(level)
+}
+
+// Lowest `max_iterations` the keyboard and mouse wheel go down to.
+const MIN_ITERATIONS: u32 = 128;
+
+// With `--target-fps`, frames should take at least ITERATION_BUDGET_HEADROOM of the budget, and
+// `max_iterations` changes by at most a factor of ITERATION_BUDGET_STEP per frame, up to
+// MAX_BUDGETED_ITERATIONS.
+const ITERATION_BUDGET_HEADROOM: f64 = 0.7;
+const ITERATION_BUDGET_STEP: f64 = 1.25;
+const MAX_BUDGETED_ITERATIONS: u32 = 1 << 20;
+
+// Scales `iterations` by how far a render taking `time` was from `budget`, by at most
+// ITERATION_BUDGET_STEP. Renders between ITERATION_BUDGET_HEADROOM of the budget and the budget
+// itself are left alone, so the limit settles instead of redrawing forever.
+fn budgeted_iterations(iterations: u32, time: Duration, budget: Duration) -> u32 {
+    let ratio = budget.as_secs_f64() / time.as_secs_f64();
+    let scale = if ratio < 1.0 {
+        ratio.max(1.0 / ITERATION_BUDGET_STEP)
+    } else if ratio * ITERATION_BUDGET_HEADROOM > 1.0 {
+        (ratio * ITERATION_BUDGET_HEADROOM).min(ITERATION_BUDGET_STEP)
+    } else {
+        return iterations;
+    };
+    ((iterations as f64 * scale).round() as u32).clamp(MIN_ITERATIONS, MAX_BUDGETED_ITERATIONS)
+}
+
+// Time it takes to cross-fade between coloring settings.
+const FADE_DURATION: Duration = Duration::from_millis(250);
+
+// Number of views kept for undoing, and the time within which changes count as one.
+const HISTORY_LENGTH: usize = 100;
+const HISTORY_DEBOUNCE: Duration = Duration::from_millis(500);
+// Time it takes to glide to a view from the history.
+const GLIDE_DURATION: Duration = Duration::from_millis(400);
+
+// Width of the split screen divider, and how close to it the cursor has to be to drag it, in
+// physical pixels.
+const DIVIDER_WIDTH: u32 = 2;
+const DIVIDER_GRAB_DISTANCE: f64 = 4.0;
+
+// Color period the palette starts repeating with when first adjusted.
+const COLOR_PERIOD_START: f32 = 32.0;
+
+// Number of lines the axes grid spans the view's height with, roughly.
+const AXES_GRID_LINES: f64 = 8.0;
+
+// Inner size of the window if `--window-size` isn't given.
+pub(crate) const DEFAULT_WINDOW_SIZE: [u32; 2] = [800, 600];
+// Name the window title starts with unless the config file gives another.
+const WINDOW_TITLE: &str = "Mandelbrot Explorer";
+
+// Size of images rendered with `--out` if `--size` isn't given.
+const DEFAULT_OUT_SIZE: [u32; 2] = [1920, 1080];
+
+// File Ctrl+E saves the palette to and Ctrl+O loads it from, in the working directory.
+const PALETTE_FILE: &str = "palette.txt";
+
+// Format `fs_inspect` writes the escape time of the inspected pixel in.
+const INSPECT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+// Offset of the escape time in `InspectProbe::readback`, after the color's row.
+const INSPECT_ROW: usize = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+
+// Single pixel targets the inspector renders to, and the buffer they're read back through
+// without blocking.
+struct InspectProbe {
+    // Targets of `screenshot_pipeline` and `inspect_pipeline`.
+    color: wgpu::Texture,
+    escape: wgpu::Texture,
+    // The color at the start and the escape time at INSPECT_ROW, as texture copies need rows
+    // of their own.
+    readback: wgpu::Buffer,
+    // The inspection waiting for `readback` to be mapped, and the mapping's result.
+    pending: Option<(
+        Inspection,
+        mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    )>,
+    // Whether the cursor moved since the pending inspection was started.
+    stale: bool,
+}
+
+impl InspectProbe {
+    fn new(device: &wgpu::Device) -> Self {
+        let target = |format| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Inspect Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        };
+        Self {
+            color: target(gpu::IMAGE_FORMAT),
+            escape: target(INSPECT_FORMAT),
+            readback: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Inspect Readback Buffer"),
+                size: 2 * INSPECT_ROW as u64,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            pending: None,
+            stale: false,
+        }
+    }
+
+    fn copy_to_readback(&self, encoder: &mut wgpu::CommandEncoder) {
+        for (texture, offset) in [(&self.color, 0), (&self.escape, INSPECT_ROW as u64)] {
+            encoder.copy_texture_to_buffer(
+                texture.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &self.readback,
+                    layout: wgpu::ImageDataLayout {
+                        offset,
+                        bytes_per_row: None,
+                        rows_per_image: None,
+                    },
+                },
+                texture.size(),
+            );
+        }
+    }
+}
+
+// What the inspector found out about the pixel under the cursor.
+struct Inspection {
+    point: [f64; 2],
+    // Size of the pixel, which the point is shown to the precision of.
+    resolution: f64,
+    smooth_iterations: f32,
+    iterations: u32,
+    max_iterations: u32,
+    interior: bool,
+    // The color as displayed, in sRGB.
+    rgb: [u8; 3],
+}
+
+impl std::fmt::Display for Inspection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", format_point(self.point, self.resolution))?;
+        if self.interior {
+            write!(f, "interior after {} iterations", self.iterations)?;
+        } else if self.iterations >= self.max_iterations {
+            write!(f, "didn't escape")?;
+        } else {
+            write!(f, "escaped after {:.3} iterations", self.smooth_iterations)?;
+        }
+        let [r, g, b] = self.rgb;
+        write!(f, ", #{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+// Diameter of the loupe in surface pixels, and how much more it magnifies than the view.
+const LOUPE_SIZE: f64 = 240.0;
+const LOUPE_MAGNIFICATION: f64 = 8.0;
+
+// Pixels compared by `--verify-precision` if `--size` isn't given.
+const VERIFY_PRECISION_SIZE: [u32; 2] = [160, 120];
+
+// Largest number of tiles per side of the comparison grid.
+const GRID_MAX_SIZE: u32 = 4;
+// The last tiles of large grids would otherwise take millions of iterations per pixel, long
+// enough for the driver to reset the GPU.
+const MAX_GRID_ITERATIONS: u32 = 16384;
+
+// Iteration count of tile `i` of a `size * size` comparison grid. The counts grow geometrically
+// from 64, by 4x per tile on a 2x2 grid and less on larger ones, up to MAX_GRID_ITERATIONS.
+fn grid_iterations(size: u32, i: u32) -> u32 {
+    let shift = (8 / (size * size - 1)).max(1);
+    (64 << (shift * i)).min(MAX_GRID_ITERATIONS)
+}
+
+// Decimal places that tell apart coordinates `resolution` apart.
+fn decimals(resolution: f64) -> usize {
+    (-resolution.log10()).ceil().clamp(0.0, 17.0) as usize + 1
+}
+
+// Formats a point as a complex number with enough decimals to tell apart points `resolution`
+// apart.
+fn format_point([x, y]: [f64; 2], resolution: f64) -> String {
+    let decimals = decimals(resolution);
+    format!("{x:.decimals$}{y:+.decimals$}i")
+}
+
+// Formats a magnification like `3.4×10¹²`, or plainly while it's small.
+fn format_zoom(zoom: f64) -> String {
+    if zoom < 1e3 {
+        return format!("{zoom:.1}×");
+    }
+    let exponent = zoom.log10().floor();
+    let mantissa = zoom / 10f64.powf(exponent);
+    // Rounding may carry into the next power of ten.
+    let (mantissa, exponent) = if mantissa >= 9.95 {
+        (1.0, exponent + 1.0)
+    } else {
+        (mantissa, exponent)
+    };
+    let superscript: String = (exponent as i32)
+        .to_string()
+        .chars()
+        .map(|digit| match digit {
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4' => '⁴',
+            '5' => '⁵',
+            '6' => '⁶',
+            '7' => '⁷',
+            '8' => '⁸',
+            _ => '⁹',
+        })
+        .collect();
+    format!("{mantissa:.1}×10{superscript}")
+}
+
+// Lists the iteration counts of the grid's tiles, rows separated by `|`.
+fn grid_labels(size: u32) -> String {
+    let rows: Vec<String> = (0..size)
+        .map(|row| {
+            let tiles: Vec<String> = (0..size)
+                .map(|col| grid_iterations(size, row * size + col).to_string())
+                .collect();
+            tiles.join(" ")
+        })
+        .collect();
+    format!("iterations {}", rows.join(" | "))
+}
+
+// Brightness the filaments closest to the set are boosted to on HDR surfaces, where 1.0 is SDR
+// white.
+const HDR_PEAK: f32 = 4.0;
+
+// Iterations progressive rendering adds per frame, small enough to stay well clear of driver
+// timeouts even at large window sizes.
+const PROGRESSIVE_ITERATIONS_PER_PASS: u32 = 1024;
+
+// Size of `PixelState` in `shader.wgsl`, including its padding. Only half of it is used when the
+// shader computes in f32.
+const PROGRESSIVE_PIXEL_SIZE: u64 = 48;
+
+struct ProgressiveTarget {
+    size: [u32; 2],
+    state: wgpu::Buffer,
+    _params: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ProgressiveTarget {
+    fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, size: [u32; 2]) -> Self {
+        let state = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Progressive State Buffer"),
+            size: u64::from(size[0]) * u64::from(size[1]) * PROGRESSIVE_PIXEL_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Progressive Params Buffer"),
+            contents: bytemuck::cast_slice(&[size[0], size[1], PROGRESSIVE_ITERATIONS_PER_PASS, 0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("progressive_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: state.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+        });
+        Self {
+            size,
+            state,
+            _params: params,
+            bind_group,
+        }
+    }
+}
+
+// Renders of the full resolution view taking longer than this are spread over several frames,
+// showing a low resolution preview in the meantime.
+const HEAVY_RENDER_TIME: Duration = Duration::from_millis(50);
+// Roughly how long each submission of a background render should take.
+const BACKGROUND_BAND_TIME: Duration = Duration::from_millis(16);
+const MAX_BACKGROUND_BANDS: u32 = 64;
+// The preview has a pixel per this many pixels of the view in either direction.
+const PREVIEW_SCALE: u32 = 4;
+// Holds colors in linear light, like the surface, and HDR values above 1.
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Full resolution render of a heavy view, drawn in horizontal bands over several submissions so
+// that previews can be presented in between. `targets[front]` holds the last finished render, of
+// `front_uniform`, while the other one is rendered to.
+struct BackgroundRender {
+    size: [u32; 2],
+    targets: [OffscreenTarget; 2],
+    front: usize,
+    front_uniform: Option<MandelbrotUniform>,
+    preview: OffscreenTarget,
+    job: Option<BackgroundJob>,
+    // Set once the submission with the last band of `job` completed.
+    done: Arc<AtomicBool>,
+}
+
+struct BackgroundJob {
+    uniform: MandelbrotUniform,
+    // Next band to submit, out of `bands`.
+    band: u32,
+    bands: u32,
+}
+
+impl BackgroundRender {
+    fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, size: [u32; 2]) -> Self {
+        let preview_size = size.map(|n| n.div_ceil(PREVIEW_SCALE));
+        Self {
+            size,
+            targets: [(); 2].map(|_| OffscreenTarget::new(device, layout, size)),
+            front: 0,
+            front_uniform: None,
+            preview: OffscreenTarget::new(device, layout, preview_size),
+            job: None,
+            done: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Whether the front target shows `uniform`.
+    fn finished(&self, uniform: &MandelbrotUniform) -> bool {
+        self.front_uniform
+            .is_some_and(|front| same_uniform(&front, uniform))
+    }
+}
+
+struct OffscreenTarget {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl OffscreenTarget {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        [width, height]: [u32; 2],
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OFFSCREEN_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("offscreen_bind_group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        Self { view, bind_group }
+    }
+}
+
+fn create_cpu_target(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    size: wgpu::Extent3d,
+) -> (wgpu::Texture, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("CPU Texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("cpu_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&view),
+        }],
+    });
+    (texture, bind_group)
+}
+
+pub async fn run() {
+    env_logger::init();
+    exit_on_error(ExplorerBuilder::from_args(cli::Args::parse()).run().await)
+}
+
+/// Like [`run`], but calls `on_frame` after every presented frame.
+pub async fn run_with_frame_callback(on_frame: impl FnMut(&FrameInfo) + 'static) {
+    env_logger::init();
+    let result = ExplorerBuilder::from_args(cli::Args::parse())
+        .with_frame_callback(on_frame)
+        .run()
+        .await;
+    exit_on_error(result)
+}
+
+fn exit_on_error(result: Result<(), StateError>) {
+    if let Err(e) = result {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+// Renders the start view to `path` without a window, in the format its extension names.
+async fn render_to_file(
+    args: &cli::Args,
+    palette: &palette::Palette,
+    path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let format = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(ImageFormat::from_extension)
+        .unwrap_or(args.format);
+    let [width, height] = args.size.unwrap_or(DEFAULT_OUT_SIZE);
+    let uniform = args.render_view([width, height]);
+
+    let headless = Headless::with_backends(args.backends()).await?;
+    headless.set_palette(palette);
+    match (args.strip_height, format) {
+        (Some(rows), ImageFormat::Png) => {
+            let progress = |done| println!("rendered {done} of {height} rows");
+            headless
+                .save_png_strips(&uniform, [width, height], rows, path, progress)
+                .await?
+        }
+        (strip_height, _) => {
+            if strip_height.is_some() {
+                log::warn!("--strip-height only applies to PNGs, rendering at once");
+            }
+            headless
+                .save_image(&uniform, width, height, format, path)
+                .await?
+        }
+    }
+    if !args.no_manifest {
+        Manifest::new(&uniform).write(path.with_extension("json"))?;
+    }
+    println!("saved {}", path.display());
+    Ok(())
+}
+
+// Saves a heightmap of the distance to the set in the start view to `path`, without a window.
+async fn render_heightmap(
+    args: &cli::Args,
+    path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let [width, height] = args.size.unwrap_or(DEFAULT_OUT_SIZE);
+    let uniform = args.render_view([width, height]);
+
+    let headless = Headless::with_backends(args.backends()).await?;
+    headless
+        .save_heightmap(&uniform, width, height, path)
+        .await?;
+    if !args.no_manifest {
+        Manifest::new(&uniform).write(path.with_extension("json"))?;
+    }
+    println!("saved {}", path.display());
+    Ok(())
+}
+
+async fn render_contour_svg(
+    args: &cli::Args,
+    path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let [width, height] = args.size.unwrap_or(DEFAULT_OUT_SIZE);
+    let uniform = args.render_view([width, height]);
+
+    let headless = Headless::with_backends(args.backends()).await?;
+    headless
+        .save_contour_svg(&uniform, width, height, uniform.contour_interval, path)
+        .await?;
+    if !args.no_manifest {
+        Manifest::new(&uniform).write(path.with_extension("json"))?;
+    }
+    println!("saved {}", path.display());
+    Ok(())
+}
+
+// Renders every job of the batch file at `path`, returning how many failed.
+async fn run_batch(
+    args: &cli::Args,
+    palette: &palette::Palette,
+    path: &std::path::Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let jobs = batch::load(path)?;
+    let headless = Headless::with_backends(args.backends()).await?;
+    headless.set_palette(palette);
+    Ok(batch::run(&headless, &jobs, !args.no_manifest).await)
+}
+
+// Renders the start view at the size of the image at `path` and returns how much it differs
+// from it, see `Headless::compare_to`.
+async fn compare_to_golden(
+    args: &cli::Args,
+    palette: &palette::Palette,
+    path: &std::path::Path,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    let (width, height) = image::image_dimensions(path)?;
+    let uniform = args.render_view([width, height]);
+    let headless = Headless::with_backends(args.backends()).await?;
+    headless.set_palette(palette);
+    Ok(headless.compare_to(&uniform, path).await?)
+}
+
+// Runs whatever `args` ask for, the interactive explorer unless it's one of the headless modes.
+pub(crate) async fn run_with_args(
+    args: cli::Args,
+    on_frame: Option<FrameCallback>,
+) -> Result<(), StateError> {
+    if args.verify_precision {
+        let report = precision_check::verify(args.size.unwrap_or(VERIFY_PRECISION_SIZE));
+        println!("{report}");
+        if !report.passed() {
+            return Err(StateError::Headless(
+                "the precision check failed".to_owned(),
+            ));
+        }
+        return Ok(());
+    }
+
+    let palette = match (&args.palette, &args.palette_image, args.random_palette) {
+        (Some(path), _, _) => palette::load(path).unwrap_or_else(|e| {
+            log::error!("failed to load palette from {}: {e}", path.display());
+            palette::hsv_ramp()
+        }),
+        (None, Some(path), _) => palette::from_image(path).unwrap_or_else(|e| {
+            log::error!("failed to load palette from {}: {e}", path.display());
+            palette::hsv_ramp()
+        }),
+        (None, None, Some(seed)) => {
+            println!("random palette seed: {seed}");
+            palette::random(seed)
+        }
+        (None, None, None) => palette::hsv_ramp(),
+    };
+
+    if let Some(path) = &args.batch {
+        return match run_batch(&args, &palette, path).await {
+            Ok(0) => Ok(()),
+            Ok(failed) => Err(StateError::Headless(format!("{failed} jobs failed"))),
+            Err(e) => Err(StateError::Headless(format!(
+                "failed to run {}: {e}",
+                path.display()
+            ))),
+        };
+    }
+
+    if let Some(path) = &args.heightmap {
+        return render_heightmap(&args, path).await.map_err(|e| {
+            StateError::Headless(format!("failed to render {}: {e}", path.display()))
+        });
+    }
+
+    if let Some(path) = &args.svg {
+        return render_contour_svg(&args, path).await.map_err(|e| {
+            StateError::Headless(format!("failed to render {}: {e}", path.display()))
+        });
+    }
+
+    if let Some(path) = &args.compare_to {
+        let max_error = args.max_error.unwrap_or(headless::MAX_GOLDEN_ERROR);
+        return match compare_to_golden(&args, &palette, path).await {
+            Ok(error) if error <= max_error => {
+                println!("mean absolute error {error:.4} from {}", path.display());
+                Ok(())
+            }
+            Ok(error) => Err(StateError::Headless(format!(
+                "mean absolute error {error:.4} from {} exceeds {max_error}",
+                path.display()
+            ))),
+            Err(e) => Err(StateError::Headless(format!(
+                "failed to compare to {}: {e}",
+                path.display()
+            ))),
+        };
+    }
+
+    if let Some(path) = &args.out {
+        return render_to_file(&args, &palette, path).await.map_err(|e| {
+            StateError::Headless(format!("failed to render {}: {e}", path.display()))
+        });
+    }
+
+    let config = load_config(&args);
+    let event_loop = EventLoop::new().map_err(StateError::EventLoop)?;
+    let window = WindowBuilder::new()
+        .with_inner_size(winit::dpi::PhysicalSize::<u32>::from(
+            args.window_size.unwrap_or(DEFAULT_WINDOW_SIZE),
+        ))
+        .with_title(config.title.as_deref().unwrap_or(WINDOW_TITLE))
+        .with_window_icon(config.icon.as_deref().and_then(|path| {
+            load_icon(path)
+                .map_err(|e| log::error!("failed to load icon from {}: {e}", path.display()))
+                .ok()
+        }))
+        .build(&event_loop)
+        .map_err(StateError::CreateWindow)?;
+
+    let mut app = App {
+        config,
+        args,
+        window: Arc::new(window),
+        palette,
+        on_frame,
+        view: None,
+        surface_format: None,
+        state: None,
+        error: None,
+    };
+    event_loop
+        .run(|event, tgt| app.handle(event, tgt))
+        .map_err(StateError::EventLoop)?;
+    app.error.map_or(Ok(()), Err)
+}
+
+// Names screenshots after when they were taken and the region they show, so they're
+// self-documenting even without a manifest. All three numbers round-trip, the height in
+// scientific notation to show the depth at a glance.
+fn screenshot_name(uniform: &MandelbrotUniform, timestamp: u64, extension: &str) -> String {
+    format!(
+        "mandelbrot-{timestamp}_x{}_y{}_h{:e}.{extension}",
+        uniform.min_x, uniform.min_y, uniform.height
+    )
+}
+
+// Reads the config file given with `--config`, or the default one if there is one. Problems
+// with it are logged and leave the defaults in place.
+fn load_config(args: &cli::Args) -> Config {
+    let path = match &args.config {
+        Some(path) => path.clone(),
+        None if std::path::Path::new(config::CONFIG_FILE).exists() => config::CONFIG_FILE.into(),
+        None => return Config::default(),
+    };
+    Config::load(&path).unwrap_or_else(|e| {
+        log::error!("failed to load config from {}: {e}", path.display());
+        Config::default()
+    })
+}
+
+fn load_icon(path: &std::path::Path) -> Result<Icon, Box<dyn std::error::Error>> {
+    let image = image::open(path)?.into_rgba8();
+    let (width, height) = image.dimensions();
+    Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+}
+
+// Owns the window and the state drawing into it, which only exists while the app is resumed.
+// Mobile platforms take the surface away while suspended, so it's dropped along with the
+// device and recreated on resume, keeping the view and palette.
+struct App {
+    args: cli::Args,
+    config: Config,
+    window: Arc<Window>,
+    // What the next state starts with, updated from the current one when it's dropped.
+    palette: palette::Palette,
+    on_frame: Option<FrameCallback>,
+    view: Option<MandelbrotUniform>,
+    // Chosen with Ctrl+R, otherwise the state picks one.
+    surface_format: Option<wgpu::TextureFormat>,
+    state: Option<State>,
+    // Why the state couldn't be created, which ends the event loop.
+    error: Option<StateError>,
+}
+
+impl App {
+    fn handle(&mut self, event: Event<()>, tgt: &EventLoopWindowTarget<()>) {
+        if self.state.as_ref().is_some_and(State::device_lost) {
+            // Everything created from the lost device is unusable, so start over with a new one.
+            log::info!("recreating the GPU device");
+            self.suspended();
+            self.resumed(tgt);
+        }
+        match event {
+            Event::Resumed => self.resumed(tgt),
+            Event::Suspended => self.suspended(),
+            Event::AboutToWait => self.about_to_wait(tgt),
+            Event::WindowEvent { window_id, event } if window_id == self.window.id() => {
+                self.window_event(event, tgt)
+            }
+            _ => {}
+        }
+    }
+
+    fn resumed(&mut self, tgt: &EventLoopWindowTarget<()>) {
+        if self.state.is_some() || self.error.is_some() {
+            return;
+        }
+        let state = pollster::block_on(State::new(
+            self.window.clone(),
+            &self.palette,
+            &self.config,
+            &self.args,
+            self.surface_format,
+            self.on_frame.take(),
+        ));
+        let mut state = match state {
+            Ok(state) => state,
+            Err(e) => {
+                self.error = Some(e);
+                tgt.exit();
+                return;
+            }
+        };
+        if let Some(view) = self.view.take() {
+            // The peak brightness goes with the surface, which may not be the old one's format.
+            state.mandelbrot_uniform = MandelbrotUniform {
+                hdr_peak: state.mandelbrot_uniform.hdr_peak,
+                ..view
+            };
+            state.update_aspect_ratio();
+        }
+        self.state = Some(state);
+    }
+
+    fn suspended(&mut self) {
+        if let Some(mut state) = self.state.take() {
+            self.view = Some(state.mandelbrot_uniform);
+            self.palette = state.palette;
+            self.surface_format = state.chosen_surface_format;
+            self.on_frame = state.on_frame.take();
+        }
+    }
+
+    fn about_to_wait(&mut self, tgt: &EventLoopWindowTarget<()>) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        let inspecting = state.poll_inspection();
+        if state.warping || state.continuous {
+            // Draw frames back to back for as long as the button is held or the mode is on.
+            tgt.set_control_flow(ControlFlow::Poll);
+            state.window.request_redraw();
+            return;
+        }
+        if inspecting {
+            // Check back until the inspected pixel is read back.
+            tgt.set_control_flow(ControlFlow::Poll);
+            return;
+        }
+        if state
+            .frame_time_deadline()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            state.update_title();
+        }
+        if state.idle() {
+            state.window.request_redraw();
+        }
+        // Wake up in time to start zooming, or to show that frames stopped coming.
+        let idle_deadline = state.idle_deadline().filter(|_| !state.idle());
+        match idle_deadline
+            .into_iter()
+            .chain(state.frame_time_deadline())
+            .min()
+        {
+            Some(deadline) => tgt.set_control_flow(ControlFlow::WaitUntil(deadline)),
+            None => tgt.set_control_flow(ControlFlow::Wait),
+        }
+    }
+
+    fn window_event(&mut self, event: WindowEvent, tgt: &EventLoopWindowTarget<()>) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if state.input(&event) {
+            return;
+        }
+        match event {
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key: Key::Named(NamedKey::Escape),
+                        ..
+                    },
+                ..
+            } => tgt.exit(),
+            WindowEvent::Resized(physical_size) => {
+                state.resize(physical_size);
+            }
+            WindowEvent::RedrawRequested => {
+                state.update();
+                match state.render() {
+                    Ok(_) => {}
+                    Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
+                    Err(wgpu::SurfaceError::OutOfMemory) => tgt.exit(),
+                    Err(e) => eprintln!("{:?}", e),
+                }
+                if state.benchmark_done() {
+                    state.report_benchmark(&self.args);
+                    tgt.exit();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screenshot_names_hold_the_region() {
+        let uniform = MandelbrotUniform {
+            min_x: -0.743643887037151,
+            min_y: 0.131825904205330,
+            height: 1.5e-10,
+            ..MandelbrotUniform::default_view(1.0)
+        };
+        let name = screenshot_name(&uniform, 1700000000, "png");
+        assert_eq!(
+            name,
+            "mandelbrot-1700000000_x-0.743643887037151_y0.13182590420533_h1.5e-10.png"
+        );
+        let stem = std::path::Path::new(&name).file_stem().unwrap();
+        let fields: Vec<_> = stem.to_str().unwrap().split('_').collect();
+        let parse = |field: &str, prefix| field.strip_prefix(prefix).unwrap().parse::<f64>();
+        assert_eq!(parse(fields[1], "x"), Ok(uniform.min_x));
+        assert_eq!(parse(fields[2], "y"), Ok(uniform.min_y));
+        assert_eq!(parse(fields[3], "h"), Ok(uniform.height));
+    }
+
+    #[test]
+    fn cursor_positions_map_to_uv() {
+        use winit::dpi::{LogicalPosition, PhysicalPosition, PhysicalSize};
+        let size = PhysicalSize::new(1500, 1000);
+        // Letterboxed to a square in the middle of the window.
+        let viewport = (250.0, 0.0, 1000.0, 1000.0);
+        let uv = |position: winit::dpi::Position| {
+            screen_to_uv(surface_position(position, 1.5, size, size), viewport)
+        };
+        assert_eq!(uv(PhysicalPosition::new(750.0, 500.0).into()), (0.5, 0.5));
+        assert_eq!(uv(PhysicalPosition::new(250.0, 1000.0).into()), (0.0, 0.0));
+        assert_eq!(uv(PhysicalPosition::new(1250.0, 0.0).into()), (1.0, 1.0));
+        // The same points in logical pixels at 150% scaling.
+        assert_eq!(
+            uv(LogicalPosition::new(500.0, 1000.0 / 3.0).into()),
+            (0.5, 0.5)
+        );
+        assert_eq!(
+            uv(LogicalPosition::new(500.0 / 3.0, 2000.0 / 3.0).into()),
+            (0.0, 0.0)
+        );
+        // A surface still at its old size while the window already grew.
+        let window = PhysicalSize::new(3000, 2000);
+        let position = PhysicalPosition::new(1500.0, 1000.0).into();
+        assert_eq!(
+            screen_to_uv(surface_position(position, 1.5, window, size), viewport),
+            (0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn iterations_follow_the_frame_budget() {
+        let budget = Duration::from_millis(10);
+        let scaled = |iterations, millis| {
+            budgeted_iterations(iterations, Duration::from_secs_f64(millis / 1e3), budget)
+        };
+        // Within the headroom nothing changes.
+        assert_eq!(scaled(1000, 10.0), 1000);
+        assert_eq!(scaled(1000, 8.0), 1000);
+        // Slightly too fast or slow changes by the ratio, far off by a limited step.
+        assert_eq!(scaled(1000, 6.0), 1167);
+        assert_eq!(scaled(1000, 11.0), 909);
+        assert_eq!(scaled(1000, 1.0), 1250);
+        assert_eq!(scaled(1000, 100.0), 800);
+        assert_eq!(scaled(MIN_ITERATIONS, 100.0), MIN_ITERATIONS);
+        assert_eq!(
+            scaled(MAX_BUDGETED_ITERATIONS, 1.0),
+            MAX_BUDGETED_ITERATIONS
+        );
+    }
+
+    #[test]
+    fn grid_iterations_are_capped() {
+        assert_eq!(
+            (0..4).map(|i| grid_iterations(2, i)).collect::<Vec<_>>(),
+            [64, 256, 1024, 4096]
+        );
+        for size in 2..=GRID_MAX_SIZE {
+            let last = grid_iterations(size, size * size - 1);
+            assert!(last <= MAX_GRID_ITERATIONS, "{size}x{size} grid: {last}");
+        }
+    }
+
+    #[test]
+    fn extreme_scrolling_keeps_the_view() {
+        let mut uniform = MandelbrotUniform::default_view(4.0 / 3.0);
+        for delta in [10.0, 120.0, 1e6, f64::MAX] {
+            uniform.zoom_at(0.3, 0.7, wheel_scale(delta));
+            assert!(uniform.height > 0.0, "height collapsed after {delta}");
+            assert!(uniform.min_x.is_finite() && uniform.min_y.is_finite());
+        }
+    }
+
+    #[test]
+    fn touchpad_scrolling_zooms_smoothly() {
+        assert_eq!(pixel_scroll_lines(25.0), 0.5);
+        assert_eq!(pixel_scroll_lines(-1e4), -MAX_PIXEL_SCROLL_LINES);
+        // A burst of events is spread over frames and eventually fully applied.
+        let mut pending = (0..100).fold(0.0f64, |pending, _| {
+            (pending + wheel_scale(pixel_scroll_lines(400.0)).ln())
+                .clamp(-MAX_PENDING_SCROLL_ZOOM, MAX_PENDING_SCROLL_ZOOM)
+        });
+        assert_eq!(pending, -MAX_PENDING_SCROLL_ZOOM);
+        let mut total = 0.0;
+        for _ in 0..60 {
+            let step = scroll_zoom_step(pending, 1.0 / 60.0);
+            assert!(step.abs() < 0.5, "jumped by {step}");
+            pending -= step;
+            total += step;
+        }
+        assert_eq!(pending, 0.0);
+        assert!((total + MAX_PENDING_SCROLL_ZOOM).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zoom_steps_snap_to_powers_of_the_factor() {
+        let mut height = 2.0;
+        for _ in 0..10 {
+            height = step_zoom(height, 2.0, 2, 1);
+        }
+        assert_eq!(height, 2.0 / 1024.0);
+        assert_eq!(step_zoom(height, 2.0, 2, -3), 2.0 / 128.0);
+        // From between two steps to the next power in either direction.
+        assert_eq!(step_zoom(2.0 / 5.0, 2.0, 2, 1), 2.0 / 8.0);
+        assert_eq!(step_zoom(2.0 / 5.0, 2.0, 2, -1), 2.0 / 4.0);
+        assert_eq!(step_zoom(2.0, 2.0, 10, 2), 0.02);
+    }
+
+    #[test]
+    fn zoom_is_formatted_with_superscripts() {
+        assert_eq!(format_zoom(1.0), "1.0×");
+        assert_eq!(format_zoom(3.4e12), "3.4×10¹²");
+        assert_eq!(format_zoom(9.99e5), "1.0×10⁶");
+    }
+
+    #[test]
+    fn points_are_formatted_to_the_pixel() {
+        assert_eq!(format_point([-0.75, 0.1], 0.004), "-0.7500+0.1000i");
+        assert_eq!(
+            format_point([0.25, -1e-9], 1e-10),
+            "0.25000000000-0.00000000100i"
+        );
+    }
+}
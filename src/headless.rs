@@ -0,0 +1,785 @@
+//! Rendering without a window, for analysing views programmatically.
+
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    contours, gpu,
+    manifest::{Manifest, SequenceFrame, SequenceManifest},
+    palette, MandelbrotUniform,
+};
+
+/// Distance from the set in pixels at which [`Headless::save_heightmap`] reaches `1 - 1/e` of
+/// its full height.
+pub const HEIGHTMAP_SCALE: f32 = 32.0;
+
+/// Set on escape values of pixels that were detected to be inside the set before reaching
+/// `max_iterations`. Must match `INTERIOR_BIT` in `shader.wgsl`.
+pub const INTERIOR_BIT: u32 = 1 << 31;
+
+/// Largest [`mean_absolute_error`] a render may have from its golden image. Renders of the same
+/// view on different GPUs differ by a few pixels along the boundary and by rounding, which stays
+/// well below this, while a shifted view or a changed palette doesn't.
+pub const MAX_GOLDEN_ERROR: f64 = 0.002;
+
+/// The mean absolute difference between the channels of `a` and `b`, from 0 for identical
+/// images to 1. `None` if their sizes differ.
+pub fn mean_absolute_error(a: &image::RgbImage, b: &image::RgbImage) -> Option<f64> {
+    if a.dimensions() != b.dimensions() {
+        return None;
+    }
+    let total: u64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(&x, &y)| u64::from(x.abs_diff(y)))
+        .sum();
+    Some(total as f64 / (a.as_raw().len().max(1) as f64 * 255.0))
+}
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAdapter => write!(f, "no compatible GPU adapter found"),
+            Self::RequestDevice(e) => write!(f, "failed to open the GPU device: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+/// Path the constant of [`Headless::record_julia_sweep`] follows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SweepPath {
+    /// A full counterclockwise turn around `center`, starting on its right.
+    Circle { center: [f64; 2], radius: f64 },
+    /// A straight line from `from` to `to`.
+    Line { from: [f64; 2], to: [f64; 2] },
+}
+
+impl SweepPath {
+    /// The point at `t` from 0 at the start of the path to 1 at its end.
+    pub fn point(self, t: f64) -> [f64; 2] {
+        match self {
+            Self::Circle {
+                center: [x, y],
+                radius,
+            } => {
+                let angle = std::f64::consts::TAU * t;
+                [x + radius * angle.cos(), y + radius * angle.sin()]
+            }
+            Self::Line {
+                from: [x0, y0],
+                to: [x1, y1],
+            } => [x0 + (x1 - x0) * t, y0 + (y1 - y0) * t],
+        }
+    }
+}
+
+/// File format rendered images are saved in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// 8-bit sRGB color.
+    #[default]
+    Png,
+    /// 32-bit float linear color, plus the smooth escape time in an `iterations` channel.
+    Exr,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Exr => "exr",
+        }
+    }
+
+    /// Parses a format from its file extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "exr" => Some(Self::Exr),
+            _ => None,
+        }
+    }
+}
+
+/// Summary of the escape values of a rendered view.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IterationStats {
+    /// Fraction of pixels that never escaped, i.e. that are (presumably) inside the set.
+    pub interior_fraction: f64,
+    /// `histogram[i]` is the number of pixels that escaped after exactly `i` iterations.
+    pub histogram: Vec<u32>,
+    /// Smallest iteration count among escaped pixels, `None` if no pixel escaped.
+    pub min_iterations: Option<u32>,
+    /// Largest iteration count among escaped pixels, `None` if no pixel escaped.
+    pub max_iterations: Option<u32>,
+}
+
+impl IterationStats {
+    /// Computes the statistics of escape values as returned by [`Headless::escape_values`].
+    pub fn from_escape_values(escapes: &[u32], max_iterations: u32) -> Self {
+        let mut histogram = vec![0; max_iterations as usize];
+        let mut interior = 0usize;
+        for &its in escapes {
+            if its & INTERIOR_BIT != 0 || its >= max_iterations {
+                interior += 1;
+            } else {
+                histogram[its as usize] += 1;
+            }
+        }
+        Self {
+            interior_fraction: interior as f64 / escapes.len().max(1) as f64,
+            min_iterations: histogram.iter().position(|&n| n > 0).map(|i| i as u32),
+            max_iterations: histogram.iter().rposition(|&n| n > 0).map(|i| i as u32),
+            histogram,
+        }
+    }
+}
+
+/// Format of the textures [`Headless::render_to_texture`] draws into: linear color with
+/// premultiplied alpha (see [`MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR`]), in half floats so
+/// that bright colors of HDR views aren't clipped.
+pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// A renderer that owns its own device and reads its results back to the CPU.
+///
+/// Its results can also stay on the GPU for further passes, such as bloom or tone mapping, with
+/// [`Headless::render_to_texture`]. Those have to use [`Headless::device`] and
+/// [`Headless::queue`], since textures can't be shared between devices.
+pub struct Headless {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    bindings: gpu::ViewBindings,
+    escape_layout: wgpu::BindGroupLayout,
+    escape_pipeline: wgpu::ComputePipeline,
+    image_pipeline: wgpu::RenderPipeline,
+    float_pipelines: gpu::FloatPipelines,
+    distance_pipeline: wgpu::RenderPipeline,
+    texture_pipeline: wgpu::RenderPipeline,
+}
+
+impl Headless {
+    pub async fn new() -> Result<Self, HeadlessError> {
+        Self::with_backends(wgpu::Backends::all()).await
+    }
+
+    /// Like [`Headless::new`], but only considering adapters of the given backends.
+    pub async fn with_backends(backends: wgpu::Backends) -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or(HeadlessError::NoAdapter)?;
+        log::info!("using the {:?} backend", adapter.get_info().backend);
+        let float = gpu::ShaderFloat::for_adapter(&adapter);
+        float.log_choice();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features: float.features(),
+                    required_limits: wgpu::Limits::default(),
+                    label: None,
+                },
+                None,
+            )
+            .await
+            .map_err(HeadlessError::RequestDevice)?;
+
+        let shader = float.shader(&device);
+        let bindings = gpu::ViewBindings::new(
+            &device,
+            float,
+            &MandelbrotUniform::default_view(1.0),
+            &palette::hsv_ramp(),
+        );
+
+        let escape_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("escape_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let escape_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bindings.layout, &escape_layout],
+                push_constant_ranges: &[],
+            });
+        let escape_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("cs_escape"),
+            layout: Some(&escape_pipeline_layout),
+            module: &shader,
+            entry_point: "cs_escape",
+        });
+
+        let image_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bindings.layout],
+                push_constant_ranges: &[],
+            });
+        let image_pipeline = gpu::create_pipeline(
+            &device,
+            &image_pipeline_layout,
+            &shader,
+            "fs_image",
+            gpu::IMAGE_FORMAT,
+        );
+        let float_pipelines = gpu::FloatPipelines::new(&device, &image_pipeline_layout, &shader);
+        let distance_pipeline = gpu::create_pipeline(
+            &device,
+            &image_pipeline_layout,
+            &shader,
+            "fs_distance",
+            wgpu::TextureFormat::R32Float,
+        );
+
+        let texture_pipeline = gpu::create_pipeline(
+            &device,
+            &image_pipeline_layout,
+            &shader,
+            "fs_linear",
+            TEXTURE_FORMAT,
+        );
+
+        Ok(Self {
+            device,
+            queue,
+            bindings,
+            escape_layout,
+            escape_pipeline,
+            image_pipeline,
+            float_pipelines,
+            distance_pipeline,
+            texture_pipeline,
+        })
+    }
+
+    /// The device everything is rendered with.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// The queue of [`Headless::device`].
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Creates a `width * height` texture that [`Headless::render_to_texture`] can draw into,
+    /// which can also be sampled by later passes and copied from.
+    pub fn create_texture(&self, width: u32, height: u32) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Render Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    /// Renders `uniform` into `view` and submits the work, without waiting for it or reading
+    /// anything back. The view has to be of a texture created on [`Headless::device`] in
+    /// [`TEXTURE_FORMAT`] with [`wgpu::TextureUsages::RENDER_ATTACHMENT`], like those of
+    /// [`Headless::create_texture`]. Unlike the other renders, this keeps the aspect ratio of
+    /// `uniform`, which should match that of the texture.
+    pub fn render_to_texture(&self, uniform: &MandelbrotUniform, view: &wgpu::TextureView) {
+        self.queue.write_buffer(
+            &self.bindings.uniform_buffer,
+            0,
+            &self.bindings.float.uniform_bytes(uniform),
+        );
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Texture Encoder"),
+            });
+        gpu::draw_quad(
+            &mut encoder,
+            &self.texture_pipeline,
+            &self.bindings.bind_group,
+            view,
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Sets the palette all following renders are colored with.
+    pub fn set_palette(&self, palette: &palette::Palette) {
+        self.queue.write_buffer(
+            &self.bindings.palette_buffer,
+            0,
+            bytemuck::cast_slice(palette),
+        );
+    }
+
+    fn set_uniform(&self, uniform: &MandelbrotUniform, width: u32, height: u32) {
+        // Never stretch the image, whatever aspect ratio the uniform was made for.
+        let uniform = MandelbrotUniform {
+            aspect_ratio: width as f64 / height as f64,
+            ..*uniform
+        };
+        self.queue.write_buffer(
+            &self.bindings.uniform_buffer,
+            0,
+            &self.bindings.float.uniform_bytes(&uniform),
+        );
+    }
+
+    /// Computes the escape iteration of every pixel of a `width * height` image of `uniform`,
+    /// row by row starting at the top. Pixels that didn't escape hold `max_iterations`, or
+    /// their iteration count with [`INTERIOR_BIT`] set if interior detection caught them.
+    /// Blocks until the GPU is done.
+    pub fn escape_values(&self, uniform: &MandelbrotUniform, width: u32, height: u32) -> Vec<u32> {
+        self.set_uniform(uniform, width, height);
+
+        // A tile at a time, as large as fits into a storage buffer and a single dispatch. Tiles
+        // span whole rows unless a single row is already too large.
+        let max_pixels = gpu::max_storage_buffer_size(&self.device) / 4;
+        let max_side = u64::from(self.device.limits().max_compute_workgroups_per_dimension) * 8;
+        let tile_columns = u64::from(width.max(1)).min(max_pixels).min(max_side);
+        let tile_rows =
+            (max_pixels / tile_columns).clamp(1, u64::from(height.max(1)).min(max_side));
+        let (tile_columns, tile_rows) = (tile_columns as u32, tile_rows as u32);
+        let size = u64::from(tile_columns) * u64::from(tile_rows) * 4;
+        let escapes = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Escape Buffer"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Escape Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut values = vec![0; width as usize * height as usize];
+        for first_row in (0..height).step_by(tile_rows as usize) {
+            for first_column in (0..width).step_by(tile_columns as usize) {
+                let rows = tile_rows.min(height - first_row);
+                let columns = tile_columns.min(width - first_column);
+                let tile = self
+                    .device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Escape Tile Buffer"),
+                        contents: bytemuck::cast_slice(&[
+                            width,
+                            height,
+                            first_column,
+                            first_row,
+                            columns,
+                            rows,
+                        ]),
+                        usage: wgpu::BufferUsages::UNIFORM,
+                    });
+                let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("escape_bind_group"),
+                    layout: &self.escape_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: escapes.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: tile.as_entire_binding(),
+                        },
+                    ],
+                });
+
+                let mut encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Escape Encoder"),
+                        });
+                {
+                    let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some("Escape Pass"),
+                        timestamp_writes: None,
+                    });
+                    pass.set_pipeline(&self.escape_pipeline);
+                    pass.set_bind_group(0, &self.bindings.bind_group, &[]);
+                    pass.set_bind_group(1, &bind_group, &[]);
+                    pass.dispatch_workgroups(columns.div_ceil(8), rows.div_ceil(8), 1);
+                }
+                let tile_size = u64::from(columns) * u64::from(rows) * 4;
+                encoder.copy_buffer_to_buffer(&escapes, 0, &readback, 0, tile_size);
+                self.queue.submit(std::iter::once(encoder.finish()));
+
+                let data = gpu::read_buffer(&self.device, &readback);
+                let tile_values =
+                    bytemuck::pod_collect_to_vec::<u8, u32>(&data[..tile_size as usize]);
+                for (row, tile_row) in tile_values.chunks(columns as usize).enumerate() {
+                    let start = (first_row as usize + row) * width as usize + first_column as usize;
+                    values[start..start + columns as usize].copy_from_slice(tile_row);
+                }
+            }
+        }
+        values
+    }
+
+    /// Renders `uniform` at the given size and summarizes its escape values.
+    pub async fn iteration_stats(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+    ) -> IterationStats {
+        let escapes = self.escape_values(uniform, width, height);
+        IterationStats::from_escape_values(&escapes, uniform.max_iterations)
+    }
+
+    /// Renders a colored `width * height` image of `uniform`, as it would be shown on screen.
+    pub async fn render_image(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+    ) -> image::RgbImage {
+        self.set_uniform(uniform, width, height);
+        gpu::render_image(
+            &self.device,
+            &self.queue,
+            &self.image_pipeline,
+            &self.bindings.bind_group,
+            width,
+            height,
+        )
+    }
+
+    /// Renders a colored `width * height` image of `uniform` with an alpha channel, which is
+    /// transparent where points don't escape if `uniform` has
+    /// [`MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR`] and opaque everywhere else.
+    pub async fn render_rgba_image(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+    ) -> image::RgbaImage {
+        self.set_uniform(uniform, width, height);
+        gpu::render_rgba_image(
+            &self.device,
+            &self.queue,
+            &self.image_pipeline,
+            &self.bindings.bind_group,
+            width,
+            height,
+        )
+    }
+
+    /// Renders a `width * height` image of `uniform` in strips of `strip_height` rows, streaming
+    /// each into the PNG at `path` before rendering the next, so neither GPU nor main memory
+    /// ever hold more than a strip. Strips wider than the GPU's texture size limit are rendered
+    /// in several tiles. `progress` is called with the number of rows written after every strip.
+    /// There's no alpha channel, even with [`MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR`].
+    pub async fn save_png_strips(
+        &self,
+        uniform: &MandelbrotUniform,
+        [width, height]: [u32; 2],
+        strip_height: u32,
+        path: impl AsRef<Path>,
+        mut progress: impl FnMut(u32),
+    ) -> io::Result<()> {
+        let max_size = self.device.limits().max_texture_dimension_2d;
+        let strip_height = strip_height.clamp(1, max_size);
+        let file = io::BufWriter::new(fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        let mut stream = writer.stream_writer().map_err(io::Error::other)?;
+
+        let (w, h) = (width as f64, height as f64);
+        let mut strip = Vec::new();
+        for top in (0..height).step_by(strip_height as usize) {
+            let rows = strip_height.min(height - top);
+            strip.clear();
+            strip.resize(width as usize * rows as usize * 3, 0);
+            for left in (0..width).step_by(max_size as usize) {
+                let columns = max_size.min(width - left);
+                // uv coordinates start at the bottom.
+                let tile = uniform.region(
+                    [left as f64 / w, 1.0 - (top + rows) as f64 / h],
+                    [(left + columns) as f64 / w, 1.0 - top as f64 / h],
+                );
+                let image = self.render_image(&tile, columns, rows).await;
+                for (y, row) in image.as_raw().chunks(columns as usize * 3).enumerate() {
+                    let start = (y * width as usize + left as usize) * 3;
+                    strip[start..start + row.len()].copy_from_slice(row);
+                }
+            }
+            stream.write_all(&strip)?;
+            progress(top + rows);
+        }
+        stream.finish().map_err(io::Error::other)
+    }
+
+    /// Renders `uniform` at the size of the image at `path` and returns its
+    /// [`mean_absolute_error`] from that image, for catching visual regressions against a known
+    /// good render.
+    pub async fn compare_to(
+        &self,
+        uniform: &MandelbrotUniform,
+        path: impl AsRef<Path>,
+    ) -> image::ImageResult<f64> {
+        let golden = image::open(path)?.into_rgb8();
+        let image = self
+            .render_image(uniform, golden.width(), golden.height())
+            .await;
+        Ok(mean_absolute_error(&image, &golden).expect("rendered at the golden image's size"))
+    }
+
+    /// Renders `uniform` at the given size and saves it to `path` in `format`. PNGs get an
+    /// alpha channel with [`MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR`].
+    pub async fn save_image(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+        format: ImageFormat,
+        path: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        match format {
+            ImageFormat::Png if uniform.flag(MandelbrotUniform::FLAG_TRANSPARENT_INTERIOR) => self
+                .render_rgba_image(uniform, width, height)
+                .await
+                .save(path)
+                .map_err(io::Error::other),
+            ImageFormat::Png => self
+                .render_image(uniform, width, height)
+                .await
+                .save(path)
+                .map_err(io::Error::other),
+            ImageFormat::Exr => {
+                self.set_uniform(uniform, width, height);
+                self.float_pipelines.write_exr(
+                    &self.device,
+                    &self.queue,
+                    &self.bindings.bind_group,
+                    width,
+                    height,
+                    path,
+                )
+            }
+        }
+    }
+
+    /// Computes the smooth escape time of every pixel of a `width * height` image of `uniform`,
+    /// row by row starting at the top. Points that didn't escape, including detected interior
+    /// points, hold `max_iterations`.
+    pub async fn smooth_iterations(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+    ) -> Vec<f32> {
+        self.set_uniform(uniform, width, height);
+        self.float_pipelines.smooth_iterations(
+            &self.device,
+            &self.queue,
+            &self.bindings.bind_group,
+            width,
+            height,
+        )
+    }
+
+    /// Saves the contour lines of the [smooth escape time](Headless::smooth_iterations) of
+    /// `uniform` at every multiple of `interval` below `max_iterations` as an SVG of `width *
+    /// height` pixels, see [`contours::write_svg`].
+    pub async fn save_contour_svg(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+        interval: f32,
+        path: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        let field = self.smooth_iterations(uniform, width, height).await;
+        let levels = contours::levels(interval, uniform.max_iterations as f32);
+        contours::write_svg(path, &field, width as usize, height as usize, &levels)
+    }
+
+    /// Estimates the distance from every pixel of a `width * height` image of `uniform` to the
+    /// set, in pixels, row by row starting at the top. Points that didn't escape within
+    /// `max_iterations` are at distance 0.
+    pub async fn distances(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+    ) -> Vec<f32> {
+        self.set_uniform(uniform, width, height);
+        let data = gpu::render_texture(
+            &self.device,
+            &self.queue,
+            &self.distance_pipeline,
+            &self.bindings.bind_group,
+            wgpu::TextureFormat::R32Float,
+            width,
+            height,
+        );
+        bytemuck::pod_collect_to_vec(&data)
+    }
+
+    /// Saves the [distances](Headless::distances) of `uniform` as a 16-bit grayscale PNG
+    /// heightmap, for use as a displacement texture. The set itself is at height 0, and the
+    /// height rises smoothly towards white with the distance, reaching about two thirds of it
+    /// [`HEIGHTMAP_SCALE`] pixels away.
+    pub async fn save_heightmap(
+        &self,
+        uniform: &MandelbrotUniform,
+        width: u32,
+        height: u32,
+        path: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        let heights = self
+            .distances(uniform, width, height)
+            .await
+            .into_iter()
+            .map(|distance| {
+                let h = 1.0 - (-distance.max(0.0) / HEIGHTMAP_SCALE).exp();
+                (h * f32::from(u16::MAX)).round() as u16
+            })
+            .collect();
+        image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(width, height, heights)
+            .expect("readback has the image's size")
+            .save(path)
+            .map_err(io::Error::other)
+    }
+
+    /// Renders `frames` views of size `[width, height]` zooming from `start` to `end` into the
+    /// directory `dir`, creating it if needed. Every frame is saved as a colored image in
+    /// `format` and as its raw escape values (see [`Headless::escape_values`]) in little endian
+    /// `u32`s, and `sequence.json` lists them along with the exact view each one shows.
+    pub async fn export_zoom_sequence(
+        &self,
+        dir: impl AsRef<Path>,
+        start: &MandelbrotUniform,
+        end: &MandelbrotUniform,
+        frames: u32,
+        [width, height]: [u32; 2],
+        format: ImageFormat,
+    ) -> io::Result<SequenceManifest> {
+        let frame_view = |t| start.zoom_towards(end, t);
+        self.export_sequence(dir, frames, [width, height], format, frame_view)
+            .await
+    }
+
+    /// Renders `frames` Julia sets of size `[width, height]` into the directory `dir`, with
+    /// their constant swept along `path` from its start to its end. Every frame shows the region
+    /// of `view`, with its iteration and coloring settings. The files written are the same as
+    /// for [`Headless::export_zoom_sequence`].
+    pub async fn record_julia_sweep(
+        &self,
+        dir: impl AsRef<Path>,
+        view: &MandelbrotUniform,
+        path: SweepPath,
+        frames: u32,
+        [width, height]: [u32; 2],
+        format: ImageFormat,
+    ) -> io::Result<SequenceManifest> {
+        let frame_view = |t| MandelbrotUniform {
+            flags: view.flags | MandelbrotUniform::FLAG_JULIA,
+            julia_c: path.point(t),
+            ..*view
+        };
+        self.export_sequence(dir, frames, [width, height], format, frame_view)
+            .await
+    }
+
+    // Saves the views `frame_view` returns for `frames` evenly spaced times from 0 to 1, see
+    // `export_zoom_sequence`.
+    async fn export_sequence(
+        &self,
+        dir: impl AsRef<Path>,
+        frames: u32,
+        [width, height]: [u32; 2],
+        format: ImageFormat,
+        frame_view: impl Fn(f64) -> MandelbrotUniform,
+    ) -> io::Result<SequenceManifest> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut sequence = SequenceManifest {
+            width,
+            height,
+            frames: Vec::with_capacity(frames as usize),
+        };
+        for i in 0..frames {
+            let t = if frames > 1 {
+                i as f64 / (frames - 1) as f64
+            } else {
+                0.0
+            };
+            let uniform = MandelbrotUniform {
+                aspect_ratio: width as f64 / height as f64,
+                ..frame_view(t)
+            };
+
+            let image_name = format!("frame-{i:05}.{}", format.extension());
+            self.save_image(&uniform, width, height, format, dir.join(&image_name))
+                .await?;
+
+            let escapes_name = format!("frame-{i:05}.bin");
+            let escapes = self.escape_values(&uniform, width, height);
+            let bytes: Vec<u8> = escapes.iter().flat_map(|e| e.to_le_bytes()).collect();
+            fs::write(dir.join(&escapes_name), bytes)?;
+
+            sequence.frames.push(SequenceFrame {
+                image: image_name,
+                escapes: escapes_name,
+                view: Manifest::new(&uniform),
+            });
+        }
+        sequence.write(dir.join("sequence.json"))?;
+        Ok(sequence)
+    }
+}
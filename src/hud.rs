@@ -0,0 +1,116 @@
+//! The on-screen text overlay: a few lines rasterized on the CPU with a built-in 5×7 pixel font,
+//! so drawing them takes nothing more than the texture blit the window already has.
+
+/// Width and height of a glyph in font pixels.
+const GLYPH_SIZE: (u32, u32) = (5, 7);
+/// Space between glyphs and between lines, and around the text, in font pixels.
+const SPACING: (u32, u32) = (1, 3);
+const PADDING: u32 = 3;
+
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const FOREGROUND: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// Rows from top to bottom, the lowest 5 bits of each from left to right.
+#[rustfmt::skip]
+const GLYPHS: [(char, [u8; 7]); 41] = [
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('×', [0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b00000]),
+];
+
+fn glyph(c: char) -> Option<&'static [u8; 7]> {
+    let c = c.to_ascii_uppercase();
+    GLYPHS.iter().find(|(g, _)| *g == c).map(|(_, rows)| rows)
+}
+
+/// Draws `lines` in white on black, each font pixel `scale` pixels wide. Returns the width and
+/// height of the image and its pixels row by row from the top. Letters are drawn in upper case
+/// and characters the font lacks as spaces.
+pub fn rasterize(lines: &[String], scale: u32) -> (u32, u32, Vec<[f32; 4]>) {
+    let (glyph_width, glyph_height) = GLYPH_SIZE;
+    let (advance, line_height) = (glyph_width + SPACING.0, glyph_height + SPACING.1);
+    let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+    let rows = lines.len() as u32;
+    // The spacing after the last glyph and line is replaced by the padding.
+    let width = (2 * PADDING + (columns * advance).saturating_sub(SPACING.0)) * scale;
+    let height = (2 * PADDING + (rows * line_height).saturating_sub(SPACING.1)) * scale;
+
+    let mut pixels = vec![BACKGROUND; (width * height) as usize];
+    for (row, line) in lines.iter().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            let Some(glyph) = glyph(c) else { continue };
+            let left = PADDING + column as u32 * advance;
+            let top = PADDING + row as u32 * line_height;
+            for (y, bits) in glyph.iter().enumerate() {
+                for x in 0..glyph_width {
+                    if bits >> (glyph_width - 1 - x) & 1 == 0 {
+                        continue;
+                    }
+                    let (px, py) = ((left + x) * scale, (top + y as u32) * scale);
+                    for sy in py..py + scale {
+                        let start = (sy * width + px) as usize;
+                        pixels[start..start + scale as usize].fill(FOREGROUND);
+                    }
+                }
+            }
+        }
+    }
+    (width, height, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_fits_the_image() {
+        let (width, height, pixels) = rasterize(&["x 1".to_owned(), "-".to_owned()], 2);
+        assert_eq!((width, height), ((2 * 3 + 17) * 2, (2 * 3 + 17) * 2));
+        assert_eq!(pixels.len(), (width * height) as usize);
+        // Top left corner of the `x` and middle of the `-`, which sits one line further down.
+        let at = |x: u32, y: u32| pixels[(y * 2 * width + x * 2) as usize];
+        assert_eq!(at(3, 3), FOREGROUND);
+        assert_eq!(at(4, 3), BACKGROUND);
+        assert_eq!(at(5, 3 + 10 + 3), FOREGROUND);
+        // The unknown space stays blank.
+        assert!((9..15).all(|x| at(x, 5) == BACKGROUND));
+    }
+}
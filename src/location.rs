@@ -0,0 +1,271 @@
+//! Parses view locations written in the notations different fractal programs share them in.
+
+use std::fmt;
+
+/// How a location was written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Notation {
+    /// `a+bi`, optionally followed by the radius: `-0.745+0.113i 0.001`.
+    Complex,
+    /// Real part, imaginary part and radius: `-0.745, 0.113, 0.001`.
+    CenterRadius,
+    /// Bottom left corner followed by width and height: `-2.5 -1.25 3.5 2.5`.
+    CornerSize,
+    /// A Kalles Fraktaler location file, see [`parse_kfr`].
+    Kfr,
+}
+
+impl fmt::Display for Notation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Complex => "complex number",
+            Self::CenterRadius => "center and radius",
+            Self::CornerSize => "corner and size",
+            Self::Kfr => "Kalles Fraktaler location",
+        })
+    }
+}
+
+/// A rectangle of the complex plane to show, as parsed by [`parse`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+    pub notation: Notation,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseLocationError {
+    InvalidNumber(String),
+    UnknownNotation,
+    NotPositive,
+    /// A location file lacks a required key.
+    MissingKey(&'static str),
+}
+
+impl fmt::Display for ParseLocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNumber(s) => write!(f, "{s:?} isn't a number"),
+            Self::UnknownNotation => write!(
+                f,
+                "expected `a+bi [radius]`, `x y radius` or `min_x min_y width height`"
+            ),
+            Self::NotPositive => write!(f, "sizes have to be positive"),
+            Self::MissingKey(key) => write!(f, "there's no `{key}` line"),
+        }
+    }
+}
+
+impl std::error::Error for ParseLocationError {}
+
+/// Radius around a complex number given without one, enough to show the whole set.
+pub const DEFAULT_RADIUS: f64 = 1.25;
+
+/// Parses a location, detecting its notation. Numbers can be separated by whitespace, commas or
+/// semicolons, and an `r=` before a radius is ignored.
+pub fn parse(s: &str) -> Result<Location, ParseLocationError> {
+    let parts: Vec<&str> = s
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.strip_prefix("r=").unwrap_or(part))
+        .collect();
+
+    if let Some(first) = parts.first().filter(|p| p.ends_with(['i', 'j'])) {
+        let center = parse_complex(first)?;
+        let radius = match &parts[1..] {
+            [] => DEFAULT_RADIUS,
+            [radius] => number(radius)?,
+            _ => return Err(ParseLocationError::UnknownNotation),
+        };
+        return square(center, radius, Notation::Complex);
+    }
+
+    let numbers = parts
+        .iter()
+        .map(|part| number(part))
+        .collect::<Result<Vec<_>, _>>()?;
+    match numbers[..] {
+        [x, y, radius] => square([x, y], radius, Notation::CenterRadius),
+        [x, y, width, height] => {
+            if width <= 0.0 || height <= 0.0 {
+                return Err(ParseLocationError::NotPositive);
+            }
+            Ok(Location {
+                min: [x, y],
+                max: [x + width, y + height],
+                notation: Notation::CornerSize,
+            })
+        }
+        _ => Err(ParseLocationError::UnknownNotation),
+    }
+}
+
+/// A location read from a Kalles Fraktaler `.kfr` file by [`parse_kfr`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KfrLocation {
+    pub location: Location,
+    /// The iteration limit saved with the location, if any.
+    pub iterations: Option<u32>,
+    /// Whether the center was given to more digits than `f64` holds, so the location is only
+    /// approximately where it was saved.
+    pub truncated: bool,
+}
+
+/// Parses the `Re`, `Im`, `Zoom` and `Iterations` lines of a Kalles Fraktaler location file,
+/// which hold one `Key: value` pair per line, ignoring all other keys. A zoom of 1 shows a
+/// radius of 2 around the center.
+pub fn parse_kfr(text: &str) -> Result<KfrLocation, ParseLocationError> {
+    let value = |key| {
+        text.lines().find_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            (k.trim() == key).then(|| v.trim())
+        })
+    };
+    let required = |key| value(key).ok_or(ParseLocationError::MissingKey(key));
+    let (re, im) = (required("Re")?, required("Im")?);
+    let zoom = number(required("Zoom")?)?;
+    let iterations = match value("Iterations") {
+        Some(its) => Some(
+            its.parse()
+                .map_err(|_| ParseLocationError::InvalidNumber(its.to_owned()))?,
+        ),
+        None => None,
+    };
+    let location = square([number(re)?, number(im)?], 2.0 / zoom, Notation::Kfr)?;
+    Ok(KfrLocation {
+        location,
+        iterations,
+        truncated: [re, im]
+            .into_iter()
+            .any(|s| significant_digits(s) > F64_DIGITS),
+    })
+}
+
+// Decimal digits an f64 is guaranteed to round trip.
+const F64_DIGITS: usize = 17;
+
+// Digits of the mantissa of a decimal number, without leading or trailing zeros.
+fn significant_digits(s: &str) -> usize {
+    let mantissa = s.split(['e', 'E']).next().unwrap_or(s);
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    digits.trim_start_matches('0').trim_end_matches('0').len()
+}
+
+fn square(
+    [x, y]: [f64; 2],
+    radius: f64,
+    notation: Notation,
+) -> Result<Location, ParseLocationError> {
+    if radius <= 0.0 {
+        return Err(ParseLocationError::NotPositive);
+    }
+    Ok(Location {
+        min: [x - radius, y - radius],
+        max: [x + radius, y + radius],
+        notation,
+    })
+}
+
+fn number(s: &str) -> Result<f64, ParseLocationError> {
+    s.parse()
+        .map_err(|_| ParseLocationError::InvalidNumber(s.to_owned()))
+}
+
+// Parses `a+bi`, `a-bi`, `bi` or `a+i`, where the sign splitting the parts is the last one that
+// doesn't belong to an exponent.
+fn parse_complex(s: &str) -> Result<[f64; 2], ParseLocationError> {
+    let body = s.trim_end_matches(['i', 'j']);
+    let split = body
+        .char_indices()
+        .rev()
+        .find(|&(i, c)| (c == '+' || c == '-') && i > 0 && !body[..i].ends_with(['e', 'E']))
+        .map(|(i, _)| i);
+    let (re, im) = match split {
+        Some(i) => (&body[..i], &body[i..]),
+        None => ("0", body),
+    };
+    let im = match im {
+        "" | "+" => 1.0,
+        "-" => -1.0,
+        im => number(im)?,
+    };
+    Ok([number(re)?, im])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complex_numbers_are_parsed() {
+        let cases = [
+            ("-0.745+0.113i", [-0.745, 0.113]),
+            ("0.25-1.5i", [0.25, -1.5]),
+            ("2.5j", [0.0, 2.5]),
+            ("-3i", [0.0, -3.0]),
+            ("1+i", [1.0, 1.0]),
+            ("1-i", [1.0, -1.0]),
+            ("i", [0.0, 1.0]),
+            ("-i", [0.0, -1.0]),
+            // Signs of exponents don't split the parts.
+            ("1e-3+2E+4i", [1e-3, 2e4]),
+            ("-1.5E+2-2.5e-10j", [-150.0, -2.5e-10]),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_complex(s), Ok(expected), "{s}");
+        }
+
+        let invalid = |s: &str| Err(ParseLocationError::InvalidNumber(s.to_owned()));
+        assert_eq!(parse_complex("x+1i"), invalid("x"));
+        assert_eq!(parse_complex("1+yi"), invalid("+y"));
+        assert_eq!(parse_complex("1+2+3i"), invalid("1+2"));
+    }
+
+    #[test]
+    fn complex_locations_take_an_optional_radius() {
+        let location = parse("-0.5+0.25i r=0.5").unwrap();
+        assert_eq!(location.notation, Notation::Complex);
+        assert_eq!((location.min, location.max), ([-1.0, -0.25], [0.0, 0.75]));
+        let default = parse("0+0i").unwrap();
+        assert_eq!(default.max, [DEFAULT_RADIUS, DEFAULT_RADIUS]);
+        assert_eq!(parse("1+i 0.5 2"), Err(ParseLocationError::UnknownNotation));
+        assert_eq!(parse("1+i, 0"), Err(ParseLocationError::NotPositive));
+    }
+
+    #[test]
+    fn real_notations_are_parsed() {
+        let location = parse("-0.5; 0.25, r=0.5").unwrap();
+        assert_eq!(location.notation, Notation::CenterRadius);
+        assert_eq!((location.min, location.max), ([-1.0, -0.25], [0.0, 0.75]));
+        let location = parse("-2 -1\t3 2").unwrap();
+        assert_eq!(location.notation, Notation::CornerSize);
+        assert_eq!((location.min, location.max), ([-2.0, -1.0], [1.0, 1.0]));
+
+        assert_eq!(parse("0 0 -1"), Err(ParseLocationError::NotPositive));
+        assert_eq!(parse("0 0 1 0"), Err(ParseLocationError::NotPositive));
+        assert_eq!(parse("1 2"), Err(ParseLocationError::UnknownNotation));
+        assert_eq!(parse(""), Err(ParseLocationError::UnknownNotation));
+        assert_eq!(
+            parse("0 0 one"),
+            Err(ParseLocationError::InvalidNumber("one".to_owned()))
+        );
+    }
+
+    #[test]
+    fn kfr_files_are_parsed() {
+        let text = "Re: -0.75\r\nIm: 0.1\r\nZoom: 4\r\nIterations: 2000\r\nIterDiv: 0.01\r\n";
+        let kfr = parse_kfr(text).unwrap();
+        assert_eq!(kfr.location.min, [-1.25, -0.4]);
+        assert_eq!(kfr.location.max, [-0.25, 0.6]);
+        assert_eq!(kfr.iterations, Some(2000));
+        assert!(!kfr.truncated);
+
+        let deep = "Re: -1.7499576837060935036022145060706997072711\nIm: 0.0000000000000000000000000000001\nZoom: 1E30\n";
+        assert!(parse_kfr(deep).unwrap().truncated);
+        assert_eq!(
+            parse_kfr("Re: 0\nZoom: 1"),
+            Err(ParseLocationError::MissingKey("Im"))
+        );
+    }
+}
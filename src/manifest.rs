@@ -0,0 +1,208 @@
+//! Sidecar files describing exactly which view an exported image shows.
+
+use std::{fs::File, io::BufWriter, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{uniform::NORM_NAMES, MandelbrotUniform};
+
+/// Everything needed to reproduce a rendered view. Coordinates are written with full `f64`
+/// precision.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// `mandelbrot`, or `julia` for the Julia set of `julia_c`.
+    pub fractal: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub julia_c: Option<[f64; 2]>,
+    /// Whether orbits were folded like the Burning Ship's, see
+    /// [`MandelbrotUniform::FLAG_BURNING_SHIP`].
+    #[serde(default)]
+    pub burning_ship: bool,
+    pub min_x: f64,
+    pub min_y: f64,
+    pub height: f64,
+    pub aspect_ratio: f64,
+    pub rotation: f64,
+    /// The point a log-polar view is laid out around, if the view is log-polar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_polar_center: Option<[f64; 2]>,
+    pub max_iterations: u32,
+    /// Norm the escape radius is measured in, see `NORM_NAMES`.
+    #[serde(default = "default_norm")]
+    pub norm: String,
+    /// Radius orbits escaped at before smooth coloring raised it, see
+    /// [`MandelbrotUniform::escape_radius`].
+    #[serde(default = "default_escape_radius")]
+    pub escape_radius: f32,
+    pub coloring: Coloring,
+}
+
+fn default_norm() -> String {
+    NORM_NAMES[0].to_owned()
+}
+
+fn default_escape_radius() -> f32 {
+    2.0
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Coloring {
+    pub interior_detection: bool,
+    pub hdr_peak: f32,
+    /// Spacing of the contour lines in iterations, if they're drawn.
+    #[serde(default)]
+    pub contour_interval: Option<f32>,
+    /// Iterations per cycle through the palette, if it repeats.
+    #[serde(default)]
+    pub color_period: Option<f32>,
+    /// Whether color bands were softened with screen space derivatives.
+    #[serde(default)]
+    pub aa_fwidth: bool,
+    /// Whether escaped points were colored by their smooth escape time.
+    #[serde(default)]
+    pub smooth: bool,
+    /// Random samples averaged per pixel, if more than one.
+    #[serde(default)]
+    pub samples_per_pixel: Option<u32>,
+    /// Linear RGB color of points inside the set, if it isn't black.
+    #[serde(default)]
+    pub interior_color: Option<[f32; 3]>,
+}
+
+impl Manifest {
+    pub fn new(uniform: &MandelbrotUniform) -> Self {
+        let julia = uniform.flag(MandelbrotUniform::FLAG_JULIA);
+        Self {
+            fractal: if julia { "julia" } else { "mandelbrot" }.to_owned(),
+            julia_c: julia.then_some(uniform.julia_c),
+            burning_ship: uniform.flag(MandelbrotUniform::FLAG_BURNING_SHIP),
+            min_x: uniform.min_x,
+            min_y: uniform.min_y,
+            height: uniform.height,
+            aspect_ratio: uniform.aspect_ratio,
+            rotation: uniform.rotation,
+            log_polar_center: uniform
+                .flag(MandelbrotUniform::FLAG_LOG_POLAR)
+                .then_some(uniform.log_polar_center),
+            max_iterations: uniform.max_iterations,
+            norm: NORM_NAMES[uniform.norm_type as usize].to_owned(),
+            escape_radius: uniform.escape_radius,
+            coloring: Coloring {
+                interior_detection: uniform.flag(MandelbrotUniform::FLAG_INTERIOR_DETECTION),
+                hdr_peak: uniform.hdr_peak,
+                contour_interval: uniform
+                    .flag(MandelbrotUniform::FLAG_CONTOURS)
+                    .then_some(uniform.contour_interval),
+                color_period: (uniform.color_period > 0.0).then_some(uniform.color_period),
+                aa_fwidth: uniform.flag(MandelbrotUniform::FLAG_AA_FWIDTH),
+                smooth: uniform.flag(MandelbrotUniform::FLAG_SMOOTH_COLORING),
+                samples_per_pixel: (uniform.samples_per_pixel > 1)
+                    .then_some(uniform.samples_per_pixel),
+                interior_color: {
+                    let [r, g, b, _] = uniform.interior_color;
+                    ([r, g, b] != [0.0; 3]).then_some([r, g, b])
+                },
+            },
+        }
+    }
+
+    /// The uniform rendering this view. Unknown norms fall back to the Euclidean one, and
+    /// fractals other than Julia sets with a `julia_c` to the Mandelbrot set.
+    pub fn to_uniform(&self) -> MandelbrotUniform {
+        let coloring = &self.coloring;
+        let julia_c = self.julia_c.filter(|_| self.fractal == "julia");
+        let mut uniform = MandelbrotUniform {
+            julia_c: julia_c.unwrap_or_default(),
+            min_x: self.min_x,
+            min_y: self.min_y,
+            height: self.height,
+            rotation: self.rotation,
+            log_polar_center: self.log_polar_center.unwrap_or_default(),
+            max_iterations: self.max_iterations,
+            norm_type: NORM_NAMES.iter().position(|&n| n == self.norm).unwrap_or(0) as u32,
+            escape_radius: self.escape_radius,
+            hdr_peak: coloring.hdr_peak,
+            contour_interval: coloring.contour_interval.unwrap_or(1.0),
+            color_period: coloring.color_period.unwrap_or(0.0),
+            samples_per_pixel: coloring.samples_per_pixel.unwrap_or(1).max(1),
+            interior_color: match coloring.interior_color {
+                Some([r, g, b]) => [r, g, b, 1.0],
+                None => [0.0, 0.0, 0.0, 1.0],
+            },
+            ..MandelbrotUniform::default_view(self.aspect_ratio)
+        };
+        uniform.set_flag(MandelbrotUniform::FLAG_JULIA, julia_c.is_some());
+        uniform.set_flag(MandelbrotUniform::FLAG_BURNING_SHIP, self.burning_ship);
+        uniform.set_flag(
+            MandelbrotUniform::FLAG_LOG_POLAR,
+            self.log_polar_center.is_some(),
+        );
+        uniform.set_flag(
+            MandelbrotUniform::FLAG_INTERIOR_DETECTION,
+            coloring.interior_detection,
+        );
+        uniform.set_flag(
+            MandelbrotUniform::FLAG_CONTOURS,
+            coloring.contour_interval.is_some(),
+        );
+        uniform.set_flag(MandelbrotUniform::FLAG_AA_FWIDTH, coloring.aa_fwidth);
+        uniform.set_flag(MandelbrotUniform::FLAG_SMOOTH_COLORING, coloring.smooth);
+        uniform
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// Describes a sequence exported by [`crate::headless::Headless::export_zoom_sequence`] or
+/// [`crate::headless::Headless::record_julia_sweep`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SequenceManifest {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<SequenceFrame>,
+}
+
+/// One frame of a sequence, with file names relative to the sequence's directory.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SequenceFrame {
+    /// The colored image.
+    pub image: String,
+    /// Raw escape values, one little endian `u32` per pixel, row by row starting at the top.
+    pub escapes: String,
+    pub view: Manifest,
+}
+
+impl SequenceManifest {
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_polar_views_round_trip() {
+        let mut uniform = MandelbrotUniform::default_view(1.5);
+        uniform.set_center([-0.75, 0.1]);
+        uniform.toggle_log_polar();
+        let manifest = Manifest::new(&uniform);
+        assert_eq!(manifest.log_polar_center, Some(uniform.log_polar_center));
+        let json = serde_json::to_string(&manifest).unwrap();
+        let read: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(Manifest::new(&read.to_uniform()), manifest);
+
+        // Manifests written before log-polar views existed still load.
+        let mut json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        json.as_object_mut().unwrap().remove("log_polar_center");
+        let read: Manifest = serde_json::from_value(json).unwrap();
+        assert!(!read.to_uniform().flag(MandelbrotUniform::FLAG_LOG_POLAR));
+    }
+}
@@ -0,0 +1,306 @@
+use std::{fmt, io, path::Path};
+
+use crate::core::hsv2rgb;
+
+/// Number of entries in the palette uploaded to the shader. Must match `PALETTE_SIZE` in
+/// `shader.wgsl`.
+pub const PALETTE_SIZE: usize = 256;
+
+/// Linear RGBA colors, sampled by the shader with linear interpolation between neighbouring
+/// entries. The alpha channel is unused and only there to satisfy uniform array alignment.
+pub type Palette = [[f32; 4]; PALETTE_SIZE];
+
+/// The default palette, a full sweep around the HSV hue circle.
+pub fn hsv_ramp() -> Palette {
+    std::array::from_fn(|i| {
+        let [r, g, b] = hsv2rgb(i as f32 / (PALETTE_SIZE - 1) as f32, 1.0, 1.0);
+        [r, g, b, 1.0]
+    })
+}
+
+/// Names of the palettes returned by [`builtin`], in the order the window cycles through them.
+pub const BUILTIN_NAMES: [&str; 4] = ["hsv", "grayscale", "blue-gold", "fire"];
+
+/// The built-in palette named `BUILTIN_NAMES[index]`. The first one is the default.
+pub fn builtin(index: usize) -> Palette {
+    let stops = match index {
+        0 => return hsv_ramp(),
+        1 => "0 #000000\n1 #ffffff",
+        2 => "0 #000764\n0.16 #206bcb\n0.42 #edffff\n0.6425 #ffaa00\n0.8575 #000200\n1 #000764",
+        3 => "0 #000000\n0.3 #800000\n0.6 #ff8000\n0.85 #ffff00\n1 #ffffff",
+        _ => panic!("there are only {} built-in palettes", BUILTIN_NAMES.len()),
+    };
+    from_text(stops).expect("built-in palettes are valid")
+}
+
+/// Loads a palette from the top row of an image, resampling it to [`PALETTE_SIZE`] entries.
+pub fn from_image(path: impl AsRef<Path>) -> image::ImageResult<Palette> {
+    let image = image::open(path)?.into_rgb32f();
+    let width = image.width();
+    let texel = |x: u32| image.get_pixel(x, 0).0.map(srgb_to_linear);
+
+    Ok(std::array::from_fn(|i| {
+        let x = i as f32 / (PALETTE_SIZE - 1) as f32 * (width - 1) as f32;
+        let x0 = x.floor() as u32;
+        let x1 = (x0 + 1).min(width - 1);
+        let t = x - x0 as f32;
+        let (c0, c1) = (texel(x0), texel(x1));
+        let [r, g, b] = std::array::from_fn(|c| c0[c] + (c1[c] - c0[c]) * t);
+        [r, g, b, 1.0]
+    }))
+}
+
+/// Generates a smooth, cyclic palette from `seed`. The same seed always gives the same palette.
+///
+/// Each channel is a cosine wave `0.5 + 0.5 * cos(2pi * (frequency * t + phase))` with a random
+/// integer frequency, so the palette wraps around seamlessly, and a random phase.
+pub fn random(seed: u64) -> Palette {
+    let mut rng = SplitMix64(seed);
+    let waves: [(f32, f32); 3] =
+        std::array::from_fn(|_| ((1 + rng.next() % 3) as f32, rng.next_f32()));
+
+    std::array::from_fn(|i| {
+        let t = i as f32 / (PALETTE_SIZE - 1) as f32;
+        let [r, g, b] = waves.map(|(frequency, phase)| {
+            let c = 0.5 + 0.5 * (std::f32::consts::TAU * (frequency * t + phase)).cos();
+            srgb_to_linear(c)
+        });
+        [r, g, b, 1.0]
+    })
+}
+
+/// A seed for [`random`] derived from the current time.
+pub fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// The `n`th of a sequence of seeds for [`random`] derived from `seed`, for picking several
+/// palettes reproducibly.
+pub fn nth_seed(seed: u64, n: u64) -> u64 {
+    SplitMix64(seed ^ SplitMix64(n).next()).next()
+}
+
+// https://prng.di.unimi.it/splitmix64.c
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1).
+    fn next_f32(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Largest difference in any sRGB channel, out of 255, that [`to_text`] accepts when leaving
+/// out stops that interpolating their neighbours reproduces.
+const STOP_TOLERANCE: f32 = 1.0;
+
+/// Writes the palette as text, one color stop per line: its position from 0 to 1 and its sRGB
+/// color as hex digits, e.g. `0.5 #ff8000`. Colors in between are interpolated in linear RGB.
+/// Only as many stops as are needed to reproduce the palette are written, so a smooth palette
+/// makes for a short file.
+pub fn to_text(palette: &Palette) -> String {
+    let rgb = |i: usize| [0, 1, 2].map(|c| palette[i][c]);
+    let close = |a: [f32; 3], b: [f32; 3]| {
+        (0..3)
+            .all(|c| (linear_to_srgb(a[c]) - linear_to_srgb(b[c])).abs() * 255.0 <= STOP_TOLERANCE)
+    };
+    // Greedily extend each segment as long as interpolating between its ends stays close to
+    // every entry it covers.
+    let mut stops = vec![0];
+    let mut start = 0;
+    for end in 2..PALETTE_SIZE {
+        let fits = (start + 1..end).all(|i| {
+            let t = (i - start) as f32 / (end - start) as f32;
+            close(lerp(rgb(start), rgb(end), t), rgb(i))
+        });
+        if !fits {
+            start = end - 1;
+            stops.push(start);
+        }
+    }
+    stops.push(PALETTE_SIZE - 1);
+
+    let mut text = String::from("# position (0 to 1) and sRGB color of each stop\n");
+    for i in stops {
+        let [r, g, b] = rgb(i).map(|c| (linear_to_srgb(c) * 255.0).round() as u8);
+        let position = i as f32 / (PALETTE_SIZE - 1) as f32;
+        text += &format!("{position:.4} #{r:02x}{g:02x}{b:02x}\n");
+    }
+    text
+}
+
+/// Parses a palette written by [`to_text`], or by hand in the same format. Lines starting with
+/// `# ` and blank lines are ignored. Stops must be in order; before the first and after the last
+/// one the palette keeps their colors.
+pub fn from_text(text: &str) -> Result<Palette, ParsePaletteError> {
+    let mut stops: Vec<(f32, [f32; 3])> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line == "#" || line.starts_with("# ") {
+            continue;
+        }
+        let error = |kind| ParsePaletteError { line: i + 1, kind };
+        let mut parts = line.split_whitespace();
+        let (Some(position), Some(color), None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(error(PaletteErrorKind::Syntax));
+        };
+        let position = position
+            .parse()
+            .ok()
+            .filter(|p| (0.0..=1.0).contains(p))
+            .ok_or(error(PaletteErrorKind::Position))?;
+        let color = parse_hex_color(color).ok_or(error(PaletteErrorKind::Color))?;
+        if stops.last().is_some_and(|&(last, _)| position < last) {
+            return Err(error(PaletteErrorKind::Order));
+        }
+        stops.push((position, color));
+    }
+    if stops.is_empty() {
+        return Err(ParsePaletteError {
+            line: 0,
+            kind: PaletteErrorKind::Empty,
+        });
+    }
+
+    Ok(std::array::from_fn(|i| {
+        let t = i as f32 / (PALETTE_SIZE - 1) as f32;
+        let next = stops.partition_point(|&(position, _)| position <= t);
+        let [r, g, b] = match (stops.get(next.wrapping_sub(1)), stops.get(next)) {
+            (Some(&(p0, c0)), Some(&(p1, c1))) => lerp(c0, c1, (t - p0) / (p1 - p0)),
+            (Some(&(_, c)), None) | (None, Some(&(_, c))) => c,
+            (None, None) => unreachable!(),
+        };
+        [r, g, b, 1.0]
+    }))
+}
+
+/// Saves the palette to `path` in the format of [`to_text`].
+pub fn save(palette: &Palette, path: impl AsRef<Path>) -> io::Result<()> {
+    std::fs::write(path, to_text(palette))
+}
+
+/// Loads a palette saved with [`save`].
+pub fn load(path: impl AsRef<Path>) -> io::Result<Palette> {
+    let text = std::fs::read_to_string(path)?;
+    from_text(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Why [`from_text`] rejected a palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParsePaletteError {
+    /// The offending line, counting from 1. 0 if the problem isn't with a particular line.
+    pub line: usize,
+    pub kind: PaletteErrorKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteErrorKind {
+    Syntax,
+    Position,
+    Color,
+    Order,
+    Empty,
+}
+
+impl fmt::Display for ParsePaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "line {}: ", self.line)?;
+        }
+        f.write_str(match self.kind {
+            PaletteErrorKind::Syntax => "expected a position and a color like `0.5 #ff8000`",
+            PaletteErrorKind::Position => "the position isn't a number from 0 to 1",
+            PaletteErrorKind::Color => "the color isn't 6 hex digits",
+            PaletteErrorKind::Order => "the stop comes before the one above it",
+            PaletteErrorKind::Empty => "there are no color stops",
+        })
+    }
+}
+
+impl std::error::Error for ParsePaletteError {}
+
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    std::array::from_fn(|c| a[c] + (b[c] - a[c]) * t)
+}
+
+/// Parses an sRGB color written as `RRGGBB` hex digits, optionally prefixed with `#`, into
+/// linear RGB.
+pub fn parse_hex_color(s: &str) -> Option<[f32; 3]> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let [r, g, b] = [channel(0)?, channel(2)?, channel(4)?];
+    Some([r, g, b].map(|c| srgb_to_linear(f32::from(c) / 255.0)))
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_palettes_parse() {
+        for (i, name) in BUILTIN_NAMES.iter().enumerate() {
+            let palette = builtin(i);
+            assert_ne!(palette[0], palette[PALETTE_SIZE / 2], "{name} is flat");
+        }
+    }
+
+    #[test]
+    fn text_round_trips_with_few_stops() {
+        for palette in [hsv_ramp(), random(7)] {
+            let text = to_text(&palette);
+            assert!(text.lines().count() < PALETTE_SIZE / 4, "{text}");
+            let parsed = from_text(&text).unwrap();
+            for (a, b) in palette.iter().zip(&parsed) {
+                for c in 0..3 {
+                    let error = (linear_to_srgb(a[c]) - linear_to_srgb(b[c])).abs() * 255.0;
+                    assert!(error <= STOP_TOLERANCE + 1.0, "{a:?} != {b:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn text_errors_name_the_line() {
+        let error = |text| from_text(text).unwrap_err();
+        assert_eq!(
+            error("# comment\n0 #000000\n0.5 #00ff0\n"),
+            ParsePaletteError {
+                line: 3,
+                kind: PaletteErrorKind::Color
+            }
+        );
+        assert_eq!(error("1 #000000\n0 #ffffff").kind, PaletteErrorKind::Order);
+        assert_eq!(error("1.5 #000000").kind, PaletteErrorKind::Position);
+        assert_eq!(error("#000000").kind, PaletteErrorKind::Syntax);
+        assert_eq!(error("\n# nothing\n").kind, PaletteErrorKind::Empty);
+    }
+}
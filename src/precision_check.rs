@@ -0,0 +1,276 @@
+//! Self-test of the double-double iteration (`--verify-precision`): renders a moderately deep
+//! and a very deep view in both `f64` and double-double and compares the escape times pixel by
+//! pixel. Where `f64` is still precise enough the two should agree, deeper the double-double
+//! pixels should stay distinct while the `f64` ones collapse onto each other.
+//!
+//! Both sides are computed on the CPU with [`core::escape`] and [`core::escape_extended`], so the
+//! check runs without a GPU. The shader's double-double mode mirrors the latter.
+//!
+//! [`max_zoom`] measures how deep a representation gets around a given center before the image
+//! turns blocky, by zooming in on small probe images until neighbouring pixels stop differing.
+
+use std::fmt;
+
+use crate::core::{self, ExtendedPoint, Precision};
+
+/// Near the boundary in the seahorse valley, so there's detail at every depth.
+pub const CENTER: [f64; 2] = [-0.743643887037151, 0.131825904205330];
+/// A view where `f64` resolves every pixel, but deep enough for rounding to matter.
+pub const SHALLOW_PIXEL_SIZE: f64 = 1e-10;
+/// Orbits near the boundary are chaotic, so with many more iterations than this `f64` rounding
+/// errors grow large enough to change escape times even where pixels are resolved.
+pub const SHALLOW_ITERATIONS: u32 = 1000;
+/// A view past the end of `f64` precision.
+pub const DEEP_PIXEL_SIZE: f64 = 1e-17;
+pub const DEEP_ITERATIONS: u32 = 5000;
+/// Largest fraction of pixels of the shallow view allowed to disagree.
+pub const MAX_SHALLOW_DISAGREEMENT: f64 = 0.01;
+
+/// Width and height of the probe images of [`max_zoom`].
+pub const PROBE_SIZE: u32 = 32;
+/// Each probe's pixels are this many times smaller than the last one's.
+pub const PROBE_ZOOM_STEP: f64 = 4.0;
+/// [`max_zoom`] gives up after this many probes, far beyond what double-double resolves.
+pub const MAX_PROBES: u32 = 80;
+/// Fraction of neighbouring escaped pixels with identical escape times above which a probe
+/// counts as blocky. Smooth escape times hardly ever coincide unless the pixels were computed at
+/// the same point.
+pub const MAX_BLOCKINESS: f64 = 0.25;
+
+/// How escape times computed in `f64` and double-double compare over a grid of pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comparison {
+    pub pixels: usize,
+    /// Pixels whose iteration counts differ, including those escaping in only one of them.
+    pub disagreements: usize,
+    /// Largest difference in iteration counts, counting points that don't escape as
+    /// `max_iterations`.
+    pub max_difference: u32,
+    /// Pixels with a point of their own, rather than one rounded onto a neighbour's.
+    pub resolved_f64: usize,
+    pub resolved_extended: usize,
+}
+
+impl Comparison {
+    pub fn disagreement(&self) -> f64 {
+        self.disagreements as f64 / self.pixels as f64
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}% of {} pixels disagree (by up to {} iterations), {} resolved in f64, {} in \
+             double-double",
+            self.disagreement() * 100.0,
+            self.pixels,
+            self.max_difference,
+            self.resolved_f64,
+            self.resolved_extended,
+        )
+    }
+}
+
+/// Compares the escape times of a `width` by `height` grid of pixels of size `pixel_size`
+/// around `center`.
+pub fn compare(
+    center: [f64; 2],
+    pixel_size: f64,
+    [width, height]: [u32; 2],
+    max_iterations: u32,
+) -> Comparison {
+    let offset = |i: u32, n: u32| (i as f64 + 0.5 - n as f64 / 2.0) * pixel_size;
+    let xs: Vec<_> = (0..width).map(|i| offset(i, width)).collect();
+    let ys: Vec<_> = (0..height).map(|j| offset(j, height)).collect();
+    let iterations = |escape: Option<core::Escape>| escape.map_or(max_iterations, |e| e.iterations);
+
+    let mut disagreements = 0;
+    let mut max_difference = 0;
+    for &dy in &ys {
+        for &dx in &xs {
+            let c = [center[0] + dx, center[1] + dy];
+            let mut extended = ExtendedPoint::new(center);
+            extended.translate([dx, dy]);
+            let a = iterations(core::escape(c, max_iterations));
+            let b = iterations(core::escape_extended(extended, max_iterations));
+            if a != b {
+                disagreements += 1;
+                max_difference = max_difference.max(a.abs_diff(b));
+            }
+        }
+    }
+
+    // The pixels form a grid, so they're resolved if both their column and row are.
+    let distinct = |c: f64, offsets: &[f64]| {
+        let rounded: Vec<_> = offsets.iter().map(|d| c + d).collect();
+        1 + rounded.windows(2).filter(|w| w[0] != w[1]).count()
+    };
+    let distinct_extended = |c: [f64; 2], offsets: &[f64], axis: usize| {
+        let points: Vec<_> = offsets
+            .iter()
+            .map(|&d| {
+                let mut point = ExtendedPoint::new(c);
+                point.translate(if axis == 0 { [d, 0.0] } else { [0.0, d] });
+                point
+            })
+            .collect();
+        1 + points
+            .windows(2)
+            .filter(|w| w[1].offset_from(w[0]) != [0.0; 2])
+            .count()
+    };
+    Comparison {
+        pixels: xs.len() * ys.len(),
+        disagreements,
+        max_difference,
+        resolved_f64: distinct(center[0], &xs) * distinct(center[1], &ys),
+        resolved_extended: distinct_extended(center, &xs, 0) * distinct_extended(center, &ys, 1),
+    }
+}
+
+/// The outcome of [`verify`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Report {
+    pub shallow: Comparison,
+    pub deep: Comparison,
+}
+
+impl Report {
+    /// Whether double-double matches `f64` in the shallow view and resolves every pixel of the
+    /// deep one, which `f64` can't.
+    pub fn passed(&self) -> bool {
+        let Self { shallow, deep } = self;
+        shallow.disagreement() <= MAX_SHALLOW_DISAGREEMENT
+            && shallow.resolved_extended == shallow.pixels
+            && shallow.resolved_f64 == shallow.pixels
+            && deep.resolved_extended == deep.pixels
+            && deep.resolved_f64 < deep.pixels
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "pixel size {SHALLOW_PIXEL_SIZE:e}: {}", self.shallow)?;
+        writeln!(f, "pixel size {DEEP_PIXEL_SIZE:e}: {}", self.deep)?;
+        f.write_str(if self.passed() { "passed" } else { "FAILED" })
+    }
+}
+
+/// Compares `size` pixels of the shallow and the deep view around [`CENTER`].
+pub fn verify(size: [u32; 2]) -> Report {
+    Report {
+        shallow: compare(CENTER, SHALLOW_PIXEL_SIZE, size, SHALLOW_ITERATIONS),
+        deep: compare(CENTER, DEEP_PIXEL_SIZE, size, DEEP_ITERATIONS),
+    }
+}
+
+/// The fraction of horizontally or vertically neighbouring pixels of the `width` pixels wide
+/// image `values` that have the same escape time, among those where both escaped within
+/// `max_iterations`. `None` if there are no such pairs.
+pub fn blockiness(values: &[f64], width: usize, max_iterations: u32) -> Option<f64> {
+    let escaped = |v: f64| v < max_iterations as f64;
+    let (mut pairs, mut equal) = (0, 0);
+    let mut count = |a: f64, b: f64| {
+        if escaped(a) && escaped(b) {
+            pairs += 1;
+            equal += usize::from(a == b);
+        }
+    };
+    for (i, &value) in values.iter().enumerate() {
+        if (i + 1) % width != 0 && i + 1 < values.len() {
+            count(value, values[i + 1]);
+        }
+        if let Some(&below) = values.get(i + width) {
+            count(value, below);
+        }
+    }
+    (pairs > 0).then(|| equal as f64 / pairs as f64)
+}
+
+/// The smooth escape times of a [`PROBE_SIZE`] pixels square probe image around `center`,
+/// computed on the CPU in `f64` or double-double, row by row. Points that don't escape hold
+/// `max_iterations`. `F32` is computed in `f64` as well, and there's no perturbation renderer
+/// yet, it's treated as double-double.
+pub fn cpu_probe(
+    precision: Precision,
+    center: [f64; 2],
+    pixel_size: f64,
+    max_iterations: u32,
+) -> Vec<f64> {
+    let offset = |i: u32| (i as f64 + 0.5 - PROBE_SIZE as f64 / 2.0) * pixel_size;
+    let mut values = Vec::with_capacity((PROBE_SIZE * PROBE_SIZE) as usize);
+    for j in (0..PROBE_SIZE).rev() {
+        for i in 0..PROBE_SIZE {
+            let delta = [offset(i), offset(j)];
+            let escape = match precision {
+                Precision::F32 | Precision::F64 => {
+                    core::escape([center[0] + delta[0], center[1] + delta[1]], max_iterations)
+                }
+                Precision::DoubleDouble | Precision::Perturbation => {
+                    let mut point = ExtendedPoint::new(center);
+                    point.translate(delta);
+                    core::escape_extended(point, max_iterations)
+                }
+            };
+            values.push(escape.map_or(max_iterations as f64, core::smooth_iterations));
+        }
+    }
+    values
+}
+
+/// How far [`max_zoom`] got, as the smallest pixel size whose probe still looked fine, or the
+/// first one if none did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZoomLimit {
+    /// The next probe was too blocky.
+    Blocky(f64),
+    /// No pair of neighbouring points of the next probe escaped, so its blockiness couldn't be
+    /// judged. More iterations or a center closer to the boundary might get further.
+    Underiterated(f64),
+    /// Still fine after [`MAX_PROBES`].
+    Unreached(f64),
+}
+
+impl ZoomLimit {
+    pub fn pixel_size(self) -> f64 {
+        match self {
+            Self::Blocky(size) | Self::Underiterated(size) | Self::Unreached(size) => size,
+        }
+    }
+}
+
+impl fmt::Display for ZoomLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blocky(size) => write!(f, "pixels of {size:.1e} resolved, smaller turn blocky"),
+            Self::Underiterated(size) => write!(
+                f,
+                "pixels of {size:.1e} resolved, nothing escaped beyond that"
+            ),
+            Self::Unreached(size) => write!(f, "pixels of {size:.1e} and larger all resolved"),
+        }
+    }
+}
+
+/// Zooms in from pixels of `start_pixel_size` by [`PROBE_ZOOM_STEP`] at a time, rendering a
+/// [`PROBE_SIZE`] pixels square image with `probe` at every pixel size, until an image is more
+/// blocky than [`MAX_BLOCKINESS`] allows.
+pub fn max_zoom(
+    start_pixel_size: f64,
+    max_iterations: u32,
+    mut probe: impl FnMut(f64) -> Vec<f64>,
+) -> ZoomLimit {
+    let mut resolved = start_pixel_size;
+    let mut pixel_size = start_pixel_size;
+    for _ in 0..MAX_PROBES {
+        let values = probe(pixel_size);
+        match blockiness(&values, PROBE_SIZE as usize, max_iterations) {
+            None => return ZoomLimit::Underiterated(resolved),
+            Some(blockiness) if blockiness > MAX_BLOCKINESS => return ZoomLimit::Blocky(resolved),
+            Some(_) => resolved = pixel_size,
+        }
+        pixel_size /= PROBE_ZOOM_STEP;
+    }
+    ZoomLimit::Unreached(resolved)
+}
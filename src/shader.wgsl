@@ -1,88 +1,1063 @@
-// Vertex shader
-
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) vert_pos: vec2<f32>,
-};
-
-@vertex
-fn vs_main(
-    @builtin(vertex_index) in_vertex_index: u32,
-) -> VertexOutput {
-    var positions: array<vec2<f32>, 4> = array<vec2<f32>, 4>(
-        vec2<f32>(-1, -1),
-        vec2<f32>(1, -1),
-        vec2<f32>(-1, 1),
-        vec2<f32>(1, 1),
-    );
-    var pos: vec2<f32> = positions[in_vertex_index];
-    return VertexOutput(
-        vec4<f32>(pos, 0.0, 1.0),
-        pos/2.0 + vec2<f32>(0.5),
-    );
-} 
-
-// Fragment shader
-
-fn hsv2rgb(c: vec3<f32>) -> vec3<f32> {
-    var K = vec4<f32>(1.0, 2.0/3.0, 1.0/3.0, 3.0);
-    var p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
-    return c.z * mix(K.xxx, clamp(p - K.xxx, vec3<f32>(0.0), vec3<f32>(1.0)), c.y);
-}
-
-fn mandelbrot(c: vec2<f64>, max_its: u32) -> u32 {
-    var z = vec2<f64>(0.0);
-
-    for (var i = 0u; i < max_its; i++) {
-        if (dot(z, z) >= 4.0) {
-            return i;
-        }
-        var z_conj = vec2<f64>(z.x, -z.y);
-        z = vec2<f64>(
-            z.x*z.x - z.y*z.y + c.x,
-            2.0*z.x*z.y + c.y
-        );
-    }
-
-    return max_its;
-}
-
-fn pixel_color(its: u32, max_its: u32) -> vec3<f32> {
-    if its == max_its {
-        return vec3<f32>(0.0);
-    } else {
-        var h = f32(its)/f32(max_its);
-        var hsv = vec3<f32>(h, 1.0, f32(its < max_its));
-        return hsv2rgb(hsv);
-    }
-}
-
-fn uv2coord(uv: vec2<f64>) -> vec2<f64> {
-    var width = uni.aspect_ratio * uni.height;
-    return vec2<f64>(
-        uni.min_x + uv.x * width,
-        uni.min_y + uv.y * uni.height,
-    );
-}
-
-struct MandelbrotUniform {
-    min_x: f64,
-    min_y: f64,
-    height: f64,
-    aspect_ratio: f64,
-    max_its: u32,
-    padding: u32,
-}
-
-@group(0) @binding(0)
-var<uniform> uni: MandelbrotUniform;
-
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // var xrange = vec2<f32>(-0.749488, -0.7492405);
-    // var yrange = vec2<f32>(0.031567533, 0.03170943);
-    var coord = uv2coord(vec2<f64>(in.vert_pos));
-    var max_its: u32 = uni.max_its;
-    var m = mandelbrot(coord, max_its);
-    return vec4<f32>(pixel_color(m, max_its), 0.0);
-}
+// Float types of view coordinates and orbits. `ShaderFloat::source` swaps these two lines for
+// `f32` on GPUs without `SHADER_F64`, and only them.
+alias float = f64;
+alias float2 = vec2<f64>;
+
+// Vertex shader
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) vert_pos: vec2<f32>,
+};
+
+// Draws a quad covering the whole viewport as a 4 vertex triangle strip, counterclockwise in
+// clip space: bottom left, bottom right, top left, top right. Pipelines don't cull faces, so
+// the winding doesn't matter for visibility.
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var positions: array<vec2<f32>, 4> = array<vec2<f32>, 4>(
+        vec2<f32>(-1, -1),
+        vec2<f32>(1, -1),
+        vec2<f32>(-1, 1),
+        vec2<f32>(1, 1),
+    );
+    var pos: vec2<f32> = positions[in_vertex_index];
+    return VertexOutput(
+        vec4<f32>(pos, 0.0, 1.0),
+        pos/2.0 + vec2<f32>(0.5),
+    );
+} 
+
+// Fragment shader
+
+struct EscapeResult {
+    its: u32,
+    // Set when the orbit was found to converge to an attracting cycle, in which case `its` is
+    // the iteration at which that was detected.
+    interior: bool,
+    // Continuous escape time, normalized like `core::smooth_iterations`. Equal to `its` for
+    // points that didn't escape.
+    smooth_its: f32,
+}
+
+// Squared magnitude of the orbit derivative below which the orbit is considered to have
+// settled into an attracting cycle, i.e. the point lies inside the set, in views of the whole
+// set. Same as `core::INTERIOR_THRESHOLD`.
+const INTERIOR_THRESHOLD: float = 1e-6;
+
+// Same as `core::interior_threshold`: smaller in deeper views, where exterior orbits starting
+// close to the boundary pass near 0 and briefly shrink their derivative.
+fn interior_threshold() -> float {
+    var pixel_size = uni.height / 1000.0;
+    return min(INTERIOR_THRESHOLD, pixel_size * pixel_size * pixel_size);
+}
+
+// Smallest escape radius with `FLAG_SMOOTH_COLORING`, same as `core::SMOOTH_ESCAPE_RADIUS`. The
+// smooth escape time assumes |z| squares every iteration once the orbit escaped, which only
+// holds well for large |z|. Stopping at radius 2 leaves slight steps in the colors where the
+// iteration count changes.
+const SMOOTH_ESCAPE_RADIUS: float = 16.0;
+
+// `uni.escape_radius` as `core::escape_radius` adjusts it.
+fn escape_radius() -> float {
+    var radius = float(max(uni.escape_radius, 2.0));
+    if flag(FLAG_SMOOTH_COLORING) {
+        return max(radius, SMOOTH_ESCAPE_RADIUS);
+    }
+    return radius;
+}
+
+// Whether z lies outside the `escape_radius()` ball of the norm selected by `uni.norm_type`.
+fn escaped(z: float2) -> bool {
+    var radius = escape_radius();
+    switch uni.norm_type {
+        case 1u: {
+            return max(abs(z.x), abs(z.y)) >= radius;
+        }
+        case 2u: {
+            return abs(z.x) + abs(z.y) >= radius;
+        }
+        default: {
+            return dot(z, z) >= radius * radius;
+        }
+    }
+}
+
+// Continuous escape time of an orbit that escaped at `z` after `its` iterations, normalized
+// like `core::smooth_iterations` so it doesn't depend on the escape radius.
+fn smooth_escape(its: u32, z: float2) -> f32 {
+    var ratio = log(f32(dot(z, z))) / log(4.0);
+    return f32(its) + 1.0 - log2(ratio);
+}
+
+// Where the orbit of the pixel at `point` starts and what's added every iteration.
+struct Orbit {
+    z: float2,
+    c: float2,
+}
+
+// The Mandelbrot set starts every orbit at 0 and adds the pixel, Julia sets start at the pixel
+// and add `uni.julia_c`.
+fn orbit_start(point: float2) -> Orbit {
+    if flag(FLAG_JULIA) {
+        return Orbit(point, uni.julia_c);
+    }
+    return Orbit(float2(0.0), point);
+}
+
+// With `FLAG_BURNING_SHIP`, orbits are folded into the first quadrant before every squaring,
+// like `core::burning_ship_escape`. Interior detection only looks at the size of the derivative,
+// which the fold doesn't change.
+fn fold(z: float2) -> float2 {
+    if flag(FLAG_BURNING_SHIP) {
+        return abs(z);
+    }
+    return z;
+}
+
+fn mandelbrot(point: float2, max_its: u32) -> EscapeResult {
+    var orbit = orbit_start(point);
+    var z = orbit.z;
+    var c = orbit.c;
+    // Derivative of z with respect to the orbit's second point, like `core::classify`. The
+    // first is the critical point 0 for every Mandelbrot orbit.
+    var dz = float2(1.0, 0.0);
+    var threshold = interior_threshold();
+
+    for (var i = 0u; i < max_its; i++) {
+        if escaped(z) {
+            return EscapeResult(i, false, smooth_escape(i, z));
+        }
+        if flag(FLAG_INTERIOR_DETECTION) && i > 0u {
+            dz = 2.0 * float2(
+                z.x*dz.x - z.y*dz.y,
+                z.x*dz.y + z.y*dz.x
+            );
+            if (dot(dz, dz) < threshold) {
+                return EscapeResult(i, true, f32(i));
+            }
+        }
+        z = fold(z);
+        z = float2(
+            z.x*z.x - z.y*z.y + c.x,
+            2.0*z.x*z.y + c.y
+        );
+    }
+
+    return EscapeResult(max_its, false, f32(max_its));
+}
+
+// Double-double arithmetic like `core::DoubleDouble`: an unevaluated sum `x + y` with `|y|` at
+// most half an ulp of `x`, for about 106 bits of mantissa.
+fn two_sum(a: float, b: float) -> float2 {
+    var sum = a + b;
+    var b_rounded = sum - a;
+    return float2(sum, (a - (sum - b_rounded)) + (b - b_rounded));
+}
+
+// Like `two_sum`, for `|a| >= |b|`.
+fn fast_two_sum(a: float, b: float) -> float2 {
+    var sum = a + b;
+    return float2(sum, b - (sum - a));
+}
+
+fn dd_add(a: float2, b: float2) -> float2 {
+    var sum = two_sum(a.x, b.x);
+    return fast_two_sum(sum.x, sum.y + a.y + b.y);
+}
+
+// 2^27 + 1, which splits an f64's 53 bit mantissa into two halves. The f32 variant never takes
+// the double-double path, the host clears `FLAG_DOUBLE_DOUBLE` for it.
+const SPLITTER: float = 134217729.0;
+
+// Veltkamp's split of `a` into a high part holding its upper 26 bits and the rest, so the
+// products of the halves of two numbers are exact.
+fn split(a: float) -> float2 {
+    var scaled = SPLITTER * a;
+    var hi = scaled - (scaled - a);
+    return float2(hi, a - hi);
+}
+
+// The rounded product of `a` and `b` and its rounding error, by Dekker's algorithm. `fma` would
+// be shorter, but not every GPU fuses it into a single rounding for f64.
+fn two_product(a: float, b: float) -> float2 {
+    var product = a * b;
+    var a_parts = split(a);
+    var b_parts = split(b);
+    var err = ((a_parts.x * b_parts.x - product) + a_parts.x * b_parts.y + a_parts.y * b_parts.x)
+        + a_parts.y * b_parts.y;
+    return float2(product, err);
+}
+
+fn dd_mul(a: float2, b: float2) -> float2 {
+    var product = two_product(a.x, b.x);
+    return fast_two_sum(product.x, product.y + a.x * b.y + a.y * b.x);
+}
+
+// A point of the complex plane with double-double coordinates.
+struct ExtendedPoint {
+    x: float2,
+    y: float2,
+}
+
+// Like `uv2coord`, but adding the offset from the corner of the view to its low order bits in
+// `min_lo` before rounding, so pixels too close together for f64 still get points of their own.
+// Not for log-polar views, whose points are only computed in f32 anyway.
+fn uv2coord_extended(uv: float2) -> ExtendedPoint {
+    var width = uni.aspect_ratio * uni.height;
+    var size = float2(width, uni.height);
+    var offset = (uv - float2(0.5)) * size;
+    var angle = f32(uni.rotation);
+    var cs = float2(vec2<f32>(cos(angle), sin(angle)));
+    var from_min = size / 2.0 + float2(
+        offset.x * cs.x - offset.y * cs.y,
+        offset.x * cs.y + offset.y * cs.x,
+    );
+    return ExtendedPoint(
+        dd_add(float2(uni.min_x, uni.min_lo.x), float2(from_min.x, 0.0)),
+        dd_add(float2(uni.min_y, uni.min_lo.y), float2(from_min.y, 0.0)),
+    );
+}
+
+// Like `mandelbrot`, iterating in double-double. Escaping and the orbit derivative for interior
+// detection only need the f64 part, and the derivative is updated from z before z advances, the
+// same as there.
+fn mandelbrot_extended(point: ExtendedPoint, max_its: u32) -> EscapeResult {
+    var x = float2(0.0);
+    var y = float2(0.0);
+    var cx = point.x;
+    var cy = point.y;
+    if flag(FLAG_JULIA) {
+        x = point.x;
+        y = point.y;
+        cx = float2(uni.julia_c.x, 0.0);
+        cy = float2(uni.julia_c.y, 0.0);
+    }
+    var dz = float2(1.0, 0.0);
+    var threshold = interior_threshold();
+
+    for (var i = 0u; i < max_its; i++) {
+        var z = float2(x.x, y.x);
+        if escaped(z) {
+            return EscapeResult(i, false, smooth_escape(i, z));
+        }
+        if flag(FLAG_INTERIOR_DETECTION) && i > 0u {
+            dz = 2.0 * float2(
+                z.x*dz.x - z.y*dz.y,
+                z.x*dz.y + z.y*dz.x
+            );
+            if (dot(dz, dz) < threshold) {
+                return EscapeResult(i, true, f32(i));
+            }
+        }
+        if flag(FLAG_BURNING_SHIP) {
+            x = select(x, -x, x.x < 0.0);
+            y = select(y, -y, y.x < 0.0);
+        }
+        var xx = dd_mul(x, x);
+        var yy = dd_mul(y, y);
+        var xy = dd_mul(x, y);
+        x = dd_add(dd_add(xx, -yy), cx);
+        y = dd_add(dd_add(xy, xy), cy);
+    }
+
+    return EscapeResult(max_its, false, f32(max_its));
+}
+
+// The escape time of the point at `uv`, iterated in double-double with `FLAG_DOUBLE_DOUBLE`.
+fn escape_at(uv: float2, max_its: u32) -> EscapeResult {
+    if flag(FLAG_DOUBLE_DOUBLE) && !flag(FLAG_LOG_POLAR) {
+        return mandelbrot_extended(uv2coord_extended(uv), max_its);
+    }
+    return mandelbrot(uv2coord(uv), max_its);
+}
+
+// Linearly interpolates between the two palette entries surrounding t, with t in [0, 1].
+fn sample_palette(t: f32) -> vec3<f32> {
+    var x = clamp(t, 0.0, 1.0) * f32(PALETTE_SIZE - 1u);
+    var i = u32(x);
+    var j = min(i + 1u, PALETTE_SIZE - 1u);
+    return mix(palette[i].rgb, palette[j].rgb, fract(x));
+}
+
+// Scales colors up to hdr_peak the closer t gets to 1, so the thin filaments that take the most
+// iterations to escape glow on HDR displays.
+fn hdr_boost(t: f32) -> f32 {
+    return 1.0 + (uni.hdr_peak - 1.0) * t * t;
+}
+
+// Marks points that ran out of iterations when `FLAG_SHOW_UNDERITERATED` is set.
+const UNDERITERATED_COLOR: vec3<f32> = vec3<f32>(1.0, 0.0, 1.0);
+
+fn pixel_color(res: EscapeResult, max_its: u32) -> vec3<f32> {
+    if res.interior {
+        // Quickly converging points get a faint glow, fading out towards the boundary.
+        return uni.interior_color.rgb + vec3<f32>(0.2 * (1.0 - f32(res.its)/f32(max_its)));
+    } else if res.its == max_its {
+        if flag(FLAG_SHOW_UNDERITERATED) {
+            return UNDERITERATED_COLOR;
+        }
+        return uni.interior_color.rgb;
+    } else if flag(FLAG_SMOOTH_COLORING) {
+        return escaped_color(res.smooth_its, res.smooth_its, max_its);
+    } else {
+        return escaped_color(f32(res.its), res.smooth_its, max_its);
+    }
+}
+
+// Colors an escaped point by its escape time, which may be fractional. The palette repeats
+// according to the smooth escape time if a color period is set.
+fn escaped_color(its: f32, smooth_its: f32, max_its: u32) -> vec3<f32> {
+    var t = its/f32(max_its);
+    var color_t = t;
+    if uni.color_period > 0.0 {
+        color_t = fract(smooth_its / uni.color_period);
+    }
+    return sample_palette(color_t) * hdr_boost(t);
+}
+
+fn uv2coord(uv: float2) -> float2 {
+    var width = uni.aspect_ratio * uni.height;
+    var size = float2(width, uni.height);
+    var center = float2(uni.min_x, uni.min_y) + size / 2.0;
+    var offset = (uv - float2(0.5)) * size;
+    // There are no f64 trig functions, but f32 is plenty for the rotation itself.
+    var angle = f32(uni.rotation);
+    var cs = float2(vec2<f32>(cos(angle), sin(angle)));
+    var point = center + float2(
+        offset.x * cs.x - offset.y * cs.y,
+        offset.x * cs.y + offset.y * cs.x,
+    );
+    if flag(FLAG_LOG_POLAR) {
+        return from_log_polar(point);
+    }
+    return point;
+}
+
+// The point at `[ln(r), angle]` around `log_polar_center`. The offset is only computed in f32,
+// which lacks the range for ln(r) far below -87 but is precise relative to r, so the center
+// stays sharp down to where f64 can't resolve points around it anyway.
+fn from_log_polar(w: float2) -> float2 {
+    var r = exp(f32(w.x));
+    var angle = f32(w.y);
+    return uni.log_polar_center + float2(r * vec2<f32>(cos(angle), sin(angle)));
+}
+
+// Must match `MandelbrotUniform` in uniform.rs, which asserts the offsets noted here.
+struct MandelbrotUniform {
+    // offset 0
+    min_x: float,
+    min_y: float,
+    height: float,
+    aspect_ratio: float,
+    // offset 32
+    max_its: u32,
+    flags: u32,
+    // offset 40
+    rotation: float,
+    // offset 48
+    hdr_peak: f32,
+    contour_interval: f32,
+    norm_type: u32,
+    color_period: f32,
+    // offset 64
+    grid_spacing: float,
+    // offset 72
+    aa_pattern: u32,
+    samples_per_pixel: u32,
+    // offset 80
+    julia_c: float2,
+    // offset 96
+    interior_color: vec4<f32>,
+    // offset 112
+    log_polar_center: float2,
+    // offset 128
+    min_lo: float2,
+    // offset 144
+    escape_radius: f32,
+    // size 160
+}
+
+// Bits of `uni.flags`. Must match the `FLAG_` constants of `MandelbrotUniform` in uniform.rs,
+// which a test there checks.
+//
+// Stop iterating once the orbit derivative shows convergence to a cycle.
+const FLAG_INTERIOR_DETECTION: u32 = 1u;
+// Draw lines where the smooth escape time crosses a multiple of `contour_interval`.
+const FLAG_CONTOURS: u32 = 2u;
+// Soften color bands by averaging over the escape times each pixel covers.
+const FLAG_AA_FWIDTH: u32 = 4u;
+// Overlay lines every `grid_spacing` along both axes of the complex plane.
+const FLAG_AXES_GRID: u32 = 8u;
+// Tint points that ran out of iterations without escaping or being detected as interior.
+const FLAG_SHOW_UNDERITERATED: u32 = 16u;
+// Render the Julia set of `julia_c` instead of the Mandelbrot set.
+const FLAG_JULIA: u32 = 32u;
+// Leave points that don't escape fully transparent, for compositing over other content.
+const FLAG_TRANSPARENT_INTERIOR: u32 = 64u;
+// Encode colors drawn to the window as sRGB here instead of leaving it to the surface format.
+const FLAG_ENCODE_SRGB: u32 = 128u;
+// Line art of the set's boundary, see `boundary_color`.
+const FLAG_BOUNDARY: u32 = 256u;
+// The view shows log-polar coordinates around `log_polar_center`, see `from_log_polar`.
+const FLAG_LOG_POLAR: u32 = 512u;
+// Color by the smooth escape time instead of the iteration count, see `SMOOTH_ESCAPE_RADIUS`.
+const FLAG_SMOOTH_COLORING: u32 = 1024u;
+// Compute points and iterate in double-double, see `escape_at`.
+const FLAG_DOUBLE_DOUBLE: u32 = 2048u;
+// Draw the Burning Ship instead of the Mandelbrot set, see `fold`.
+const FLAG_BURNING_SHIP: u32 = 4096u;
+
+fn flag(bit: u32) -> bool {
+    return (uni.flags & bit) != 0u;
+}
+
+@group(0) @binding(0)
+var<uniform> uni: MandelbrotUniform;
+
+// Must match `palette::PALETTE_SIZE`.
+const PALETTE_SIZE: u32 = 256u;
+
+@group(0) @binding(1)
+var<uniform> palette: array<vec4<f32>, PALETTE_SIZE>;
+
+// The color of a point with premultiplied alpha, which is only ever below 1 for points that
+// don't escape with `FLAG_TRANSPARENT_INTERIOR`. Averaging premultiplied samples makes the alpha
+// of a pixel the fraction of it that lies outside the set.
+fn premultiplied_color(res: EscapeResult, max_its: u32) -> vec4<f32> {
+    if flag(FLAG_TRANSPARENT_INTERIOR) && (res.interior || res.its == max_its) {
+        return vec4<f32>(0.0);
+    }
+    return vec4<f32>(pixel_color(res, max_its), 1.0);
+}
+
+fn shade(uv: vec2<f32>) -> vec4<f32> {
+    // var xrange = vec2<f32>(-0.749488, -0.7492405);
+    // var yrange = vec2<f32>(0.031567533, 0.03170943);
+    var max_its: u32 = uni.max_its;
+    var m = escape_at(float2(uv), max_its);
+    return premultiplied_color(m, max_its);
+}
+
+// Darkens pixels close to where the smooth escape time crosses a multiple of
+// `contour_interval`, giving lines about a pixel wide at any zoom level. `its_width` is how much
+// the escape time changes across the pixel.
+fn contour_shade(smooth_its: f32, its_width: f32) -> f32 {
+    var x = smooth_its / uni.contour_interval;
+    var distance = abs(fract(x + 0.5) - 0.5) / max(its_width / uni.contour_interval, 1e-6);
+    return mix(0.25, 1.0, smoothstep(0.5, 1.5, distance));
+}
+
+// Number of escape times averaged by `filtered_color`.
+const FWIDTH_AA_SAMPLES: u32 = 4u;
+
+// Averages the color over the range of escape times a pixel covers, which softens the bands
+// where neighbouring pixels escape at very different iterations.
+fn filtered_color(smooth_its: f32, its_width: f32) -> vec3<f32> {
+    var color = vec3<f32>(0.0);
+    for (var i = 0u; i < FWIDTH_AA_SAMPLES; i++) {
+        var its = smooth_its + its_width * ((f32(i) + 0.5) / f32(FWIDTH_AA_SAMPLES) - 0.5);
+        color += escaped_color(its, its, uni.max_its);
+    }
+    return color / f32(FWIDTH_AA_SAMPLES);
+}
+
+// How strongly the axes grid covers the pixel at `coord`, with the real and imaginary axes
+// drawn brighter than the other grid lines. `pixel` is the size of a pixel in uv space.
+fn axes_grid_coverage(coord: float2, pixel: vec2<f32>) -> f32 {
+    if uni.grid_spacing <= 0.0 {
+        return 0.0;
+    }
+    var size = vec2<f32>(f32(uni.aspect_ratio * uni.height), f32(uni.height));
+    // Rotated views make lines a bit wider, which is fine for an overlay.
+    var line_width = max(pixel.x * size.x, pixel.y * size.y);
+    var cell = coord / uni.grid_spacing;
+    var grid = vec2<f32>(abs(cell - floor(cell + 0.5))) * f32(uni.grid_spacing) / line_width;
+    var axes = vec2<f32>(abs(coord)) / line_width;
+    var on_grid = 1.0 - smoothstep(0.5, 1.0, min(grid.x, grid.y));
+    var on_axes = 1.0 - smoothstep(0.5, 1.5, min(axes.x, axes.y));
+    return max(0.4 * on_grid, 0.9 * on_axes);
+}
+
+// https://nullprogram.com/blog/2018/07/31/
+fn hash(x: u32) -> u32 {
+    var h = x;
+    h ^= h >> 16u;
+    h *= 0x7feb352du;
+    h ^= h >> 15u;
+    h *= 0x846ca68bu;
+    h ^= h >> 16u;
+    return h;
+}
+
+// Offset of the `i`th random sample of the pixel at `p` from its center, in pixels. The same
+// pixel always gets the same samples, so still images don't shimmer, and the hash only uses
+// integer arithmetic, so they're the same on every GPU.
+fn jitter(p: vec2<u32>, i: u32) -> vec2<f32> {
+    var h = hash(p.x ^ hash(p.y ^ hash(i)));
+    var bits = vec2<u32>(h, hash(h)) >> vec2<u32>(8u);
+    return vec2<f32>(bits) / 16777216.0 - 0.5;
+}
+
+// Averages `uni.samples_per_pixel` randomly placed samples of the pixel at `uv`, catching
+// filaments thinner than a pixel that the center alone misses. `pixel` is the size of a pixel
+// in uv space.
+fn supersample(uv: vec2<f32>, p: vec2<u32>, pixel: vec2<f32>) -> vec4<f32> {
+    var color = vec4<f32>(0.0);
+    for (var i = 0u; i < uni.samples_per_pixel; i++) {
+        color += shade(uv + jitter(p, i) * pixel);
+    }
+    return color / f32(uni.samples_per_pixel);
+}
+
+// Escape time change between neighboring pixels above which `FLAG_BOUNDARY` draws a line. Thin
+// filaments never contain a pixel that doesn't escape, but the escape time jumps across them.
+const BOUNDARY_ITS_JUMP: f32 = 4.0;
+
+// The color of a pixel with `FLAG_BOUNDARY`: black where it or a neighbor is on the other side
+// of the boundary, or where the escape time changes sharply, and white elsewhere. The widths
+// are the `fwidth` of whether the point escaped and of its smooth escape time.
+fn boundary_color(inside_width: f32, its_width: f32) -> vec4<f32> {
+    var line = max(step(0.5, inside_width), smoothstep(0.5, 1.0, its_width / BOUNDARY_ITS_JUMP));
+    return vec4<f32>(vec3<f32>(1.0 - line), 1.0);
+}
+
+// `boundary_color` from the escape results of a pixel and its right and lower neighbours, for
+// renderers that have those at hand instead of derivatives.
+fn neighbour_boundary_color(
+    m: EscapeResult,
+    right: EscapeResult,
+    below: EscapeResult,
+) -> vec4<f32> {
+    var inside = vec3<f32>(
+        select(0.0, 1.0, m.interior || m.its == uni.max_its),
+        select(0.0, 1.0, right.interior || right.its == uni.max_its),
+        select(0.0, 1.0, below.interior || below.its == uni.max_its),
+    );
+    var inside_width = abs(inside.y - inside.x) + abs(inside.z - inside.x);
+    var its_width = abs(right.smooth_its - m.smooth_its) + abs(below.smooth_its - m.smooth_its);
+    return boundary_color(inside_width, its_width);
+}
+
+// The color of a pixel of the view with premultiplied alpha, with all the enabled overlays and
+// anti-aliasing.
+fn view_color(in: VertexOutput) -> vec4<f32> {
+    var p = vec2<u32>(in.clip_position.xy);
+    if (uni.flags & (FLAG_CONTOURS | FLAG_AA_FWIDTH | FLAG_AXES_GRID | FLAG_BOUNDARY)) == 0u {
+        if uni.samples_per_pixel > 1u {
+            // The branches so far are uniform, so derivatives can still be taken here.
+            var pixel = vec2<f32>(dpdx(in.vert_pos.x), dpdy(in.vert_pos.y));
+            return supersample(in.vert_pos, p, pixel);
+        }
+        return shade(in.vert_pos);
+    }
+    var coord = uv2coord(float2(in.vert_pos));
+    var m = escape_at(float2(in.vert_pos), uni.max_its);
+    // Derivatives have to be taken before branching on anything that differs between pixels.
+    var its_width = fwidth(m.smooth_its);
+    var pixel = fwidth(in.vert_pos);
+    var inside_width = fwidth(select(0.0, 1.0, m.interior || m.its == uni.max_its));
+    var color = premultiplied_color(m, uni.max_its);
+    if uni.samples_per_pixel > 1u {
+        color = supersample(in.vert_pos, p, pixel);
+    }
+    if flag(FLAG_BOUNDARY) {
+        color = boundary_color(inside_width, its_width);
+    } else if !m.interior && m.its < uni.max_its {
+        // Supersampling already softens the bands.
+        if flag(FLAG_AA_FWIDTH) && uni.samples_per_pixel <= 1u {
+            color = vec4<f32>(filtered_color(m.smooth_its, its_width), 1.0);
+        }
+        if flag(FLAG_CONTOURS) {
+            color = vec4<f32>(color.rgb * contour_shade(m.smooth_its, its_width), color.a);
+        }
+    }
+    if flag(FLAG_AXES_GRID) {
+        color = mix(color, vec4<f32>(1.0), axes_grid_coverage(coord, pixel));
+    }
+    return color;
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    var low = c * 12.92;
+    var high = 1.055 * pow(max(c, vec3<f32>(0.0)), vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, c <= vec3<f32>(0.0031308));
+}
+
+// Applies `FLAG_ENCODE_SRGB` to a linear color about to be drawn to the window. On an sRGB
+// surface that encodes it twice, which is what colors that look too bright would suggest.
+fn window_color(color: vec3<f32>) -> vec3<f32> {
+    if flag(FLAG_ENCODE_SRGB) {
+        return linear_to_srgb(color);
+    }
+    return color;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = view_color(in);
+    return vec4<f32>(window_color(color.rgb), color.a);
+}
+
+// The view in linear color whatever `FLAG_ENCODE_SRGB` says, for saving as floating point data.
+@fragment
+fn fs_linear(in: VertexOutput) -> @location(0) vec4<f32> {
+    return view_color(in);
+}
+
+// The view for saving as an image. Image files like PNG store straight rather than
+// premultiplied alpha, so the color is divided back out; without `FLAG_TRANSPARENT_INTERIOR`
+// alpha is always 1 and this matches fs_linear.
+@fragment
+fn fs_image(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = view_color(in);
+    if color.a > 0.0 {
+        return vec4<f32>(color.rgb / color.a, color.a);
+    }
+    return color;
+}
+
+// Fraction of the loupe's radius taken up by its rim.
+const LOUPE_RIM: f32 = 0.03;
+
+// The magnifier loupe: the view drawn into a square viewport around the cursor, cut to a circle
+// with a white rim.
+@fragment
+fn fs_loupe(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = view_color(in).rgb;
+    var r = 2.0 * length(in.vert_pos - 0.5);
+    if r > 1.0 {
+        discard;
+    }
+    if r > 1.0 - LOUPE_RIM {
+        color = vec3<f32>(1.0);
+    }
+    return vec4<f32>(window_color(color), 0.0);
+}
+
+// Escape time of a single pixel for the inspector: the smooth and whole iteration count, and 1
+// if the point was detected as interior.
+@fragment
+fn fs_inspect(in: VertexOutput) -> @location(0) vec4<f32> {
+    var m = escape_at(float2(in.vert_pos), uni.max_its);
+    return vec4<f32>(m.smooth_its, f32(m.its), select(0.0, 1.0, m.interior), 0.0);
+}
+
+// The continuous escape time, for exporting as raw data. Points that didn't escape, including
+// detected interior points, hold max_its.
+@fragment
+fn fs_smooth(in: VertexOutput) -> @location(0) f32 {
+    var m = escape_at(float2(in.vert_pos), uni.max_its);
+    if m.interior {
+        return f32(uni.max_its);
+    }
+    return m.smooth_its;
+}
+
+// Squared radius orbits are followed to for distance estimation. The estimate gets more accurate
+// the larger it is.
+const DISTANCE_BAILOUT: float = 1e10;
+
+// The distance estimate |z| ln|z| / |dz/dp| from the pixel to the set, in pixels, for exporting
+// as a heightmap, where p is c for the Mandelbrot set and the starting point for Julia sets.
+// Points that didn't escape are at distance 0. Always uses the Euclidean norm.
+@fragment
+fn fs_distance(in: VertexOutput) -> @location(0) f32 {
+    // Taken before the loop, as derivatives need uniform control flow.
+    var pixel = float(abs(dpdy(in.vert_pos.y))) * uni.height;
+    var orbit = orbit_start(uv2coord(float2(in.vert_pos)));
+    var z = orbit.z;
+    var c = orbit.c;
+    // Julia sets start with dz/dp = 1, and c doesn't depend on p.
+    var julia = flag(FLAG_JULIA);
+    var dc = select(float2(0.0), float2(1.0, 0.0), julia);
+    var dc_step = select(float2(1.0, 0.0), float2(0.0), julia);
+    for (var i = 0u; i < uni.max_its; i++) {
+        var r = dot(z, z);
+        if r >= DISTANCE_BAILOUT {
+            var abs_z = sqrt(f32(r));
+            // Scaled to pixels while still in f64, where |dz/dc| can't overflow.
+            var dc_pixels = vec2<f32>(dc * pixel);
+            return abs_z * log(abs_z) / length(dc_pixels);
+        }
+        if flag(FLAG_BURNING_SHIP) {
+            // Folding z mirrors its derivative along with it.
+            dc *= sign(z);
+            z = abs(z);
+        }
+        dc = 2.0 * float2(
+            z.x*dc.x - z.y*dc.y,
+            z.x*dc.y + z.y*dc.x
+        ) + dc_step;
+        z = float2(
+            z.x*z.x - z.y*z.y + c.x,
+            2.0*z.x*z.y + c.y
+        );
+    }
+    return 0.0;
+}
+
+@fragment
+fn fs_divider(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+
+// Adaptive anti-aliasing
+
+// Escape values are the iteration count, negated (and offset by one) for detected interior
+// points, so that interior and exterior neighbours always compare as an edge, followed by the
+// smooth escape time.
+fn encode_escape(res: EscapeResult) -> vec2<f32> {
+    if res.interior {
+        return vec2<f32>(-1.0 - f32(res.its), res.smooth_its);
+    }
+    return vec2<f32>(f32(res.its), res.smooth_its);
+}
+
+fn decode_escape(value: vec2<f32>) -> EscapeResult {
+    if value.x < 0.0 {
+        return EscapeResult(u32(-1.0 - value.x), true, value.y);
+    }
+    return EscapeResult(u32(value.x), false, value.y);
+}
+
+// Neighbouring pixels whose escape values differ by more than this get supersampled.
+const AA_EDGE_THRESHOLD: f32 = 1.0;
+
+// The escape values for adaptive anti-aliasing, or the CPU or background rendered image for
+// fs_blit.
+@group(1) @binding(0)
+var input_texture: texture_2d<f32>;
+
+@fragment
+fn fs_escape(in: VertexOutput) -> @location(0) vec2<f32> {
+    return encode_escape(escape_at(float2(in.vert_pos), uni.max_its));
+}
+
+// Offset of the `i`th of 4 supersamples from the pixel center in pixels, in the pattern picked
+// by `uni.aa_pattern`.
+fn aa_offset(i: i32) -> vec2<f32> {
+    switch uni.aa_pattern {
+        // Axis aligned grid
+        case 1u: {
+            var offsets = array<vec2<f32>, 4>(
+                vec2<f32>(-0.25, -0.25),
+                vec2<f32>(0.25, -0.25),
+                vec2<f32>(-0.25, 0.25),
+                vec2<f32>(0.25, 0.25),
+            );
+            return offsets[i];
+        }
+        // Halton sequence in bases 2 and 3, points 1 to 4, centered on the pixel
+        case 2u: {
+            var offsets = array<vec2<f32>, 4>(
+                vec2<f32>(0.0, -1.0 / 6.0),
+                vec2<f32>(-0.25, 1.0 / 6.0),
+                vec2<f32>(0.25, -7.0 / 18.0),
+                vec2<f32>(-0.375, -1.0 / 18.0),
+            );
+            return offsets[i];
+        }
+        // Rotated grid
+        default: {
+            var offsets = array<vec2<f32>, 4>(
+                vec2<f32>(0.125, 0.375),
+                vec2<f32>(0.375, -0.125),
+                vec2<f32>(-0.125, -0.375),
+                vec2<f32>(-0.375, 0.125),
+            );
+            return offsets[i];
+        }
+    }
+}
+
+@fragment
+fn fs_adaptive_aa(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Size of a pixel in uv space. uv's y axis points up while framebuffer rows go down, which
+    // dpdy accounts for.
+    var pixel = vec2<f32>(dpdx(in.vert_pos.x), dpdy(in.vert_pos.y));
+    var size = vec2<i32>(textureDimensions(input_texture));
+    var p = vec2<i32>(in.clip_position.xy);
+    var center = textureLoad(input_texture, p, 0).xy;
+    if flag(FLAG_BOUNDARY) {
+        // Neighbours past the edge are taken from the other side, like derivatives would be.
+        var right = select(p + vec2<i32>(1, 0), p - vec2<i32>(1, 0), p.x + 1 >= size.x);
+        var below = select(p + vec2<i32>(0, 1), p - vec2<i32>(0, 1), p.y + 1 >= size.y);
+        var color = neighbour_boundary_color(
+            decode_escape(center),
+            decode_escape(textureLoad(input_texture, max(right, vec2<i32>(0)), 0).xy),
+            decode_escape(textureLoad(input_texture, max(below, vec2<i32>(0)), 0).xy),
+        );
+        return vec4<f32>(window_color(color.rgb), 0.0);
+    }
+
+    var neighbours = array<vec2<i32>, 4>(
+        vec2<i32>(-1, 0),
+        vec2<i32>(1, 0),
+        vec2<i32>(0, -1),
+        vec2<i32>(0, 1),
+    );
+    var edge = false;
+    for (var i = 0; i < 4; i++) {
+        var q = clamp(p + neighbours[i], vec2<i32>(0), size - 1);
+        if abs(textureLoad(input_texture, q, 0).x - center.x) > AA_EDGE_THRESHOLD {
+            edge = true;
+        }
+    }
+
+    if !edge {
+        return vec4<f32>(window_color(pixel_color(decode_escape(center), uni.max_its)), 0.0);
+    }
+
+    var color = vec3<f32>(0.0);
+    for (var i = 0; i < 4; i++) {
+        color += shade(in.vert_pos + aa_offset(i) * pixel).rgb;
+    }
+    return vec4<f32>(window_color(color / 4.0), 0.0);
+}
+
+// Displays an image rendered on the CPU, stretched over the viewport.
+@fragment
+fn fs_blit(in: VertexOutput) -> @location(0) vec4<f32> {
+    var size = vec2<f32>(textureDimensions(input_texture));
+    var uv = vec2<f32>(in.vert_pos.x, 1.0 - in.vert_pos.y);
+    var p = min(vec2<i32>(uv * size), vec2<i32>(size) - 1);
+    return vec4<f32>(textureLoad(input_texture, p, 0).rgb, 0.0);
+}
+
+// Like fs_blit, but interpolating bilinearly between texels, for scaling up low resolution
+// previews without blocky edges. The texture isn't filterable, so this does by hand what a
+// sampler would.
+@fragment
+fn fs_blit_smooth(in: VertexOutput) -> @location(0) vec4<f32> {
+    var size = vec2<f32>(textureDimensions(input_texture));
+    var uv = vec2<f32>(in.vert_pos.x, 1.0 - in.vert_pos.y);
+    var p = clamp(uv * size - 0.5, vec2<f32>(0.0), size - 1.0);
+    var p0 = vec2<i32>(p);
+    var p1 = min(p0 + 1, vec2<i32>(size) - 1);
+    var t = fract(p);
+    var top = mix(
+        textureLoad(input_texture, p0, 0).rgb,
+        textureLoad(input_texture, vec2<i32>(p1.x, p0.y), 0).rgb,
+        t.x,
+    );
+    var bottom = mix(
+        textureLoad(input_texture, vec2<i32>(p0.x, p1.y), 0).rgb,
+        textureLoad(input_texture, p1, 0).rgb,
+        t.x,
+    );
+    return vec4<f32>(mix(top, bottom, t.y), 0.0);
+}
+
+// Luminance above which colors start to glow with bloom, fully so at 1.
+const BLOOM_THRESHOLD: f32 = 0.6;
+
+// The bright parts of the view, which bloom blurs and adds back on top of it.
+@fragment
+fn fs_bloom_extract(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = view_color(in).rgb;
+    var luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return vec4<f32>(color * smoothstep(BLOOM_THRESHOLD, 1.0, luminance), 0.0);
+}
+
+// Weights of a 9 tap Gaussian blur, from the center outwards.
+const BLOOM_WEIGHTS = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+// One direction of the separable blur of the bright parts, between textures of the same size.
+fn bloom_blur(p: vec2<i32>, step: vec2<i32>) -> vec4<f32> {
+    // Constant arrays can only be indexed dynamically once they're in a variable.
+    var weights = BLOOM_WEIGHTS;
+    var size = vec2<i32>(textureDimensions(input_texture));
+    var color = textureLoad(input_texture, p, 0).rgb * weights[0];
+    for (var i = 1; i < 5; i++) {
+        var a = clamp(p - step * i, vec2<i32>(0), size - 1);
+        var b = clamp(p + step * i, vec2<i32>(0), size - 1);
+        var pair = textureLoad(input_texture, a, 0).rgb + textureLoad(input_texture, b, 0).rgb;
+        color += pair * weights[i];
+    }
+    return vec4<f32>(color, 0.0);
+}
+
+@fragment
+fn fs_bloom_blur_x(in: VertexOutput) -> @location(0) vec4<f32> {
+    return bloom_blur(vec2<i32>(in.clip_position.xy), vec2<i32>(1, 0));
+}
+
+@fragment
+fn fs_bloom_blur_y(in: VertexOutput) -> @location(0) vec4<f32> {
+    return bloom_blur(vec2<i32>(in.clip_position.xy), vec2<i32>(0, 1));
+}
+
+// Offscreen escape values
+
+// Set on escape values of points detected to be inside the set. Must match
+// `headless::INTERIOR_BIT`.
+const INTERIOR_BIT: u32 = 0x80000000u;
+
+// The part of the image `escapes` holds, as images too large for a single storage buffer are
+// computed a tile at a time.
+struct EscapeTile {
+    // Size of the whole image.
+    size: vec2<u32>,
+    // Top left pixel of the tile, and its size.
+    origin: vec2<u32>,
+    extent: vec2<u32>,
+}
+
+@group(1) @binding(1)
+var<storage, read_write> escapes: array<u32>;
+
+@group(1) @binding(2)
+var<uniform> escape_tile: EscapeTile;
+
+@compute @workgroup_size(8, 8)
+fn cs_escape(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= escape_tile.extent) {
+        return;
+    }
+    // Pixel centers, with uv's y axis pointing up like in the fragment shader.
+    var p = escape_tile.origin + id.xy;
+    var uv = (vec2<f32>(p) + 0.5) / vec2<f32>(escape_tile.size);
+    uv.y = 1.0 - uv.y;
+    var res = escape_at(float2(uv), uni.max_its);
+    var escape = select(res.its, res.its | INTERIOR_BIT, res.interior);
+    escapes[id.y * escape_tile.extent.x + id.x] = escape;
+}
+
+// Progressive rendering
+
+// The orbit of a pixel as far as it has been iterated so far. `z_lo` holds the low order parts
+// of its coordinates with `FLAG_DOUBLE_DOUBLE`.
+struct PixelState {
+    z: float2,
+    z_lo: float2,
+    its: u32,
+    escaped: u32,
+}
+
+struct ProgressiveParams {
+    size: vec2<u32>,
+    // Iterations added to every pixel per dispatch.
+    its_per_pass: u32,
+}
+
+@group(1) @binding(3)
+var<storage, read_write> progressive: array<PixelState>;
+
+@group(1) @binding(4)
+var<uniform> progressive_params: ProgressiveParams;
+
+// Continues iterating every pixel from where the previous dispatch left off. The state buffer
+// is cleared by the host whenever the view changes, which corresponds to z = 0, 0 iterations
+// (Julia sets then start from the pixel instead). Interior detection isn't supported here.
+@compute @workgroup_size(8, 8)
+fn cs_progressive(@builtin(global_invocation_id) id: vec3<u32>) {
+    var size = progressive_params.size;
+    if any(id.xy >= size) {
+        return;
+    }
+    var index = id.y * size.x + id.x;
+    var state = progressive[index];
+    if state.escaped != 0u {
+        return;
+    }
+
+    var uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size);
+    uv.y = 1.0 - uv.y;
+    var end = min(state.its + progressive_params.its_per_pass, uni.max_its);
+    if flag(FLAG_DOUBLE_DOUBLE) && !flag(FLAG_LOG_POLAR) {
+        progressive[index] = progress_extended(uv2coord_extended(float2(uv)), state, end);
+        return;
+    }
+    var orbit = orbit_start(uv2coord(float2(uv)));
+    var c = orbit.c;
+    var z = state.z;
+    if state.its == 0u {
+        z = orbit.z;
+    }
+    var its = state.its;
+    for (; its < end; its++) {
+        if escaped(z) {
+            state.escaped = 1u;
+            break;
+        }
+        z = fold(z);
+        z = float2(
+            z.x*z.x - z.y*z.y + c.x,
+            2.0*z.x*z.y + c.y
+        );
+    }
+    progressive[index] = PixelState(z, float2(0.0), its, state.escaped);
+}
+
+// Continues the orbit of `state` up to `end` iterations in double-double, like
+// `mandelbrot_extended`.
+fn progress_extended(point: ExtendedPoint, state: PixelState, end: u32) -> PixelState {
+    var x = float2(state.z.x, state.z_lo.x);
+    var y = float2(state.z.y, state.z_lo.y);
+    var cx = point.x;
+    var cy = point.y;
+    if flag(FLAG_JULIA) {
+        if state.its == 0u {
+            x = point.x;
+            y = point.y;
+        }
+        cx = float2(uni.julia_c.x, 0.0);
+        cy = float2(uni.julia_c.y, 0.0);
+    }
+    var its = state.its;
+    var done = state.escaped;
+    for (; its < end; its++) {
+        if escaped(float2(x.x, y.x)) {
+            done = 1u;
+            break;
+        }
+        if flag(FLAG_BURNING_SHIP) {
+            x = select(x, -x, x.x < 0.0);
+            y = select(y, -y, y.x < 0.0);
+        }
+        var xx = dd_mul(x, x);
+        var yy = dd_mul(y, y);
+        var xy = dd_mul(x, y);
+        x = dd_add(dd_add(xx, -yy), cx);
+        y = dd_add(dd_add(xy, xy), cy);
+    }
+    return PixelState(float2(x.x, y.x), float2(x.y, y.y), its, done);
+}
+
+// The escape result of a progressively rendered pixel so far, as if it had run out of
+// iterations while it hasn't escaped yet.
+fn progressive_result(p: vec2<u32>) -> EscapeResult {
+    var state = progressive[p.y * progressive_params.size.x + p.x];
+    if state.escaped == 0u {
+        return EscapeResult(uni.max_its, false, f32(uni.max_its));
+    }
+    return EscapeResult(state.its, false, smooth_escape(state.its, state.z));
+}
+
+// Pixels that haven't escaped yet are drawn as interior until proven otherwise.
+@fragment
+fn fs_progressive(in: VertexOutput) -> @location(0) vec4<f32> {
+    var size = progressive_params.size;
+    var uv = vec2<f32>(in.vert_pos.x, 1.0 - in.vert_pos.y);
+    var p = min(vec2<u32>(uv * vec2<f32>(size)), size - 1u);
+    var res = progressive_result(p);
+    if flag(FLAG_BOUNDARY) {
+        // Neighbours past the edge are taken from the other side, like derivatives would be.
+        var right = select(p.x + 1u, max(p.x, 1u) - 1u, p.x + 1u >= size.x);
+        var below = select(p.y + 1u, max(p.y, 1u) - 1u, p.y + 1u >= size.y);
+        var color = neighbour_boundary_color(
+            res,
+            progressive_result(vec2<u32>(right, p.y)),
+            progressive_result(vec2<u32>(p.x, below)),
+        );
+        return vec4<f32>(window_color(color.rgb), 0.0);
+    }
+    var color = uni.interior_color.rgb;
+    if res.its < uni.max_its {
+        color = pixel_color(res, uni.max_its);
+    }
+    return vec4<f32>(window_color(color), 0.0);
+}
@@ -0,0 +1,663 @@
+use crate::core::ExtendedPoint;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MandelbrotUniform {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub height: f64,
+    // width / height, i.e. width = height * aspect_ratio
+    pub aspect_ratio: f64,
+    pub max_iterations: u32,
+    // On/off settings, a combination of the `FLAG_` constants.
+    pub flags: u32,
+    // Counterclockwise rotation of the view around its center, in radians.
+    pub rotation: f64,
+    // Brightness escaped points closest to the set are scaled up to, 1.0 for SDR output.
+    pub hdr_peak: f32,
+    // Escape time between the lines drawn with `FLAG_CONTOURS`.
+    pub contour_interval: f32,
+    // Norm the escape radius is measured in, an index into `NORM_NAMES`.
+    pub norm_type: u32,
+    // Iterations per full cycle through the palette, or 0 to stretch it over `max_iterations`
+    // once.
+    pub color_period: f32,
+    // Distance between the lines drawn with `FLAG_AXES_GRID`.
+    pub grid_spacing: f64,
+    // Where adaptive anti-aliasing places its samples, an index into `AA_PATTERN_NAMES`.
+    pub aa_pattern: u32,
+    // Randomly jittered samples averaged per pixel, 1 to sample only the pixel center.
+    pub samples_per_pixel: u32,
+    // The constant of the Julia set drawn with `FLAG_JULIA`.
+    pub julia_c: [f64; 2],
+    // Linear RGB color of points that don't escape, alpha unused. Detected interior points glow
+    // slightly brighter.
+    pub interior_color: [f32; 4],
+    // The point `FLAG_LOG_POLAR` lays the plane out around.
+    pub log_polar_center: [f64; 2],
+    // Low order parts of `min_x` and `min_y`, which `FLAG_DOUBLE_DOUBLE` adds to them. Kept by
+    // panning and zooming, reset by anything setting the center outright.
+    pub min_lo: [f64; 2],
+    // Radius beyond which orbits count as escaped, in the norm selected by `norm_type`. At least
+    // 2, and raised to `core::SMOOTH_ESCAPE_RADIUS` with `FLAG_SMOOTH_COLORING`.
+    pub escape_radius: f32,
+    // Unused, keeps the size a multiple of 16 bytes.
+    pub padding: [u32; 3],
+}
+
+// The layout has to match `MandelbrotUniform` in `shader.wgsl` byte for byte. WGSL aligns f64 to
+// 8 bytes and u32/f32 to 4, so listing the fields in this order needs no implicit padding.
+const _: () = {
+    use std::mem::{offset_of, size_of};
+    assert!(offset_of!(MandelbrotUniform, min_x) == 0);
+    assert!(offset_of!(MandelbrotUniform, min_y) == 8);
+    assert!(offset_of!(MandelbrotUniform, height) == 16);
+    assert!(offset_of!(MandelbrotUniform, aspect_ratio) == 24);
+    assert!(offset_of!(MandelbrotUniform, max_iterations) == 32);
+    assert!(offset_of!(MandelbrotUniform, flags) == 36);
+    assert!(offset_of!(MandelbrotUniform, rotation) == 40);
+    assert!(offset_of!(MandelbrotUniform, hdr_peak) == 48);
+    assert!(offset_of!(MandelbrotUniform, contour_interval) == 52);
+    assert!(offset_of!(MandelbrotUniform, norm_type) == 56);
+    assert!(offset_of!(MandelbrotUniform, color_period) == 60);
+    assert!(offset_of!(MandelbrotUniform, grid_spacing) == 64);
+    assert!(offset_of!(MandelbrotUniform, aa_pattern) == 72);
+    assert!(offset_of!(MandelbrotUniform, samples_per_pixel) == 76);
+    // WGSL aligns vec2<f64> and vec4<f32> to 16 bytes, which 80 and 96 already are.
+    assert!(offset_of!(MandelbrotUniform, julia_c) == 80);
+    assert!(offset_of!(MandelbrotUniform, interior_color) == 96);
+    assert!(offset_of!(MandelbrotUniform, log_polar_center) == 112);
+    assert!(offset_of!(MandelbrotUniform, min_lo) == 128);
+    assert!(offset_of!(MandelbrotUniform, escape_radius) == 144);
+    // A multiple of 16 bytes, which some backends expect of uniform buffers.
+    assert!(size_of::<MandelbrotUniform>() == 160);
+};
+
+/// [`MandelbrotUniform`] with every `f64` rounded to `f32`, for `shader.wgsl` compiled without
+/// `SHADER_F64`, see [`crate::gpu::ShaderFloat`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct MandelbrotUniformF32 {
+    min_x: f32,
+    min_y: f32,
+    height: f32,
+    aspect_ratio: f32,
+    max_iterations: u32,
+    flags: u32,
+    rotation: f32,
+    hdr_peak: f32,
+    contour_interval: f32,
+    norm_type: u32,
+    color_period: f32,
+    grid_spacing: f32,
+    aa_pattern: u32,
+    samples_per_pixel: u32,
+    julia_c: [f32; 2],
+    interior_color: [f32; 4],
+    log_polar_center: [f32; 2],
+    min_lo: [f32; 2],
+    escape_radius: f32,
+    padding: [u32; 3],
+}
+
+// Same as above, with vec2<f32> aligned to 8 bytes and vec4<f32> to 16.
+const _: () = {
+    use std::mem::{offset_of, size_of};
+    assert!(offset_of!(MandelbrotUniformF32, rotation) == 24);
+    assert!(offset_of!(MandelbrotUniformF32, julia_c) == 56);
+    assert!(offset_of!(MandelbrotUniformF32, interior_color) == 64);
+    assert!(offset_of!(MandelbrotUniformF32, min_lo) == 88);
+    assert!(offset_of!(MandelbrotUniformF32, escape_radius) == 96);
+    assert!(size_of::<MandelbrotUniformF32>() == 112);
+};
+
+impl From<&MandelbrotUniform> for MandelbrotUniformF32 {
+    fn from(uniform: &MandelbrotUniform) -> Self {
+        let vec2 = |v: [f64; 2]| v.map(|x| x as f32);
+        Self {
+            min_x: uniform.min_x as f32,
+            min_y: uniform.min_y as f32,
+            height: uniform.height as f32,
+            aspect_ratio: uniform.aspect_ratio as f32,
+            max_iterations: uniform.max_iterations,
+            // The double-double arithmetic relies on f64 rounding, in f32 it'd only misrender.
+            flags: uniform.flags & !MandelbrotUniform::FLAG_DOUBLE_DOUBLE,
+            rotation: uniform.rotation as f32,
+            hdr_peak: uniform.hdr_peak,
+            contour_interval: uniform.contour_interval,
+            norm_type: uniform.norm_type,
+            color_period: uniform.color_period,
+            grid_spacing: uniform.grid_spacing as f32,
+            aa_pattern: uniform.aa_pattern,
+            samples_per_pixel: uniform.samples_per_pixel,
+            julia_c: vec2(uniform.julia_c),
+            interior_color: uniform.interior_color,
+            log_polar_center: vec2(uniform.log_polar_center),
+            min_lo: vec2(uniform.min_lo),
+            escape_radius: uniform.escape_radius,
+            padding: [0; 3],
+        }
+    }
+}
+
+/// Names of the norms selected by [`MandelbrotUniform::norm_type`], in order. An orbit escapes
+/// once `sqrt(x^2 + y^2)`, `max(|x|, |y|)` or `|x| + |y|` respectively reaches
+/// [`MandelbrotUniform::escape_radius`].
+pub const NORM_NAMES: [&str; 3] = ["euclidean", "max", "sum"];
+
+/// Names of the supersampling patterns selected by [`MandelbrotUniform::aa_pattern`], in order.
+/// All of them take 4 samples: on a grid rotated so no two share a row or column, on an axis
+/// aligned 2x2 grid, or at the first points of the (2, 3) Halton sequence.
+pub const AA_PATTERN_NAMES: [&str; 3] = ["rotated-grid", "grid", "halton"];
+
+/// Bounds of the region containing the whole set, as `(min, max)` corners.
+pub const WHOLE_SET: ([f64; 2], [f64; 2]) = ([-2.5, -1.25], [1.0, 1.25]);
+
+/// Region framed when switching to a Julia set, which always lies within radius 2.
+pub const JULIA_VIEW: ([f64; 2], [f64; 2]) = ([-2.0, -1.5], [2.0, 1.5]);
+
+/// Region framed when starting with the Burning Ship, which lies mostly below the real axis.
+pub const BURNING_SHIP_VIEW: ([f64; 2], [f64; 2]) = ([-2.5, -2.0], [1.5, 1.0]);
+
+/// The fractals a view can show, each selected by flags of [`MandelbrotUniform`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FractalKind {
+    /// The Mandelbrot set itself.
+    #[default]
+    Mandelbrot,
+    /// The Julia set of a point, see [`MandelbrotUniform::FLAG_JULIA`].
+    Julia([f64; 2]),
+    /// The Burning Ship, see [`MandelbrotUniform::FLAG_BURNING_SHIP`].
+    BurningShip,
+}
+
+/// Bits of [`MandelbrotUniform::flags`]. They're mirrored by the `FLAG_` constants in
+/// `shader.wgsl`, so new settings take a bit here and there rather than a field of their own.
+impl MandelbrotUniform {
+    /// Stop iterating once the orbit derivative shows convergence to a cycle, marking the point
+    /// as inside the set.
+    pub const FLAG_INTERIOR_DETECTION: u32 = 1 << 0;
+    /// Draw lines where the smooth escape time crosses a multiple of `contour_interval`.
+    pub const FLAG_CONTOURS: u32 = 1 << 1;
+    /// Soften color bands by averaging over the escape times each pixel covers.
+    pub const FLAG_AA_FWIDTH: u32 = 1 << 2;
+    /// Overlay lines every `grid_spacing` along both axes of the complex plane.
+    pub const FLAG_AXES_GRID: u32 = 1 << 3;
+    /// Tint points that reached `max_iterations` without escaping or being detected as
+    /// interior, which more iterations might still resolve.
+    pub const FLAG_SHOW_UNDERITERATED: u32 = 1 << 4;
+    /// Render the Julia set of `julia_c` instead of the Mandelbrot set, iterating from z = pixel
+    /// rather than adding the pixel as c.
+    pub const FLAG_JULIA: u32 = 1 << 5;
+    /// Leave points that don't escape fully transparent, so the image can be composited over
+    /// other content. Pixels partly inside the set get partial alpha when supersampled.
+    pub const FLAG_TRANSPARENT_INTERIOR: u32 = 1 << 6;
+    /// Encode colors drawn to the window as sRGB in the shader, on top of whatever the surface
+    /// format does. Only meant for debugging colors that look too dark or too bright; saved
+    /// images are always encoded correctly.
+    pub const FLAG_ENCODE_SRGB: u32 = 1 << 7;
+    /// Draw only the boundary of the set, as dark lines on a plain background, instead of
+    /// coloring every point.
+    pub const FLAG_BOUNDARY: u32 = 1 << 8;
+    /// Treat the view as showing log-polar coordinates around `log_polar_center`: x is the log
+    /// of the distance to it and y the angle, so zooming into the center becomes a steady pan
+    /// to the left.
+    pub const FLAG_LOG_POLAR: u32 = 1 << 9;
+    /// Color escaped points by their smooth escape time rather than the integer iteration
+    /// count, which removes the color bands. Orbits are iterated up to a larger escape radius
+    /// then, which keeps the smoothing from leaving steps of its own.
+    pub const FLAG_SMOOTH_COLORING: u32 = 1 << 10;
+    /// Compute the points of pixels and iterate them in double-double, adding `min_lo` to the
+    /// corner of the view, so views past `f64` precision stay sharp. Several times slower, and
+    /// not applied to log-polar views.
+    pub const FLAG_DOUBLE_DOUBLE: u32 = 1 << 11;
+    /// Fold orbits into the first quadrant before squaring them, which draws the Burning Ship
+    /// instead of the Mandelbrot set, or its Julia sets together with `FLAG_JULIA`.
+    pub const FLAG_BURNING_SHIP: u32 = 1 << 12;
+
+    /// Every flag with its name in `shader.wgsl`.
+    pub const FLAGS: [(&'static str, u32); 13] = [
+        ("FLAG_INTERIOR_DETECTION", Self::FLAG_INTERIOR_DETECTION),
+        ("FLAG_CONTOURS", Self::FLAG_CONTOURS),
+        ("FLAG_AA_FWIDTH", Self::FLAG_AA_FWIDTH),
+        ("FLAG_AXES_GRID", Self::FLAG_AXES_GRID),
+        ("FLAG_SHOW_UNDERITERATED", Self::FLAG_SHOW_UNDERITERATED),
+        ("FLAG_JULIA", Self::FLAG_JULIA),
+        ("FLAG_TRANSPARENT_INTERIOR", Self::FLAG_TRANSPARENT_INTERIOR),
+        ("FLAG_ENCODE_SRGB", Self::FLAG_ENCODE_SRGB),
+        ("FLAG_BOUNDARY", Self::FLAG_BOUNDARY),
+        ("FLAG_LOG_POLAR", Self::FLAG_LOG_POLAR),
+        ("FLAG_SMOOTH_COLORING", Self::FLAG_SMOOTH_COLORING),
+        ("FLAG_DOUBLE_DOUBLE", Self::FLAG_DOUBLE_DOUBLE),
+        ("FLAG_BURNING_SHIP", Self::FLAG_BURNING_SHIP),
+    ];
+}
+
+impl MandelbrotUniform {
+    /// The initial view, framing the whole set (see [`WHOLE_SET`]) as tightly as `aspect_ratio`
+    /// allows, in landscape as well as portrait orientation.
+    pub fn default_view(aspect_ratio: f64) -> Self {
+        let mut view = Self {
+            min_x: 0.0,
+            min_y: 0.0,
+            height: 1.0,
+            aspect_ratio,
+            max_iterations: 128,
+            flags: 0,
+            rotation: 0.0,
+            hdr_peak: 1.0,
+            contour_interval: 1.0,
+            norm_type: 0,
+            color_period: 0.0,
+            grid_spacing: 0.0,
+            aa_pattern: 0,
+            samples_per_pixel: 1,
+            julia_c: [0.0; 2],
+            interior_color: [0.0, 0.0, 0.0, 1.0],
+            log_polar_center: [0.0; 2],
+            min_lo: [0.0; 2],
+            escape_radius: 2.0,
+            padding: [0; 3],
+        };
+        view.fit_whole_set();
+        view
+    }
+
+    /// Whether all bits of `flag`, a combination of the `FLAG_` constants, are set.
+    pub fn flag(&self, flag: u32) -> bool {
+        self.flags & flag == flag
+    }
+
+    pub fn set_flag(&mut self, flag: u32, on: bool) {
+        if on {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+
+    /// Flips `flag` and returns whether it's set now.
+    pub fn toggle_flag(&mut self, flag: u32) -> bool {
+        self.flags ^= flag;
+        self.flag(flag)
+    }
+
+    /// Frames the rectangle from `min` to `max` as tightly as the aspect ratio allows, centered
+    /// and unrotated.
+    pub fn fit(&mut self, [min_x, min_y]: [f64; 2], [max_x, max_y]: [f64; 2]) {
+        let (width, height) = (max_x - min_x, max_y - min_y);
+        self.height = height.max(width / self.aspect_ratio);
+        self.rotation = 0.0;
+        self.set_center([(min_x + max_x) / 2.0, (min_y + max_y) / 2.0]);
+    }
+
+    /// Frames the whole set, see [`WHOLE_SET`].
+    pub fn fit_whole_set(&mut self) {
+        let (min, max) = WHOLE_SET;
+        self.fit(min, max);
+    }
+
+    /// Goes back to the region, fractal and iteration limit of `start`, framing its region for
+    /// this view's aspect ratio. Rendering settings like coloring and anti-aliasing are kept.
+    pub fn reset_to(&mut self, start: &Self) {
+        const FRACTAL_FLAGS: u32 = MandelbrotUniform::FLAG_JULIA
+            | MandelbrotUniform::FLAG_BURNING_SHIP
+            | MandelbrotUniform::FLAG_LOG_POLAR;
+        self.flags = self.flags & !FRACTAL_FLAGS | start.flags & FRACTAL_FLAGS;
+        self.julia_c = start.julia_c;
+        self.log_polar_center = start.log_polar_center;
+        self.max_iterations = start.max_iterations;
+        let [x, y] = start.center();
+        let [dx, dy] = [start.width() / 2.0, start.height / 2.0];
+        self.fit([x - dx, y - dy], [x + dx, y + dy]);
+        self.rotation = start.rotation;
+        self.set_center_extended(start.center_extended());
+    }
+
+    pub fn width(&self) -> f64 {
+        self.height * self.aspect_ratio
+    }
+
+    pub fn center(&self) -> [f64; 2] {
+        let [lo_x, lo_y] = self.min_lo;
+        [
+            self.min_x + (lo_x + self.width() / 2.0),
+            self.min_y + (lo_y + self.height / 2.0),
+        ]
+    }
+
+    pub fn set_center(&mut self, [x, y]: [f64; 2]) {
+        self.min_x = x - self.width() / 2.0;
+        self.min_y = y - self.height / 2.0;
+        self.min_lo = [0.0; 2];
+    }
+
+    /// The center with all the bits `min_lo` adds to it.
+    pub fn center_extended(&self) -> ExtendedPoint {
+        let mut center = ExtendedPoint::from_parts([self.min_x, self.min_y], self.min_lo);
+        center.translate([self.width() / 2.0, self.height / 2.0]);
+        center
+    }
+
+    /// Like [`Self::set_center`], but keeping all the bits of `center` in `min_lo`.
+    pub fn set_center_extended(&mut self, center: ExtendedPoint) {
+        let mut min = center;
+        min.translate([-self.width() / 2.0, -self.height / 2.0]);
+        ([self.min_x, self.min_y], self.min_lo) = min.parts();
+    }
+
+    // Moves the corner of the view by `delta`, carrying what doesn't fit into `min_x` and `min_y`
+    // over to `min_lo`.
+    fn translate(&mut self, delta: [f64; 2]) {
+        let mut min = ExtendedPoint::from_parts([self.min_x, self.min_y], self.min_lo);
+        min.translate(delta);
+        ([self.min_x, self.min_y], self.min_lo) = min.parts();
+    }
+
+    // Sets the size of the view to `height` and `aspect_ratio`, moving its center by `delta`.
+    fn resize(&mut self, height: f64, aspect_ratio: f64, [dx, dy]: [f64; 2]) {
+        let (width, old_height) = (self.width(), self.height);
+        self.height = height;
+        self.aspect_ratio = aspect_ratio;
+        self.translate([
+            dx + (width - self.width()) / 2.0,
+            dy + (old_height - self.height) / 2.0,
+        ]);
+    }
+
+    // Rotates an offset from the view's axes onto the complex plane's.
+    fn rotate(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let (sin, cos) = self.rotation.sin_cos();
+        [x * cos - y * sin, x * sin + y * cos]
+    }
+
+    /// Maps a position within the view, with `(0, 0)` being the bottom left and `(1, 1)` the
+    /// top right corner, to the complex plane.
+    pub fn uv_to_complex(&self, u: f64, v: f64) -> [f64; 2] {
+        let point = self.uv_to_plane(u, v);
+        if self.flag(Self::FLAG_LOG_POLAR) {
+            self.from_log_polar(point)
+        } else {
+            point
+        }
+    }
+
+    // Maps a position within the view to the plane the view's region is given in, which is the
+    // complex plane unless `FLAG_LOG_POLAR` is set.
+    fn uv_to_plane(&self, u: f64, v: f64) -> [f64; 2] {
+        let [cx, cy] = self.center();
+        let [dx, dy] = self.offset_to(u, v);
+        [cx + dx, cy + dy]
+    }
+
+    // The offset from the center of the view to the position `(u, v)` within it.
+    fn offset_to(&self, u: f64, v: f64) -> [f64; 2] {
+        self.rotate([(u - 0.5) * self.width(), (v - 0.5) * self.height])
+    }
+
+    /// The part of the view from `min` to `max`, given like the positions of
+    /// [`Self::uv_to_complex`], as a view of its own with the same rotation and settings.
+    pub fn region(&self, [u0, v0]: [f64; 2], [u1, v1]: [f64; 2]) -> Self {
+        let mut region = *self;
+        region.resize(
+            self.height * (v1 - v0),
+            self.aspect_ratio * (u1 - u0) / (v1 - v0),
+            self.offset_to((u0 + u1) / 2.0, (v0 + v1) / 2.0),
+        );
+        region
+    }
+
+    /// The point of the complex plane at log-polar coordinates `[ln(r), angle]` around
+    /// `log_polar_center`.
+    pub fn from_log_polar(&self, [log_r, angle]: [f64; 2]) -> [f64; 2] {
+        let [x, y] = self.log_polar_center;
+        let (sin, cos) = angle.sin_cos();
+        let r = log_r.exp();
+        [x + r * cos, y + r * sin]
+    }
+
+    /// Switches `FLAG_LOG_POLAR` on or off. Switching it on lays the plane out around the center
+    /// of the view, showing every angle and distances up to the view's half height at the right
+    /// edge. Switching it off goes back to that center, zoomed to the distance at the right edge.
+    pub fn toggle_log_polar(&mut self) -> bool {
+        if self.toggle_flag(Self::FLAG_LOG_POLAR) {
+            self.log_polar_center = self.center();
+            let max_log_r = (self.height / 2.0).ln();
+            self.rotation = 0.0;
+            self.height = 2.0 * std::f64::consts::PI;
+            self.set_center([max_log_r - self.width() / 2.0, 0.0]);
+            true
+        } else {
+            let max_log_r = self.min_x + self.width();
+            self.rotation = 0.0;
+            self.height = 2.0 * max_log_r.exp();
+            self.set_center(self.log_polar_center);
+            false
+        }
+    }
+
+    /// Scales the view by `scale` while keeping the point at `(u, v)` in place.
+    pub fn zoom_at(&mut self, u: f64, v: f64, scale: f64) {
+        let [dx, dy] = self.offset_to(u, v);
+        let shift = [dx * (1.0 - scale), dy * (1.0 - scale)];
+        self.resize(self.height * scale, self.aspect_ratio, shift);
+    }
+
+    /// The view a fraction `t` of the way through a zoom from `self` to `end`. The height
+    /// changes geometrically so the zoom speed looks constant, and the center follows it at the
+    /// same pace. Everything but the region, rotation and iteration count is taken from `self`.
+    pub fn zoom_towards(&self, end: &Self, t: f64) -> Self {
+        let height = self.height * (end.height / self.height).powf(t);
+        let progress = if self.height == end.height {
+            t
+        } else {
+            (self.height - height) / (self.height - end.height)
+        };
+        let lerp = |a: f64, b: f64| a + (b - a) * progress;
+        let mut center = self.center_extended();
+        let [dx, dy] = end.center_extended().offset_from(center);
+        center.translate([dx * progress, dy * progress]);
+        let mut uniform = Self {
+            height,
+            rotation: lerp(self.rotation, end.rotation),
+            max_iterations: lerp(self.max_iterations as f64, end.max_iterations as f64).round()
+                as u32,
+            ..*self
+        };
+        uniform.set_center_extended(center);
+        uniform
+    }
+
+    /// Moves the image by `(du, dv)` view widths/heights, e.g. to follow the cursor.
+    pub fn pan(&mut self, du: f64, dv: f64) {
+        let [dx, dy] = self.rotate([du * self.width(), dv * self.height]);
+        self.translate([-dx, -dy]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_view_fits_the_set_in_any_orientation() {
+        let (min, max) = WHOLE_SET;
+        for [width, height] in [[800.0, 600.0], [600.0, 800.0], [1000.0, 100.0]] {
+            let view = MandelbrotUniform::default_view(width / height);
+            let [x0, y0] = view.uv_to_complex(0.0, 0.0);
+            let [x1, y1] = view.uv_to_complex(1.0, 1.0);
+            assert!(
+                x0 <= min[0] && y0 <= min[1],
+                "{width}x{height} crops {min:?}"
+            );
+            assert!(
+                x1 >= max[0] && y1 >= max[1],
+                "{width}x{height} crops {max:?}"
+            );
+            // Tight along one of the axes.
+            let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+            assert!(close(x0, min[0]) || close(y0, min[1]));
+        }
+    }
+
+    #[test]
+    fn regions_cover_their_part_of_the_view() {
+        let mut view = MandelbrotUniform::default_view(2.0);
+        view.rotation = 0.5;
+        let region = view.region([0.5, 0.25], [1.0, 0.5]);
+        for [u, v] in [[0.0, 0.0], [1.0, 1.0], [0.3, 0.6]] {
+            let [x, y] = region.uv_to_complex(u, v);
+            let [ex, ey] = view.uv_to_complex(0.5 + u * 0.5, 0.25 + v * 0.25);
+            assert!((x - ex).abs() < 1e-12 && (y - ey).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn panning_a_rotated_view_follows_the_cursor() {
+        let mut view = MandelbrotUniform::default_view(16.0 / 9.0);
+        view.rotation = 1.0;
+        // Dragging from (0.25, 0.5) to (0.5, 0.75) keeps the grabbed point under the cursor.
+        let grabbed = view.uv_to_complex(0.25, 0.5);
+        view.pan(0.25, 0.25);
+        let [x, y] = view.uv_to_complex(0.5, 0.75);
+        assert!((x - grabbed[0]).abs() < 1e-12 && (y - grabbed[1]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn deep_views_keep_moving_past_f64_precision() {
+        let mut view = MandelbrotUniform::default_view(1.0);
+        view.height = 1e-20;
+        view.set_center([-0.75, 0.1]);
+        let start = view.center_extended();
+        // Every step is far below an ulp of the coordinates.
+        for _ in 0..100 {
+            view.pan(-0.5, 0.25);
+        }
+        view.zoom_at(1.0, 0.5, 0.5);
+        let [dx, dy] = view.center_extended().offset_from(start);
+        assert!((dx - 50.25e-20).abs() < 1e-30 && (dy + 25e-20).abs() < 1e-30);
+        assert_eq!(view.center(), [-0.75, 0.1]);
+    }
+
+    #[test]
+    fn deep_zooms_glide_past_f64_precision() {
+        let mut start = MandelbrotUniform::default_view(1.0);
+        start.height = 1e-20;
+        start.set_center([-0.75, 0.1]);
+        let mut end = start;
+        end.pan(-40.0, 20.0);
+        let offset =
+            |view: &MandelbrotUniform| view.center_extended().offset_from(start.center_extended());
+        let [dx, dy] = offset(&start.zoom_towards(&end, 0.5));
+        assert!((dx - 20e-20).abs() < 1e-30 && (dy + 10e-20).abs() < 1e-30);
+        assert_eq!(offset(&start.zoom_towards(&end, 1.0)), offset(&end));
+
+        let mut centered = start;
+        centered.set_center_extended(end.center_extended());
+        assert_eq!(offset(&centered), offset(&end));
+    }
+
+    #[test]
+    fn log_polar_view_round_trips() {
+        let mut view = MandelbrotUniform::default_view(16.0 / 9.0);
+        view.height = 0.01;
+        view.set_center([-0.75, 0.1]);
+        assert!(view.toggle_log_polar());
+        // The right edge is half a view height away from the old center, at every angle.
+        let [x, y] = view.uv_to_complex(1.0, 0.25);
+        assert!(((x + 0.75).hypot(y - 0.1) - 0.005).abs() < 1e-12);
+        assert!(!view.toggle_log_polar());
+        assert!((view.height - 0.01).abs() < 1e-12);
+        let [cx, cy] = view.center();
+        assert!((cx + 0.75).abs() < 1e-12 && (cy - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn f32_uniform_rounds_every_field() {
+        let mut view = MandelbrotUniform::default_view(16.0 / 9.0);
+        view.set_center([-0.75, 0.1]);
+        view.rotation = 0.5;
+        view.grid_spacing = 0.25;
+        view.julia_c = [-0.8, 0.156];
+        view.log_polar_center = [0.3, -0.4];
+        view.min_lo = [1e-20, -2e-20];
+        view.escape_radius = 8.0;
+        view.flags = MandelbrotUniform::FLAG_JULIA | MandelbrotUniform::FLAG_BOUNDARY;
+        view.set_flag(MandelbrotUniform::FLAG_DOUBLE_DOUBLE, true);
+        let rounded = MandelbrotUniformF32::from(&view);
+        assert_eq!(rounded.min_x, view.min_x as f32);
+        assert_eq!(rounded.min_y, view.min_y as f32);
+        assert_eq!(rounded.height, view.height as f32);
+        assert_eq!(rounded.aspect_ratio, view.aspect_ratio as f32);
+        assert_eq!(rounded.rotation, 0.5);
+        assert_eq!(rounded.grid_spacing, 0.25);
+        assert_eq!(rounded.julia_c, [-0.8, 0.156]);
+        assert_eq!(rounded.log_polar_center, [0.3, -0.4]);
+        assert_eq!(rounded.min_lo, [1e-20, -2e-20]);
+        assert_eq!(rounded.escape_radius, 8.0);
+        assert_eq!(rounded.max_iterations, view.max_iterations);
+        // Left to the f64 shader.
+        assert_eq!(
+            rounded.flags,
+            MandelbrotUniform::FLAG_JULIA | MandelbrotUniform::FLAG_BOUNDARY
+        );
+        assert_eq!(rounded.interior_color, view.interior_color);
+        assert_eq!(rounded.padding, [0; 3]);
+    }
+
+    #[test]
+    fn reset_returns_to_the_start_fractal_and_region() {
+        let mut start = MandelbrotUniform::default_view(1.5);
+        start.set_flag(MandelbrotUniform::FLAG_JULIA, true);
+        start.julia_c = [-0.8, 0.156];
+        start.max_iterations = 500;
+        let (min, max) = JULIA_VIEW;
+        start.fit(min, max);
+
+        let mut view = MandelbrotUniform::default_view(1.5);
+        view.set_flag(MandelbrotUniform::FLAG_BURNING_SHIP, true);
+        view.set_flag(MandelbrotUniform::FLAG_SMOOTH_COLORING, true);
+        view.height = 1e-6;
+        view.set_center([-1.76, -0.03]);
+        view.toggle_log_polar();
+        view.max_iterations = 5000;
+        view.reset_to(&start);
+        assert_eq!(
+            view.flags,
+            MandelbrotUniform::FLAG_JULIA | MandelbrotUniform::FLAG_SMOOTH_COLORING
+        );
+        assert_eq!(view.julia_c, start.julia_c);
+        assert_eq!(view.log_polar_center, start.log_polar_center);
+        assert_eq!(view.max_iterations, 500);
+        assert_eq!(view.center(), start.center());
+        assert_eq!(view.height, start.height);
+
+        // A narrower window still shows all of the start region.
+        let mut narrow = MandelbrotUniform::default_view(0.5);
+        narrow.reset_to(&start);
+        assert_eq!(narrow.center(), start.center());
+        assert!((narrow.width() - start.width()).abs() < 1e-12);
+        assert!(narrow.height > start.height);
+    }
+
+    #[test]
+    fn flags_match_the_shader() {
+        let shader = include_str!("shader.wgsl");
+        let mut seen = 0;
+        for (name, bit) in MandelbrotUniform::FLAGS {
+            assert_eq!(bit.count_ones(), 1, "{name}");
+            assert_eq!(seen & bit, 0, "{name} shares a bit");
+            seen |= bit;
+            let declaration = format!("const {name}: u32 = {bit}u;");
+            assert!(
+                shader.contains(&declaration),
+                "shader.wgsl lacks `{declaration}`"
+            );
+        }
+        // And the shader doesn't declare flags the host doesn't know about.
+        assert_eq!(
+            shader.matches("const FLAG_").count(),
+            MandelbrotUniform::FLAGS.len()
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Renders views on the GPU and compares them to the known good images in `tests/golden`.
+//! Needs a GPU, run with `cargo test -- --ignored`. The same comparison is available from the
+//! command line, e.g. `--compare-to tests/golden/default.png` for the first one. The images are
+//! rendered on the GPU with each test's options plus
+//! `--size 160x120 --out tests/golden/<name>.png`.
+#![cfg(feature = "gui")]
+
+use mandelbrot_gpu::{
+    headless::{Headless, MAX_GOLDEN_ERROR},
+    MandelbrotUniform,
+};
+
+// The golden images are 160x120.
+const ASPECT_RATIO: f64 = 4.0 / 3.0;
+
+fn assert_matches(uniform: &MandelbrotUniform, golden: &str) {
+    let headless = pollster::block_on(Headless::new()).unwrap();
+    let path = format!("{}/tests/golden/{golden}", env!("CARGO_MANIFEST_DIR"));
+    let error = pollster::block_on(headless.compare_to(uniform, &path)).unwrap();
+    assert!(error <= MAX_GOLDEN_ERROR, "{golden} is off by {error}");
+}
+
+#[test]
+#[ignore]
+fn default_view_matches_golden() {
+    assert_matches(
+        &MandelbrotUniform::default_view(ASPECT_RATIO),
+        "default.png",
+    );
+}
+
+/// `--center-x -0.743643887 --center-y 0.131825904 --zoom 100 --iterations 500`.
+#[test]
+#[ignore]
+fn seahorse_valley_matches_golden() {
+    let mut uniform = MandelbrotUniform::default_view(ASPECT_RATIO);
+    uniform.height /= 100.0;
+    uniform.max_iterations = 500;
+    uniform.set_center([-0.743643887, 0.131825904]);
+    assert_matches(&uniform, "seahorse.png");
+}
@@ -0,0 +1,222 @@
+//! Checks the headless renderer's readbacks against the CPU and each other. Needs a GPU, run with
+//! `cargo test -- --ignored`.
+#![cfg(feature = "gui")]
+
+use mandelbrot_gpu::{
+    core::{self, ExtendedPoint},
+    headless::{Headless, INTERIOR_BIT},
+    precision_check, MandelbrotUniform,
+};
+
+#[test]
+#[ignore]
+fn smooth_iterations_match_the_cpu() {
+    let headless = pollster::block_on(Headless::new()).unwrap();
+    // An odd width, so rows don't line up with the readback's row alignment.
+    let (width, height) = (37, 23);
+    let mut uniform = MandelbrotUniform::default_view(width as f64 / height as f64);
+    uniform.max_iterations = 200;
+    for burning_ship in [false, true] {
+        uniform.set_flag(MandelbrotUniform::FLAG_BURNING_SHIP, burning_ship);
+        if burning_ship {
+            uniform.fit([-2.5, -2.0], [1.5, 1.0]);
+        }
+        let field = pollster::block_on(headless.smooth_iterations(&uniform, width, height));
+        assert_eq!(field.len(), (width * height) as usize);
+
+        // Rounding may flip the iteration count of pixels right at a band edge. The Burning
+        // Ship's folds make escape times near its boundary chaotic enough that the pixel
+        // position interpolated in f32 changes them, so it gets more slack.
+        let allowed_percent = if burning_ship { 4 } else { 1 };
+        let mut mismatches = 0;
+        for (i, &gpu) in field.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let u = (x as f64 + 0.5) / width as f64;
+            let v = 1.0 - (y as f64 + 0.5) / height as f64;
+            let point = uniform.uv_to_complex(u, v);
+            let max_its = uniform.max_iterations;
+            let cpu = if burning_ship {
+                core::burning_ship_escape([0.0; 2], point, max_its, core::BAILOUT_SQR)
+                    .map_or(max_its as f64, core::smooth_iterations)
+            } else {
+                core::escape_iterations(point, max_its)
+            };
+            if (gpu as f64 - cpu).abs() > 1e-3 * cpu.max(1.0) {
+                mismatches += 1;
+            }
+        }
+        assert!(
+            mismatches * 100 <= allowed_percent * field.len(),
+            "{mismatches} pixels differ (burning ship: {burning_ship})"
+        );
+    }
+}
+
+#[test]
+#[ignore]
+fn double_double_matches_the_cpu_at_deep_zoom() {
+    let headless = pollster::block_on(Headless::new()).unwrap();
+    // The f32 fallback cannot resolve these pixels at all.
+    if !headless
+        .device()
+        .features()
+        .contains(wgpu::Features::SHADER_F64)
+    {
+        eprintln!("skipping: the adapter has no f64 shader support");
+        return;
+    }
+    let (width, height) = (32, 24);
+    let mut uniform = MandelbrotUniform::default_view(width as f64 / height as f64);
+    uniform.max_iterations = precision_check::DEEP_ITERATIONS;
+    // Pixels far too close together for f64 to tell apart.
+    uniform.height = precision_check::DEEP_PIXEL_SIZE * height as f64;
+    uniform.set_center_extended(ExtendedPoint::new(precision_check::CENTER));
+    uniform.set_flag(MandelbrotUniform::FLAG_DOUBLE_DOUBLE, true);
+    let escapes = headless.escape_values(&uniform, width, height);
+
+    // The shader adds each pixel's offset from the corner of the view to the corner in
+    // double-double.
+    let corner = ExtendedPoint::from_parts([uniform.min_x, uniform.min_y], uniform.min_lo);
+    let size = [uniform.height * uniform.aspect_ratio, uniform.height];
+    let mut mismatches = 0;
+    for (i, &gpu) in escapes.iter().enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let uv = [
+            (x as f64 + 0.5) / width as f64,
+            1.0 - (y as f64 + 0.5) / height as f64,
+        ];
+        let mut point = corner;
+        point.translate([uv[0] * size[0], uv[1] * size[1]]);
+        let cpu = core::escape_extended(point, uniform.max_iterations)
+            .map_or(uniform.max_iterations, |escape| escape.iterations);
+        if gpu & !INTERIOR_BIT != cpu {
+            mismatches += 1;
+        }
+    }
+    // f64 would draw a few flat blocks here.
+    let mut distinct = escapes.clone();
+    distinct.sort_unstable();
+    distinct.dedup();
+    assert!(distinct.len() > 20, "only {} escape times", distinct.len());
+    // Orbits right at the escape radius may still escape an iteration apart.
+    assert!(
+        mismatches * 100 <= escapes.len(),
+        "{mismatches} pixels differ"
+    );
+}
+
+#[test]
+#[ignore]
+fn texture_renders_match_image_renders() {
+    let headless = pollster::block_on(Headless::new()).unwrap();
+    let (width, height) = (37, 23);
+    let mut uniform = MandelbrotUniform::default_view(width as f64 / height as f64);
+    uniform.max_iterations = 200;
+    let texture = headless.create_texture(width, height);
+    headless.render_to_texture(&uniform, &texture.create_view(&Default::default()));
+    let texels = read_texture(&headless, &texture);
+    let image = pollster::block_on(headless.render_image(&uniform, width, height));
+
+    // The image is encoded to 8-bit sRGB, the texture kept linear in half floats.
+    let mut worst = 0;
+    for (texel, pixel) in texels.iter().zip(image.pixels()) {
+        for (&linear, &srgb) in texel.iter().zip(&pixel.0) {
+            let encoded = (linear_to_srgb(linear) * 255.0).round() as i32;
+            worst = worst.max((encoded - srgb as i32).abs());
+        }
+    }
+    assert!(worst <= 2, "channels differ by up to {worst}");
+}
+
+#[test]
+#[ignore]
+fn strips_stitch_into_the_whole_image() {
+    let headless = pollster::block_on(Headless::new()).unwrap();
+    // An even height, so no pixel centers lie on the real axis, where the rounding error of a
+    // strip's corners decides whether points of the antenna escape.
+    let (width, height) = (37, 24);
+    let mut uniform = MandelbrotUniform::default_view(width as f64 / height as f64);
+    uniform.max_iterations = 200;
+    let path = std::env::temp_dir().join(format!("mandelbrot_strips_{}.png", std::process::id()));
+    let mut written = Vec::new();
+    let progress = |rows| written.push(rows);
+    // A strip height that doesn't divide the image, so the last strip is shorter.
+    let saved = headless.save_png_strips(&uniform, [width, height], 5, &path, progress);
+    let saved = pollster::block_on(saved);
+    let stitched = saved.and_then(|()| image::open(&path).map_err(std::io::Error::other));
+    let _ = std::fs::remove_file(&path);
+    let stitched = stitched.unwrap().into_rgb8();
+    assert_eq!(written, [5, 10, 15, 20, 24]);
+
+    let image = pollster::block_on(headless.render_image(&uniform, width, height));
+    // Strips interpolate pixel positions between corners of their own, which may move a pixel
+    // across a band edge.
+    assert_eq!(stitched.dimensions(), image.dimensions());
+    let mismatches = stitched
+        .pixels()
+        .zip(image.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0).any(|(&a, b)| a.abs_diff(b) > 2))
+        .count();
+    assert!(
+        mismatches * 100 <= (width * height) as usize,
+        "{mismatches} pixels differ"
+    );
+}
+
+// Reads back the RGB of a `headless::TEXTURE_FORMAT` texture, row by row.
+fn read_texture(headless: &Headless, texture: &wgpu::Texture) -> Vec<[f32; 3]> {
+    let (width, height) = (texture.width(), texture.height());
+    let row = 8 * width;
+    let padded_row = row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let device = headless.device();
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: u64::from(padded_row * height),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    headless.queue().submit([encoder.finish()]);
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    let data = buffer.slice(..).get_mapped_range();
+    data.chunks_exact(padded_row as usize)
+        .flat_map(|padded| padded[..row as usize].chunks_exact(8))
+        .map(|texel| {
+            [0, 1, 2].map(|c| f16_to_f32(u16::from_le_bytes([texel[2 * c], texel[2 * c + 1]])))
+        })
+        .collect()
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f32::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
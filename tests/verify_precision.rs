@@ -0,0 +1,26 @@
+//! Slow, run with `cargo test -- --ignored`.
+
+use mandelbrot_gpu::precision_check;
+
+#[test]
+#[ignore]
+fn double_double_matches_f64_and_resolves_deeper() {
+    let report = precision_check::verify([64, 48]);
+    assert!(report.passed(), "{report}");
+}
+
+#[test]
+#[ignore]
+fn double_double_zooms_deeper_than_f64() {
+    use mandelbrot_gpu::{core::Precision, precision_check::ZoomLimit};
+    let center = precision_check::CENTER;
+    let limit = |precision| match precision_check::max_zoom(1e-3, 5000, |pixel_size| {
+        precision_check::cpu_probe(precision, center, pixel_size, 5000)
+    }) {
+        ZoomLimit::Blocky(pixel_size) => pixel_size,
+        limit => panic!("{precision} {limit}"),
+    };
+    let (f64_limit, extended_limit) = (limit(Precision::F64), limit(Precision::DoubleDouble));
+    assert!((1e-18..1e-13).contains(&f64_limit), "{f64_limit:e}");
+    assert!(extended_limit < f64_limit * 1e-10, "{extended_limit:e}");
+}